@@ -0,0 +1,1159 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use super::events::{Event, EventBus, PlayerEvent};
+use super::library::chapters::{self, chapter_at, Chapter};
+use super::library::index::{get_songs_by_ids, set_unavailable, Track};
+use super::player::ab_loop::{self, SavedLoop};
+use super::player::audio_backend::{AudioBackend, TrackRange};
+use super::player::equalizer::Equalizer;
+use super::player::output::AudioOutput;
+use super::player::replay_gain::{ReplayGain, ReplayGainMode};
+use super::player::resume::{self, ResumeRules};
+use super::player::sleep_timer::{SleepTimer, SleepTimerMode, SleepTimerStatus};
+use super::player::spectrum::SpectrumAnalyzer;
+use super::player::state::{PlaybackState, SharedState};
+use super::player::stereo::{Crossfeed, StereoSettings};
+use super::playlist::manager::{Playlist, PlaylistManager};
+use super::session::{memory_play_enabled, start_play_enabled, Session};
+use crate::playlist::play_mode;
+
+pub type SharedPlayerController = Arc<Mutex<PlayerController>>;
+
+/// 连续遇到无法播放的曲目时最多自动跳过的数量，超过后停止播放
+const MAX_CONSECUTIVE_SKIPS: usize = 5;
+
+/// 章节开头这段时间内再次后退时跳到上一章，否则回到本章开头
+const CHAPTER_RESTART_THRESHOLD: Duration = Duration::from_secs(3);
+
+/// PlaybackError 事件负载：曲目无法打开或解码
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackError {
+    pub track_id: Option<usize>,
+    pub file_path: String,
+    pub title: Option<String>,
+    pub reason: String,
+}
+
+pub fn new_shared_player_controller(state: SharedState, events: EventBus) -> anyhow::Result<SharedPlayerController> {
+    Ok(Arc::new(Mutex::new(PlayerController::new(state, events)?)))
+}
+
+/// 在独立线程上响应播放器自己发布的事件：曲目播完后播放下一首（睡眠定时要求停止时除外），
+/// 无缝切换后前移播放列表，输出设备丢失后重新打开
+pub fn handle_player_events(controller: &SharedPlayerController, events: &EventBus) {
+    let controller = controller.clone();
+    let mut receiver = events.subscribe();
+    std::thread::spawn(move || loop {
+        let event = match receiver.blocking_recv() {
+            Ok(Event::Player(event)) => event,
+            Ok(_) => continue,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("player event handler lagged, {} events skipped", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let mut controller = controller.lock().unwrap_or_else(|e| e.into_inner());
+        match event {
+            PlayerEvent::TrackEnded => {
+                tracing::info!("track ended");
+                controller.finish_resumable();
+                if controller.stop_for_sleep_timer_at_track_end() {
+                    continue;
+                }
+                match controller.play_next() {
+                    Some(track) => tracing::info!("Auto-play next track: {:?}", track.title),
+                    None => tracing::info!("No next track available, playlist ended."),
+                }
+            }
+            PlayerEvent::TrackChanged(_) => match controller.advance_to_queued() {
+                Some(track) => tracing::info!("Gapless advanced to track: {:?}", track.title),
+                None => tracing::info!("No queued track to advance to."),
+            },
+            PlayerEvent::OutputDeviceLost => {
+                if let Err(e) = controller.recover_output_device() {
+                    tracing::error!("Failed to recover output device: {}", e);
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+pub struct PlayerController {
+    pub playlist_manager: PlaylistManager,
+    backend: AudioBackend,
+    state: SharedState,
+    pending_position: Option<(Option<usize>, Duration)>, // 恢复会话后首次播放的 (曲目 id, 位置)
+    shut_down: bool, // shutdown 已保存会话并清空状态，之后不再覆盖
+    sleep_timer: SleepTimer,
+    resume_rules: ResumeRules,
+    resumable: Option<usize>, // 正在播放且需要记忆位置的曲目 id
+    events: EventBus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayMode {
+    Single,
+    Queue
+}
+
+impl PlayerController {
+    pub fn new(state: SharedState, events: EventBus) -> anyhow::Result<Self> {
+        let backend = AudioBackend::new(state.clone(), events.clone())?;
+        Ok(Self::with_backend(state, backend, events))
+    }
+
+    /// 使用给定的音频输出，供无声卡环境下的测试使用
+    pub fn with_output(state: SharedState, output: Box<dyn AudioOutput>, events: EventBus) -> Self {
+        let backend = AudioBackend::with_output(state.clone(), output, events.clone());
+        Self::with_backend(state, backend, events)
+    }
+
+    fn with_backend(state: SharedState, backend: AudioBackend, events: EventBus) -> Self {
+        let playlist = Playlist::new();
+        let playlist_manager = PlaylistManager::new(playlist);
+        let sleep_timer = SleepTimer::new(backend.sleep_fader(), events.clone());
+        Self {
+            playlist_manager,
+            backend,
+            state,
+            pending_position: None,
+            shut_down: false,
+            sleep_timer,
+            resume_rules: ResumeRules::load(),
+            resumable: None,
+            events,
+        }
+    }
+
+    pub fn play_to_playlist(&mut self, tracks: Vec<Track>, play_mode: PlayMode) -> anyhow::Result<()> {
+        match play_mode {
+            PlayMode::Single => {
+                // 原逻辑：插入单首到下一首并播放
+                let Some(track) = tracks.into_iter().next() else { return Ok(()); };
+                self.playlist_manager.insert_track_to_current_next(track)
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                // self.playlist_manager.next_track();
+                self.play();
+                // 新增：同步播放列表到状态
+                self.sync_all_to_state();
+            }
+            PlayMode::Queue => {
+                // 原逻辑：覆盖播放列表
+                let mut playlist = Playlist::new();
+                playlist.tracks = tracks;
+                self.playlist_manager.overwrite_playlist(&playlist);
+                self.playlist_manager.next_track();
+                // 新增：同步播放列表和模式到状态
+                self.sync_all_to_state();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn play_from<P: AsRef<Path>>(&mut self, path: P, position: Duration) -> anyhow::Result<()> {
+        self.backend.load_and_play(path, position, ReplayGain::default(), TrackRange::default())
+    }
+    /// 播放当前曲目；无法播放时发布 PlaybackError 并自动跳到下一首，
+    /// 单曲循环或连续跳过达到上限时停止
+    pub fn play(&mut self)  {
+        self.shut_down = false;
+        self.save_resume_position();
+        self.resumable = None;
+
+        // 恢复会话后首次播放同一首曲目时从会话的位置继续，否则从曲目记忆的位置继续
+        let current_id = self.playlist_manager.get_current_track().map(|t| t.id);
+        let mut position = match self.pending_position.take() {
+            Some((id, position)) if current_id == Some(id) && !position.is_zero() => Some(position),
+            _ => None,
+        };
+
+        let mut skipped = 0;
+        loop {
+            let resumed = position.is_none().then(|| self.saved_position_of_current()).flatten();
+            let result = self.playlist_manager.play_at(&mut self.backend, position.or(resumed).unwrap_or_default());
+            let Err(e) = result else {
+                let current = self.playlist_manager.get_current_track();
+                if resumed.is_some() || current.is_some_and(|t| self.resume_rules.applies_to(t)) {
+                    self.resumable = current.and_then(|t| t.id);
+                }
+                break;
+            };
+            self.report_playback_error(&e);
+            skipped += 1;
+            let limit = MAX_CONSECUTIVE_SKIPS.min(self.playlist_manager.playlist.tracks.len());
+            if self.playlist_manager.play_mode == play_mode::PlayMode::Single
+                || skipped >= limit
+                || self.playlist_manager.next_track().is_none()
+            {
+                tracing::warn!("Stopped after {} unplayable track(s)", skipped);
+                self.stop();
+                break;
+            }
+            position = None;
+        }
+
+        if skipped == 0 {
+            self.mark_current_available();
+        } else {
+            self.sync_all_to_state();
+        }
+        self.load_chapters();
+        self.hold_queue();
+    }
+
+    /// 发布 PlaybackError，并在播放列表与曲库中把当前曲目标记为不可用
+    fn report_playback_error(&mut self, error: &anyhow::Error) {
+        let Some(track) = self.playlist_manager.set_current_unavailable(true) else { return; };
+        tracing::warn!("Failed to play {}: {:#}", track.file_path, error);
+        if let Some(id) = track.id {
+            if let Err(e) = set_unavailable(id, true) {
+                tracing::warn!("Failed to flag track {} as unavailable: {}", id, e);
+            }
+        }
+        self.events.publish(PlayerEvent::PlaybackError(PlaybackError {
+            track_id: track.id,
+            file_path: track.file_path.clone(),
+            title: track.title.clone(),
+            reason: format!("{:#}", error),
+        }));
+    }
+
+    /// 之前标记为不可用的曲目再次播放成功时清除标记
+    fn mark_current_available(&mut self) {
+        if !self.playlist_manager.get_current_track().is_some_and(|t| t.unavailable) {
+            return;
+        }
+        if let Some(id) = self.playlist_manager.set_current_unavailable(false).and_then(|t| t.id) {
+            if let Err(e) = set_unavailable(id, false) {
+                tracing::warn!("Failed to clear unavailable flag of track {}: {}", id, e);
+            }
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.playlist_manager.pause(&mut self.backend);
+        self.save_resume_position();
+    }
+
+    pub fn resume(&mut self) {
+        self.playlist_manager.resume(&mut self.backend);
+    }
+
+    pub fn stop(&mut self) {
+        self.save_resume_position();
+        self.resumable = None;
+        self.playlist_manager.stop(&mut self.backend);
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).set_chapters(Vec::new());
+    }
+    
+    pub fn next_track(&mut self) {
+        self.playlist_manager.next_track();
+    }
+    
+    pub fn previous_track(&mut self) {
+        self.playlist_manager.previous_track();
+    }
+
+    /// 按播放模式切到下一首并播放，返回实际播放的曲目（跳过无法播放的曲目后）
+    pub fn play_next(&mut self) -> Option<Track> {
+        self.playlist_manager.next_track()?;
+        self.play();
+        if self.is_playing() {
+            self.playlist_manager.get_current_track().cloned()
+        } else {
+            None
+        }
+    }
+
+    pub fn set_volume(&mut self, v: f32) {
+        self.playlist_manager.set_volume(&mut self.backend, v);
+    }
+
+    pub fn seek(&mut self, pos: Duration) -> anyhow::Result<()> {
+        self.playlist_manager.seek(&mut self.backend, pos)
+    }
+
+    pub fn set_fade_duration(&mut self, duration: Duration) {
+        self.backend.set_fade_duration(duration);
+    }
+
+    pub fn set_crossfade(&mut self, duration: Duration) {
+        self.backend.set_crossfade(duration);
+    }
+
+    pub fn set_replay_gain_mode(&mut self, mode: ReplayGainMode) {
+        self.backend.set_replay_gain_mode(mode);
+    }
+
+    pub fn replay_gain_mode(&self) -> ReplayGainMode {
+        self.backend.replay_gain_mode()
+    }
+
+    pub fn set_playback_rate(&mut self, rate: f32) -> anyhow::Result<()> {
+        self.backend.set_playback_rate(rate)
+    }
+
+    pub fn set_preserve_pitch(&mut self, enabled: bool) {
+        self.backend.set_preserve_pitch(enabled);
+    }
+
+    pub fn set_ab_loop(&mut self, start: Duration, end: Duration) -> anyhow::Result<()> {
+        self.backend.set_ab_loop(start, end)
+    }
+
+    pub fn clear_ab_loop(&mut self) {
+        self.backend.clear_ab_loop();
+    }
+
+    /// 以给定名称保存当前曲目上正在生效的 A–B 循环
+    pub fn save_ab_loop(&mut self, name: &str) -> anyhow::Result<SavedLoop> {
+        let track_id = self.current_track_id()
+            .ok_or_else(|| anyhow::anyhow!("cannot save loop: current track is not in the library"))?;
+        let (start, end) = self.state.lock().unwrap_or_else(|e| e.into_inner()).ab_loop()
+            .ok_or_else(|| anyhow::anyhow!("cannot save loop: no A-B loop is set"))?;
+        Ok(ab_loop::save_loop(track_id, name, start, end)?)
+    }
+
+    /// 在当前曲目上恢复已保存的循环
+    pub fn apply_saved_loop(&mut self, id: i64) -> anyhow::Result<()> {
+        let saved = ab_loop::get_loop(id)?
+            .ok_or_else(|| anyhow::anyhow!("saved loop {id} not found"))?;
+        if self.current_track_id() != Some(saved.track_id) {
+            return Err(anyhow::anyhow!("saved loop {id} belongs to another track"));
+        }
+        self.backend.set_ab_loop(saved.start(), saved.end())
+    }
+
+    fn current_track_id(&self) -> Option<usize> {
+        self.playlist_manager.get_current_track().and_then(|t| t.id)
+    }
+
+    pub fn equalizer(&self) -> Arc<Equalizer> {
+        self.backend.equalizer()
+    }
+
+    pub fn spectrum(&self) -> Arc<SpectrumAnalyzer> {
+        self.backend.spectrum()
+    }
+
+    pub fn stereo_image(&self) -> StereoSettings {
+        self.backend.stereo_image()
+    }
+
+    pub fn set_balance(&mut self, balance: f32) -> anyhow::Result<()> {
+        self.backend.set_balance(balance)
+    }
+
+    pub fn set_mono(&mut self, enabled: bool) {
+        self.backend.set_mono(enabled);
+    }
+
+    pub fn set_crossfeed(&mut self, crossfeed: Crossfeed) {
+        self.backend.set_crossfeed(crossfeed);
+    }
+
+    pub fn set_output_device(&mut self, name: Option<String>) -> anyhow::Result<()> {
+        self.backend.set_output_device(name)
+    }
+
+    pub fn output_device(&self) -> Option<String> {
+        self.backend.output_device()
+    }
+
+    pub fn recover_output_device(&mut self) -> anyhow::Result<()> {
+        self.backend.recover_output_device()
+    }
+
+    pub fn fade_settings(&self) -> (Duration, Duration) {
+        (self.backend.fade_duration(), self.backend.crossfade())
+    }
+
+    pub fn snapshot(&self) -> (PlaybackState, f32, Duration, Option<Duration>, Option<String>) {
+        let s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        (
+            s.playback_state(),
+            s.volume(),
+            s.current_position(),
+            s.total_duration(),
+            s.current_file().map(|s| s.to_string()),
+        )
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).is_playing()
+    }
+
+    pub fn shutdown(&mut self) {
+        self.save_session();
+        self.save_resume_position();
+        self.shut_down = true;
+        self.backend.spectrum().disable();
+        self.playlist_manager.shutdown(&mut self.backend);
+    }
+
+    /// 保存当前播放列表、索引、模式、位置与音量
+    pub fn save_session(&self) {
+        if self.shut_down {
+            return;
+        }
+        let ids = |tracks: &[Track]| tracks.iter().filter_map(|t| t.id).collect::<Vec<_>>();
+        let (position, volume) = {
+            let s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let position = match self.pending_position {
+                Some((_, position)) => position,
+                None if s.current_file().is_some() => s.current_position(),
+                None => Duration::ZERO,
+            };
+            (position, s.volume())
+        };
+        let tracks = &self.playlist_manager.get_playlist().tracks;
+        // 没有 id 的曲目不会被保存，按 id 重新定位当前曲目
+        let current_index = self.playlist_manager.get_current_track()
+            .and_then(|current| current.id)
+            .and_then(|id| tracks.iter().filter(|t| t.id.is_some()).position(|t| t.id == Some(id)));
+
+        Session {
+            track_ids: ids(tracks),
+            original_track_ids: ids(self.playlist_manager.original_tracks()),
+            current_index,
+            play_mode: self.playlist_manager.play_mode,
+            position_ms: position.as_millis() as u64,
+            volume,
+        }.save();
+    }
+
+    /// 启动时恢复上次会话；start_play 开启时自动播放，memory_play 开启时从上次位置继续
+    pub fn restore_session(&mut self) -> anyhow::Result<()> {
+        let Some(session) = Session::load() else { return Ok(()); };
+
+        let tracks = get_songs_by_ids(&session.track_ids)?;
+        let original_tracks = if session.original_track_ids.is_empty() {
+            tracks.clone()
+        } else {
+            get_songs_by_ids(&session.original_track_ids)?
+        };
+        // 部分曲目可能已从曲库删除，按 id 重新定位当前曲目
+        let current_id = session.current_index.and_then(|i| session.track_ids.get(i)).copied();
+        let current_index = current_id.and_then(|id| tracks.iter().position(|t| t.id == Some(id)));
+
+        self.playlist_manager.restore(tracks, original_tracks, current_index, session.play_mode);
+        self.backend.set_volume(session.volume);
+        self.sync_all_to_state();
+        self.load_chapters();
+
+        let Some(track) = self.playlist_manager.get_current_track().cloned() else { return Ok(()); };
+        let position = if memory_play_enabled() {
+            Duration::from_millis(session.position_ms)
+        } else {
+            Duration::ZERO
+        };
+
+        if start_play_enabled() {
+            self.pending_position = Some((track.id, position));
+            self.play();
+        } else {
+            let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            s.set_current_position(position);
+            s.set_total_duration(Some(Duration::from_secs(track.duration as u64)));
+            self.pending_position = Some((track.id, position));
+        }
+        Ok(())
+    }
+
+    pub fn overwrite_playlist(&mut self, playlist: &Playlist) {
+        // 调用PlaylistManager的方法修改播放列表
+        self.playlist_manager.overwrite_playlist(playlist);
+        // 同步到SharedState
+        self.sync_all_to_state();
+        self.requeue_next();
+    }
+
+    pub fn set_play_mode(&mut self, new_mode: play_mode::PlayMode) {
+        // 调用PlaylistManager的方法修改播放模式（可能会改变播放列表顺序）
+        self.playlist_manager.set_play_mode(new_mode);
+        // 同步播放模式和播放列表到SharedState（因为切换模式可能打乱列表）
+        self.sync_all_to_state();
+        self.requeue_next();
+    }
+
+    pub fn insert_track_at(&mut self, position: usize, track: Track) -> Result<(), String> {
+        let result = self.playlist_manager.insert_at(position, track);
+        // 无论插入成功与否，只要可能修改了列表，就同步状态
+        if result.is_ok() {
+            self.sync_all_to_state();
+            self.requeue_next();
+        }
+        result
+    }
+
+    pub fn remove_track_at(&mut self, position: usize) -> Result<(), String> {
+        let result = self.playlist_manager.remove_at(position);
+        if result.is_ok() {
+            self.sync_all_to_state();
+            self.requeue_next();
+        }
+        result
+    }
+
+    pub fn clear_playlist(&mut self) {
+        self.playlist_manager.clear();
+        self.sync_all_to_state();
+        self.requeue_next();
+    }
+
+    /// 后端已无缝切换到预排曲目：前移索引并预排再下一首
+    pub fn advance_to_queued(&mut self) -> Option<Track> {
+        self.finish_resumable();
+        let track = self.playlist_manager.advance_to_queued().cloned();
+        self.sync_all_to_state();
+        self.load_chapters();
+        if let Some(track) = &track {
+            if self.resume_rules.applies_to(track) {
+                self.resumable = track.id;
+            }
+            self.playlist_manager.queue_next(&mut self.backend);
+            self.hold_queue();
+        }
+        track
+    }
+
+    /// 当前曲目的章节
+    pub fn chapters(&self) -> Vec<Chapter> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).chapters().to_vec()
+    }
+
+    pub fn next_chapter(&mut self) -> anyhow::Result<Chapter> {
+        let next = {
+            let s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let position = s.current_position();
+            s.chapters().iter().find(|c| c.start() > position).cloned()
+        };
+        let next = next.ok_or_else(|| anyhow::anyhow!("no next chapter"))?;
+        self.seek(next.start())?;
+        Ok(next)
+    }
+
+    /// 回到本章开头；已在开头附近时跳到上一章
+    pub fn previous_chapter(&mut self) -> anyhow::Result<Chapter> {
+        let target = {
+            let s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let position = s.current_position();
+            let chapters = s.chapters();
+            match chapter_at(chapters, position) {
+                Some(current) if current.index > 0 && position.saturating_sub(current.start()) < CHAPTER_RESTART_THRESHOLD => {
+                    chapters.get(current.index - 1).cloned()
+                }
+                Some(current) => Some(current.clone()),
+                None => chapters.first().cloned(),
+            }
+        };
+        let target = target.ok_or_else(|| anyhow::anyhow!("current track has no chapters"))?;
+        self.seek(target.start())?;
+        Ok(target)
+    }
+
+    pub fn seek_to_chapter(&mut self, index: usize) -> anyhow::Result<Chapter> {
+        let chapter = self.state.lock().unwrap_or_else(|e| e.into_inner()).chapters().get(index).cloned()
+            .ok_or_else(|| anyhow::anyhow!("chapter {index} not found"))?;
+        self.seek(chapter.start())?;
+        Ok(chapter)
+    }
+
+    /// 读取当前曲目的章节供导航与状态快照使用；CUE 虚拟曲目没有章节
+    fn load_chapters(&mut self) {
+        let id = self.playlist_manager.get_current_track()
+            .filter(|t| t.start_offset.is_none())
+            .and_then(|t| t.id);
+        let chapters = match id {
+            Some(id) => chapters::get_chapters(id).unwrap_or_else(|e| {
+                tracing::warn!("Failed to load chapters of track {}: {}", id, e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).set_chapters(chapters);
+    }
+
+    pub fn resume_rules(&self) -> ResumeRules {
+        self.resume_rules.clone()
+    }
+
+    /// 修改记忆位置的规则；正在播放的曲目按新规则决定是否继续记忆
+    pub fn set_resume_rules(&mut self, rules: ResumeRules) -> anyhow::Result<()> {
+        rules.save()?;
+        self.resume_rules = rules;
+        if self.is_playing() {
+            self.resumable = self.playlist_manager.get_current_track()
+                .filter(|t| self.resume_rules.applies_to(t))
+                .and_then(|t| t.id);
+        }
+        self.requeue_next();
+        Ok(())
+    }
+
+    /// 跳到曲目记忆的位置继续播放；曲目不在播放列表中时插入到当前曲目之后
+    pub fn jump_to_resume_position(&mut self, track_id: usize) -> anyhow::Result<Option<Track>> {
+        let position = resume::get_position(track_id)?
+            .ok_or_else(|| anyhow::anyhow!("no saved position for track {track_id}"))?;
+        let loaded = self.state.lock().unwrap_or_else(|e| e.into_inner()).current_file().is_some();
+        if loaded && self.resumable == Some(track_id) {
+            self.seek(position)?;
+            return Ok(self.playlist_manager.get_current_track().cloned());
+        }
+
+        let index = self.playlist_manager.get_playlist().tracks.iter().position(|t| t.id == Some(track_id));
+        match index {
+            Some(index) => {
+                self.playlist_manager.set_current_index(index).map_err(|e| anyhow::anyhow!(e))?;
+                self.play();
+                self.sync_all_to_state();
+            }
+            None => {
+                let track = get_songs_by_ids(&[track_id])?.into_iter().next()
+                    .ok_or_else(|| anyhow::anyhow!("track {track_id} not found"))?;
+                self.play_to_playlist(vec![track], PlayMode::Single)?;
+            }
+        }
+        Ok(self.playlist_manager.get_current_track().cloned().filter(|_| self.is_playing()))
+    }
+
+    /// 记下正在播放的曲目的位置，供下次播放时继续
+    pub fn save_resume_position(&self) {
+        // shutdown 时已保存，之后播放已停止，不再覆盖
+        if self.shut_down {
+            return;
+        }
+        let Some(id) = self.resumable else { return; };
+        let (position, total) = {
+            let s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            (s.current_position(), s.total_duration())
+        };
+        if let Err(e) = resume::save_position(id, position, total) {
+            tracing::warn!("Failed to save resume position of track {}: {}", id, e);
+        }
+    }
+
+    /// 正在播放的曲目已播完，清除记忆的位置
+    fn finish_resumable(&mut self) {
+        let Some(id) = self.resumable.take() else { return; };
+        if let Err(e) = resume::clear_position(id) {
+            tracing::warn!("Failed to clear resume position of track {}: {}", id, e);
+        }
+    }
+
+    /// 当前曲目记忆的位置；记录由规则产生，规则变化后仍保留到听完或被清除
+    fn saved_position_of_current(&self) -> Option<Duration> {
+        let id = self.playlist_manager.get_current_track()?.id?;
+        resume::get_position(id)
+            .map_err(|e| tracing::warn!("Failed to read resume position: {}", e))
+            .ok()
+            .flatten()
+    }
+
+    /// 播放列表变动后重新预排下一首，保证无缝衔接到正确的曲目
+    pub fn requeue_next(&mut self) {
+        let is_loaded = self.state.lock().unwrap_or_else(|e| e.into_inner()).current_file().is_some();
+        if is_loaded {
+            self.playlist_manager.queue_next(&mut self.backend);
+            self.hold_queue();
+        }
+    }
+
+    pub fn set_sleep_timer(&mut self, mode: SleepTimerMode) -> anyhow::Result<()> {
+        self.sleep_timer.set(mode)?;
+        // 从“曲目/列表结束”切回计时模式时需要恢复预排
+        self.requeue_next();
+        self.tick_sleep_timer();
+        Ok(())
+    }
+
+    pub fn extend_sleep_timer(&mut self, minutes: u32) -> anyhow::Result<()> {
+        self.sleep_timer.extend(minutes)?;
+        self.tick_sleep_timer();
+        Ok(())
+    }
+
+    pub fn cancel_sleep_timer(&mut self) {
+        self.sleep_timer.cancel();
+        self.requeue_next();
+        self.sleep_timer.emit(None);
+    }
+
+    pub fn sleep_timer_status(&self) -> SleepTimerStatus {
+        self.sleep_timer.status(self.sleep_timer_remaining())
+    }
+
+    /// 每秒由定时任务调用：推进淡出、通知前端，Minutes 模式到期时停止播放
+    pub fn tick_sleep_timer(&mut self) {
+        let remaining = self.sleep_timer_remaining();
+        if self.sleep_timer.update(remaining) {
+            tracing::info!("Sleep timer expired, stopping playback");
+            self.stop();
+            self.sleep_timer.finish();
+        }
+    }
+
+    /// 曲目自然播完时调用；睡眠定时要求在此停止时停止播放并返回 true
+    pub fn stop_for_sleep_timer_at_track_end(&mut self) -> bool {
+        if !self.sleep_timer_stops_after_current() {
+            return false;
+        }
+        tracing::info!("Sleep timer reached end of {:?}, stopping playback", self.sleep_timer.mode());
+        self.stop();
+        self.sleep_timer.finish();
+        true
+    }
+
+    fn sleep_timer_stops_after_current(&self) -> bool {
+        match self.sleep_timer.mode() {
+            Some(SleepTimerMode::EndOfTrack) => true,
+            Some(SleepTimerMode::EndOfQueue) => {
+                let len = self.playlist_manager.get_playlist().tracks.len();
+                self.playlist_manager.current_index.is_none_or(|i| i + 1 >= len)
+            }
+            _ => false,
+        }
+    }
+
+    /// 估算距离睡眠定时生效的时间
+    fn sleep_timer_remaining(&self) -> Option<Duration> {
+        match self.sleep_timer.mode()? {
+            SleepTimerMode::Minutes { .. } => self.sleep_timer.deadline_remaining(),
+            SleepTimerMode::EndOfTrack => self.backend.track_remaining(),
+            SleepTimerMode::EndOfQueue => {
+                let current = self.backend.track_remaining()?;
+                let rest: u64 = self.playlist_manager.current_index
+                    .map(|i| self.playlist_manager.get_playlist().tracks.iter().skip(i + 1).map(|t| t.duration as u64).sum())
+                    .unwrap_or(0);
+                Some(current + Duration::from_secs(rest))
+            }
+        }
+    }
+
+    /// 睡眠定时要在当前曲目结束时停止，或下一首要从记忆的位置继续：
+    /// 撤销预排，让曲目播完后自然结束，再由 TrackEnded 决定停止或播放下一首
+    fn hold_queue(&mut self) {
+        let next_resumes = self.playlist_manager.queued_index
+            .and_then(|i| self.playlist_manager.get_playlist().tracks.get(i))
+            .and_then(|t| t.id)
+            .is_some_and(|id| resume::get_position(id).is_ok_and(|p| p.is_some()));
+        if self.sleep_timer_stops_after_current() || next_resumes {
+            self.backend.clear_queued();
+            self.playlist_manager.queued_index = None;
+        }
+    }
+
+
+    // 辅助方法：同步所有相关数据到SharedState
+    fn sync_all_to_state(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.set_current_playlist(self.playlist_manager.get_playlist().clone());
+        state.set_current_track(self.playlist_manager.get_current_track().cloned());
+        state.set_current_play_mode(self.playlist_manager.play_mode);
+        state.set_current_index(self.playlist_manager.current_index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use super::*;
+    use crate::database::tests::isolate_app_data;
+    use crate::player::output::tests::{read_wav, write_constant_wav};
+    use crate::library::index::PathType;
+    use crate::player::output::{NullOutput, WavFileOutput};
+    use crate::player::remote::tests::serve;
+    use crate::player::state::new_shared_state;
+
+    const WAIT: Duration = Duration::from_secs(20);
+
+    fn controller(events: &EventBus, output: Box<dyn AudioOutput>) -> PlayerController {
+        isolate_app_data();
+        PlayerController::with_output(new_shared_state(), output, events.clone())
+    }
+
+    fn track(path: &Path, duration: Duration) -> Track {
+        let mut track = Track::new();
+        track.file_path = path.to_string_lossy().to_string();
+        track.duration = duration.as_secs() as u32;
+        track
+    }
+
+    /// 在临时目录生成恒定电平的曲目
+    fn fixtures(dir: &Path, tracks: &[(f32, Duration)]) -> Vec<PathBuf> {
+        tracks.iter().enumerate()
+            .map(|(i, &(level, duration))| {
+                let path = dir.join(format!("track{i}.wav"));
+                write_constant_wav(&path, level, duration);
+                path
+            })
+            .collect()
+    }
+
+    /// 收到满足条件的播放器事件时通知测试线程
+    fn on_event(events: &EventBus, matches: fn(&PlayerEvent) -> bool) -> mpsc::Receiver<()> {
+        let (tx, rx) = mpsc::channel();
+        let mut receiver = events.subscribe();
+        std::thread::spawn(move || loop {
+            match receiver.blocking_recv() {
+                Ok(Event::Player(event)) if matches(&event) => {
+                    if tx.send(()).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        });
+        rx
+    }
+
+    #[test]
+    fn playlist_renders_gaplessly_to_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let second = Duration::from_secs(2);
+        let paths = fixtures(dir.path(), &[(0.25, second), (0.5, second)]);
+        let out = dir.path().join("out.wav");
+
+        let events = EventBus::new();
+        let ended = on_event(&events, |e| matches!(e, PlayerEvent::TrackEnded));
+        let changed = on_event(&events, |e| matches!(e, PlayerEvent::TrackChanged(_)));
+        let mut controller = controller(&events, Box::new(WavFileOutput::create(&out, 2, 44100).unwrap()));
+        let tracks = paths.iter().map(|p| track(p, second)).collect();
+        controller.play_to_playlist(tracks, PlayMode::Queue).unwrap();
+        controller.play();
+
+        changed.recv_timeout(WAIT).expect("track-changed not emitted");
+        ended.recv_timeout(WAIT).expect("track-ended not emitted");
+        let (state, _, _, _, file) = controller.snapshot();
+        assert_eq!(state, PlaybackState::Stopped);
+        assert_eq!(file.as_deref(), Some(paths[1].to_string_lossy().as_ref()));
+        drop(controller);
+
+        // 去掉首尾的静音后应恰好是两首曲目首尾相接，中间没有空隙
+        let (_, _, samples) = read_wav(&out);
+        let first = samples.iter().position(|&s| s != 0.0).unwrap();
+        let last = samples.iter().rposition(|&s| s != 0.0).unwrap();
+        let audio = &samples[first..=last];
+        let frames = 2 * 44100 * 2;
+        assert_eq!(audio.len(), frames * 2);
+        assert!(audio[..frames].iter().all(|&s| (s - 0.25).abs() < 1e-4));
+        assert!(audio[frames..].iter().all(|&s| (s - 0.5).abs() < 1e-4));
+    }
+
+    #[test]
+    fn cue_track_plays_its_range_of_the_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = fixtures(dir.path(), &[(0.25, Duration::from_secs(4))]).remove(0);
+        let out = dir.path().join("out.wav");
+
+        // INDEX 精确到 1/75 秒的帧，起止点都不是整秒
+        let cue = format!(
+            "FILE \"{}\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 00:01:37\n  TRACK 03 AUDIO\n    INDEX 01 00:03:37\n",
+            image.file_name().unwrap().to_string_lossy()
+        );
+        let sheet = crate::library::cue::CueSheet::parse(&cue, dir.path());
+        let range = &sheet.files[0].tracks[1];
+        let mut cue_track = track(&image, Duration::from_secs(2));
+        cue_track.start_offset = Some(range.start.as_millis() as u64);
+        cue_track.end_offset = range.end.map(|end| end.as_millis() as u64);
+        assert_eq!((cue_track.start_offset, cue_track.end_offset), (Some(1493), Some(3493)));
+
+        let events = EventBus::new();
+        let ended = on_event(&events, |e| matches!(e, PlayerEvent::TrackEnded));
+        let mut controller = controller(&events, Box::new(WavFileOutput::create(&out, 2, 44100).unwrap()));
+        controller.play_to_playlist(vec![cue_track], PlayMode::Queue).unwrap();
+        controller.play();
+        ended.recv_timeout(WAIT).expect("track-ended not emitted");
+        drop(controller);
+
+        // 只输出 1.493 s 到 3.493 s 这一段
+        let (_, _, samples) = read_wav(&out);
+        let frames = samples.iter().filter(|&&s| s != 0.0).count() / 2;
+        assert!(frames.abs_diff(2 * 44100) <= 2, "{frames}");
+    }
+
+    #[test]
+    fn track_change_advances_playlist() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(1);
+        let paths = fixtures(dir.path(), &[(0.25, length), (0.5, length), (0.75, length)]);
+
+        let events = EventBus::new();
+        let changed = on_event(&events, |e| matches!(e, PlayerEvent::TrackChanged(_)));
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        let tracks = paths.iter().map(|p| track(p, length)).collect();
+        controller.play_to_playlist(tracks, PlayMode::Queue).unwrap();
+        controller.play();
+        assert_eq!(controller.playlist_manager.queued_index, Some(1));
+
+        changed.recv_timeout(WAIT).expect("track-changed not emitted");
+        let advanced = controller.advance_to_queued().expect("no queued track");
+        assert_eq!(advanced.file_path, paths[1].to_string_lossy());
+        assert_eq!(controller.playlist_manager.current_index, Some(1));
+        assert_eq!(controller.playlist_manager.queued_index, Some(2));
+        assert_eq!(controller.snapshot().4.as_deref(), Some(paths[1].to_string_lossy().as_ref()));
+
+        controller.stop();
+        assert_eq!(controller.snapshot().0, PlaybackState::Stopped);
+    }
+
+    #[test]
+    fn seek_moves_playback_position() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(4);
+        let paths = fixtures(dir.path(), &[(0.25, length)]);
+
+        let events = EventBus::new();
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        controller.play_to_playlist(vec![track(&paths[0], length)], PlayMode::Queue).unwrap();
+        controller.play();
+        std::thread::sleep(Duration::from_millis(300));
+
+        controller.seek(Duration::from_secs(3)).unwrap();
+        assert!(controller.snapshot().2 >= Duration::from_secs(3));
+        std::thread::sleep(Duration::from_millis(500));
+        let position = controller.snapshot().2;
+        assert!(position > Duration::from_secs(3) && position < Duration::from_millis(3800), "{position:?}");
+
+        // 暂停后位置不再前进，跳转仍然生效
+        controller.pause();
+        controller.seek(Duration::from_secs(1)).unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(controller.snapshot().2, Duration::from_secs(1));
+        controller.stop();
+    }
+
+    #[test]
+    fn seek_to_fraction_of_a_second() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(3);
+        let paths = fixtures(dir.path(), &[(0.25, length)]);
+
+        let events = EventBus::new();
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        controller.play_to_playlist(vec![track(&paths[0], length)], PlayMode::Queue).unwrap();
+        controller.play();
+
+        // 进度条按毫秒跳转，位置通常不是整秒
+        controller.seek(Duration::from_millis(1250)).unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        let position = controller.snapshot().2;
+        assert!(position > Duration::from_millis(1250) && position < Duration::from_millis(1800), "{position:?}");
+        assert_eq!(controller.snapshot().0, PlaybackState::Playing);
+
+        controller.pause();
+        controller.seek(Duration::from_millis(2125)).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(controller.snapshot().2, Duration::from_millis(2125));
+        controller.stop();
+    }
+
+    #[test]
+    fn ab_loop_wraps_at_millisecond_points() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(3);
+        let paths = fixtures(dir.path(), &[(0.25, length)]);
+
+        let events = EventBus::new();
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        controller.play_to_playlist(vec![track(&paths[0], length)], PlayMode::Queue).unwrap();
+        controller.play();
+
+        // 循环点按毫秒保存；播放经过 B 点后回到 A 点，位置始终在区间内
+        let (a, b) = (Duration::from_millis(480), Duration::from_millis(930));
+        controller.set_ab_loop(a, b).unwrap();
+        let positions: Vec<Duration> = (0..15)
+            .map(|_| {
+                std::thread::sleep(Duration::from_millis(100));
+                controller.snapshot().2
+            })
+            .collect();
+        controller.stop();
+        assert!(positions.iter().all(|&p| p >= a && p < b + Duration::from_millis(100)), "{positions:?}");
+        assert!(positions.iter().any(|&p| p > a + Duration::from_millis(250)), "{positions:?}");
+        assert!(positions.windows(2).any(|w| w[1] < w[0]), "never wrapped: {positions:?}");
+    }
+
+    #[test]
+    fn remote_track_streams_over_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(2);
+        let paths = fixtures(dir.path(), &[(0.25, length)]);
+        let server = serve("track.wav", std::fs::read(&paths[0]).unwrap(), true);
+        let out = dir.path().join("out.wav");
+
+        let events = EventBus::new();
+        let ended = on_event(&events, |e| matches!(e, PlayerEvent::TrackEnded));
+        let mut controller = controller(&events, Box::new(WavFileOutput::create(&out, 2, 44100).unwrap()));
+        let track = Track::from_url(&server.url);
+        assert_eq!(track.path_type, PathType::Http.as_u8());
+        assert_eq!(track.title.as_deref(), Some("track.wav"));
+        controller.play_to_playlist(vec![track], PlayMode::Queue).unwrap();
+        controller.play();
+
+        ended.recv_timeout(WAIT).expect("track-ended not emitted");
+        drop(controller);
+
+        let (_, _, samples) = read_wav(&out);
+        let first = samples.iter().position(|&s| s != 0.0).unwrap();
+        let last = samples.iter().rposition(|&s| s != 0.0).unwrap();
+        let audio = &samples[first..=last];
+        assert_eq!(audio.len(), 2 * 44100 * 2);
+        assert!(audio.iter().all(|&s| (s - 0.25).abs() < 1e-4));
+    }
+
+    #[test]
+    fn remote_track_seeks() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(4);
+        let paths = fixtures(dir.path(), &[(0.25, length)]);
+        let server = serve("track.wav", std::fs::read(&paths[0]).unwrap(), true);
+
+        let events = EventBus::new();
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        controller.play_to_playlist(vec![Track::from_url(&server.url)], PlayMode::Queue).unwrap();
+        controller.play();
+        assert_eq!(controller.snapshot().3, Some(length));
+
+        controller.seek(Duration::from_secs(3)).unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+        let position = controller.snapshot().2;
+        assert!(position > Duration::from_secs(3) && position < Duration::from_millis(3800), "{position:?}");
+        controller.stop();
+    }
+
+    #[test]
+    fn audiobook_resumes_from_saved_position() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(30);
+        let paths = fixtures(dir.path(), &[(0.25, length)]);
+
+        let events = EventBus::new();
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        crate::database::init_schema(&crate::database::connection()).unwrap();
+
+        let mut book = track(&paths[0], length);
+        book.id = Some(9001);
+        book.genre = Some(vec!["audiobook".to_string()]);
+        resume::clear_position(9001).unwrap();
+        controller.play_to_playlist(vec![book], PlayMode::Queue).unwrap();
+        controller.play();
+        controller.seek(Duration::from_millis(10_250)).unwrap();
+        controller.stop();
+
+        let saved = resume::get_position(9001).unwrap().expect("position not saved");
+        assert!(saved >= Duration::from_millis(10_250) && saved < Duration::from_secs(12), "{saved:?}");
+
+        // 记忆的位置以毫秒保存，恢复后从该位置继续播放
+        controller.play();
+        let (_, _, position, _, _) = controller.snapshot();
+        assert!(position >= saved, "resumed at {position:?}");
+        std::thread::sleep(Duration::from_millis(300));
+        let (_, _, position, _, _) = controller.snapshot();
+        assert!(position > saved + Duration::from_millis(150), "stalled at {position:?}");
+        controller.stop();
+
+        resume::clear_position(9001).unwrap();
+        controller.play();
+        let (_, _, position, _, _) = controller.snapshot();
+        assert!(position < Duration::from_secs(2), "restarted at {position:?}");
+        controller.stop();
+    }
+
+    #[test]
+    fn chapter_navigation_seeks_to_millisecond_starts() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(4);
+        let paths = fixtures(dir.path(), &[(0.25, length)]);
+
+        let events = EventBus::new();
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        let conn = crate::database::connection();
+        crate::database::init_schema(&conn).unwrap();
+        let starts = [0, 1370, 2615];
+        let saved: Vec<Chapter> = starts.iter().enumerate()
+            .map(|(index, &start_ms)| Chapter { index, title: None, start_ms, end_ms: None })
+            .collect();
+        chapters::save_chapters(&conn, 9002, &saved).unwrap();
+
+        let mut book = track(&paths[0], length);
+        book.id = Some(9002);
+        controller.play_to_playlist(vec![book], PlayMode::Queue).unwrap();
+        controller.play();
+        assert_eq!(controller.chapters().len(), 3);
+
+        // 章节起点以毫秒保存，跳转后从起点继续播放
+        assert_eq!(controller.next_chapter().unwrap().index, 1);
+        std::thread::sleep(Duration::from_millis(300));
+        let position = controller.snapshot().2;
+        assert!(position > Duration::from_millis(1370 + 150) && position < Duration::from_millis(2000), "{position:?}");
+
+        assert_eq!(controller.seek_to_chapter(2).unwrap().start(), Duration::from_millis(2615));
+        std::thread::sleep(Duration::from_millis(300));
+        let position = controller.snapshot().2;
+        assert!(position > Duration::from_millis(2615 + 150) && position < Duration::from_millis(3300), "{position:?}");
+        controller.stop();
+    }
+
+    #[test]
+    fn unplayable_tracks_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(2);
+        let good = fixtures(dir.path(), &[(0.25, length)]);
+        let corrupt = dir.path().join("corrupt.flac");
+        std::fs::write(&corrupt, b"fLaC not really").unwrap();
+        let missing = dir.path().join("missing.mp3");
+
+        let events = EventBus::new();
+        let (tx, errors) = mpsc::channel();
+        let mut receiver = events.subscribe();
+        std::thread::spawn(move || loop {
+            match receiver.blocking_recv() {
+                Ok(Event::Player(PlayerEvent::PlaybackError(error))) => _ = tx.send(error),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        });
+
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        let tracks = [&missing, &corrupt, &good[0]].iter().map(|p| track(p, length)).collect();
+        controller.play_to_playlist(tracks, PlayMode::Queue).unwrap();
+        controller.play();
+
+        for path in [&missing, &corrupt] {
+            let error = errors.recv_timeout(WAIT).expect("playback-error not published");
+            assert_eq!(error.file_path, path.to_string_lossy());
+            assert!(!error.reason.is_empty());
+        }
+        assert_eq!(controller.playlist_manager.current_index, Some(2));
+        assert!(controller.is_playing());
+        let flags: Vec<bool> = controller.playlist_manager.playlist.tracks.iter().map(|t| t.unavailable).collect();
+        assert_eq!(flags, vec![true, true, false]);
+        controller.stop();
+    }
+
+    #[test]
+    fn all_unplayable_stops_instead_of_panicking() {
+        let dir = tempfile::tempdir().unwrap();
+        let length = Duration::from_secs(1);
+        let tracks = (0..8).map(|i| track(&dir.path().join(format!("missing{i}.mp3")), length)).collect();
+
+        let events = EventBus::new();
+        let mut controller = controller(&events, Box::new(NullOutput::new(2, 44100)));
+        controller.play_to_playlist(tracks, PlayMode::Queue).unwrap();
+        controller.play();
+
+        // 连续跳过达到上限后停止，其余曲目不再尝试
+        assert_eq!(controller.snapshot().0, PlaybackState::Stopped);
+        let flagged = controller.playlist_manager.playlist.tracks.iter().filter(|t| t.unavailable).count();
+        assert_eq!(flagged, MAX_CONSECUTIVE_SKIPS);
+    }
+}
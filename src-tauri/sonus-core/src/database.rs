@@ -0,0 +1,278 @@
+use std::path::PathBuf;
+use serde::Serialize;
+use rusqlite::{Connection, Result, Row, ToSql};
+
+#[derive(Debug, Serialize)]
+pub struct Config {
+    pub key: String,
+    pub value: String,
+}
+
+/// 应用数据目录：设置了 SONUS_DATA_DIR 时使用该目录（测试用），
+/// 否则为系统数据目录下的 Sonus；Windows 上即 %APPDATA%\Sonus，与旧版本一致
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("SONUS_DATA_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::data_dir().map(|dir| dir.join("Sonus")))
+        .unwrap_or_else(|| PathBuf::from("Sonus"))
+}
+
+pub fn connection() -> Connection {
+    let dir = data_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    Connection::open(dir.join("sonus.db")).unwrap()
+}
+
+pub fn get_config_value(conn: &Connection, key: &str) -> Result<Config> {
+    let mut stmt = conn.prepare("SELECT key, value FROM config WHERE key = ?")?;
+    let config = stmt.query_row([key], |row| {
+        Ok(Config {
+            key: row.get(0)?,
+            value: row.get(1)?,
+        })
+    })?;
+
+    Ok(config)
+}
+
+pub fn set_config_value(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute("UPDATE config SET value = ? WHERE key = ?", [value, key])?;
+    Ok(())
+}
+
+pub fn query_with_params<T, F>(
+    conn: &Connection,
+    sql: &str,
+    params: &[&dyn ToSql],
+    mapper: F
+) -> Result<Vec<T>>
+where
+    F: FnMut(&Row) -> Result<T>
+{
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params, mapper)?;
+    rows.collect()
+}
+
+pub fn execute(conn: &Connection, cmd: &str) -> Result<usize> {
+    Ok(conn.execute(cmd, [])?)
+}
+
+pub fn execute_with_params(conn: &Connection, cmd: &str, params: &[&dyn ToSql]) -> Result<usize> {
+    Ok(conn.execute(cmd, params)?)
+}
+
+/// 建表并升级旧版本数据库，应用启动与测试共用同一份表结构
+pub fn init_schema(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS config (
+        key TEXT PRIMARY KEY NOT NULL,
+        value TEXT
+    )",
+        (),
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS music (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        title TEXT,
+        album TEXT,
+        artist TEXT,
+        album_artist TEXT,
+        composer TEXT,
+        lyricist TEXT,
+        genre TEXT,
+        release_date TIMESTAMP,
+        track_number INTEGER,
+        disc_number INTEGER,
+        disc_total INTEGER,
+        bpm INTEGER,
+        duration INTEGER,
+        cover_art TEXT,
+        audio_format TEXT,
+        audio_size INTEGER,
+        bitrate INTEGER,
+        sample_rate INTEGER,
+        path_type TEXT,
+        file_path INTEGER,
+        create_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        update_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        copyright TEXT,
+        remark TEXT,
+        is_love INTEGER,
+        lyrics TEXT,
+        hash TEXT,
+        replaygain_track_gain REAL,
+        replaygain_track_peak REAL,
+        replaygain_album_gain REAL,
+        replaygain_album_peak REAL,
+        start_offset INTEGER,
+        end_offset INTEGER,
+        unavailable INTEGER DEFAULT 0,
+        mtime INTEGER,
+        missing INTEGER DEFAULT 0
+    )",
+        (),
+    )?;
+
+    // 旧版本数据库补齐新增列
+    ensure_column(conn, "music", "replaygain_track_gain", "REAL")?;
+    ensure_column(conn, "music", "replaygain_track_peak", "REAL")?;
+    ensure_column(conn, "music", "replaygain_album_gain", "REAL")?;
+    ensure_column(conn, "music", "replaygain_album_peak", "REAL")?;
+    ensure_column(conn, "music", "start_offset", "INTEGER")?;
+    ensure_column(conn, "music", "end_offset", "INTEGER")?;
+    ensure_column(conn, "music", "unavailable", "INTEGER DEFAULT 0")?;
+    ensure_column(conn, "music", "mtime", "INTEGER")?;
+    ensure_column(conn, "music", "missing", "INTEGER DEFAULT 0")?;
+
+    // 重新扫描时按哈希识别移动的文件；按路径查找使用下方的唯一索引
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_music_hash ON music (hash)",
+        (),
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS playlist (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT,
+        create_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        update_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        remark TEXT
+    )",
+        (),
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS playlist_music (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        playlist_id INTEGER,
+        music_id INTEGER,
+        create_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        update_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        remark TEXT
+    )",
+        (),
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ab_loop (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        music_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        start_ms INTEGER NOT NULL,
+        end_ms INTEGER NOT NULL,
+        create_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    )",
+        (),
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS resume_position (
+        music_id INTEGER PRIMARY KEY,
+        position_ms INTEGER NOT NULL,
+        update_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    )",
+        (),
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chapters (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        music_id INTEGER NOT NULL,
+        chapter_index INTEGER NOT NULL,
+        title TEXT,
+        start_ms INTEGER NOT NULL,
+        end_ms INTEGER
+    )",
+        (),
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_chapters_music_id ON chapters (music_id)",
+        (),
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS integrity_check (
+        music_id INTEGER PRIMARY KEY,
+        status TEXT NOT NULL,
+        detail TEXT,
+        checked_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    )",
+        (),
+    )?;
+
+    // 同一路径只有一条记录；CUE 拆分出的曲目共用镜像路径，以起点区分
+    if !index_exists(conn, "idx_music_track")? {
+        remove_duplicate_tracks(conn)?;
+        conn.execute("DROP INDEX IF EXISTS idx_music_file_path", ())?;
+    }
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_music_track ON music (file_path, IFNULL(start_offset, -1))",
+        (),
+    )?;
+    Ok(())
+}
+
+fn index_exists(conn: &Connection, name: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = ?",
+        [name],
+        |row| row.get::<_, usize>(0),
+    ).map(|count| count > 0)
+}
+
+/// 旧版本先查询再插入，可能为同一曲目留下多条记录：保留 id 最小的一条，
+/// 歌单、A-B 循环与收藏转到保留的记录上，其余附属数据随重复记录删除
+fn remove_duplicate_tracks(conn: &Connection) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(
+        "CREATE TEMP TABLE duplicate_music AS
+            SELECT id, keep FROM (
+                SELECT id, MIN(id) OVER (PARTITION BY file_path, IFNULL(start_offset, -1)) AS keep FROM music
+            ) WHERE id != keep;
+        UPDATE music SET is_love = 1 WHERE id IN (
+            SELECT d.keep FROM duplicate_music d JOIN music m ON m.id = d.id WHERE m.is_love = 1
+        );
+        UPDATE playlist_music SET music_id = (SELECT keep FROM duplicate_music WHERE id = music_id)
+            WHERE music_id IN (SELECT id FROM duplicate_music);
+        UPDATE ab_loop SET music_id = (SELECT keep FROM duplicate_music WHERE id = music_id)
+            WHERE music_id IN (SELECT id FROM duplicate_music);
+        UPDATE OR IGNORE resume_position SET music_id = (SELECT keep FROM duplicate_music WHERE id = music_id)
+            WHERE music_id IN (SELECT id FROM duplicate_music);
+        DELETE FROM resume_position WHERE music_id IN (SELECT id FROM duplicate_music);
+        DELETE FROM chapters WHERE music_id IN (SELECT id FROM duplicate_music);
+        DELETE FROM integrity_check WHERE music_id IN (SELECT id FROM duplicate_music);
+        DELETE FROM music WHERE id IN (SELECT id FROM duplicate_music);
+        DROP TABLE duplicate_music;"
+    )?;
+    tx.commit()
+}
+
+/// 表中缺少某列时追加该列，用于升级已有数据库
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), ())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::OnceLock;
+
+    /// 配置库放到临时目录，测试不读写用户的数据库
+    pub(crate) fn isolate_app_data() {
+        static APP_DATA: OnceLock<tempfile::TempDir> = OnceLock::new();
+        APP_DATA.get_or_init(|| {
+            let dir = tempfile::tempdir().unwrap();
+            std::env::set_var("SONUS_DATA_DIR", dir.path());
+            dir
+        });
+    }
+}
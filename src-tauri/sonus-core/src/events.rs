@@ -0,0 +1,94 @@
+/*
+* Events
+* 核心库对外发布的事件。播放器、曲库与任务队列把事件发到同一个广播通道，
+* 任意数量的订阅者（Tauri 前端适配层、守护进程、测试）各自接收完整的事件流。
+*/
+use tokio::sync::broadcast;
+
+use crate::controller::PlaybackError;
+use crate::library::incremental::ScanReport;
+use crate::library::integrity::IntegrityReport;
+use crate::player::audio_backend::TrackChanged;
+use crate::player::output_device::OutputDeviceChanged;
+use crate::player::sleep_timer::SleepTimerStatus;
+use crate::player::spectrum::SpectrumFrame;
+use crate::player::state::StateSnapshot;
+use crate::task_queue::TaskEvent;
+
+/// 每个订阅者可积压的事件数，超过后最旧的事件被丢弃
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// 状态快照与频谱帧较大，装箱后其他事件在通道中不必占用同样的空间
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+    StateUpdated(Box<StateSnapshot>),
+    TrackChanged(TrackChanged),     // 无缝切换到预排曲目
+    TrackEnded,                     // 播放完毕且没有预排曲目
+    SleepTimerUpdated(SleepTimerStatus),
+    Spectrum(Box<SpectrumFrame>),
+    OutputDeviceChanged(OutputDeviceChanged),
+    OutputDeviceLost,               // 正在使用的输出设备被拔出
+    PlaybackError(PlaybackError),   // 曲目无法打开或解码，已被跳过
+}
+
+#[derive(Debug, Clone)]
+pub enum LibraryEvent {
+    TrackIndexed { path: String },     // 扫描到的曲目已写入曲库
+    IntegrityChecked(IntegrityReport), // 文件完整性校验完成
+    ScanFinished(ScanReport),          // 目录扫描的所有文件处理完毕
+    TrackMissing { path: String },     // 文件已不存在，其曲目被标记为 missing
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Player(PlayerEvent),
+    Library(LibraryEvent),
+    Task(TaskEvent),
+}
+
+impl From<PlayerEvent> for Event {
+    fn from(event: PlayerEvent) -> Self {
+        Event::Player(event)
+    }
+}
+
+impl From<LibraryEvent> for Event {
+    fn from(event: LibraryEvent) -> Self {
+        Event::Library(event)
+    }
+}
+
+impl From<TaskEvent> for Event {
+    fn from(event: TaskEvent) -> Self {
+        Event::Task(event)
+    }
+}
+
+/// 事件总线，克隆后共享同一个通道
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// 发布事件；没有订阅者时直接丢弃
+    pub fn publish(&self, event: impl Into<Event>) {
+        _ = self.sender.send(event.into());
+    }
+
+    /// 订阅之后发布的所有事件
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
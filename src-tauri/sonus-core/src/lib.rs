@@ -1,6 +1,9 @@
+pub mod database;
+pub mod events;
 pub mod library;
 pub mod player;
 pub mod playlist;
 pub mod lyrics;
 pub mod task_queue;
-pub(crate) mod controller;
\ No newline at end of file
+pub mod controller;
+pub mod session;
@@ -0,0 +1,667 @@
+/*
+* Chapters
+* 读取有声书等长音频内嵌的章节：MP4/M4B 的 QuickTime 章节轨与 Nero chpl，Matroska 的 Chapters 元素。
+*/
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+use rusqlite::{Connection, Row};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use crate::database::{connection, execute_with_params, query_with_params};
+
+/// 章节表等小元素一次读入内存的上限，超出视为文件损坏
+const MAX_ELEMENT_LEN: u64 = 16 * 1024 * 1024;
+
+/// 曲目中的一个章节，时间相对于文件开头
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub index: usize,
+    pub title: Option<String>,
+    pub start_ms: u64,
+    pub end_ms: Option<u64>, // 最后一章为 None，表示到曲目末尾
+}
+
+impl Chapter {
+    pub fn start(&self) -> Duration {
+        Duration::from_millis(self.start_ms)
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            index: row.get(0)?,
+            title: row.get(1)?,
+            start_ms: row.get(2)?,
+            end_ms: row.get(3)?,
+        })
+    }
+}
+
+/// 读取文件中的章节；不是 MP4/Matroska 或没有章节时返回空列表
+pub fn read_chapters(path: &Path) -> Vec<Chapter> {
+    let result = File::open(path).and_then(|mut file| {
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic[4..8] == b"ftyp" {
+            mp4_chapters(&mut file)
+        } else if magic[0..4] == EBML_MAGIC {
+            Ok(matroska_segment(&mut file)?.chapters)
+        } else {
+            Ok(Vec::new())
+        }
+    });
+    match result {
+        Ok(marks) => number_chapters(marks),
+        Err(e) => {
+            warn!("读取章节失败: {:?}, 错误: {}", path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Matroska 的时长取自 Segment Info，lofty 无法读取 Matroska 时用于建立索引
+pub fn matroska_duration(path: &Path) -> Option<Duration> {
+    let mut file = File::open(path).ok()?;
+    matroska_segment(&mut file).ok()?.duration
+}
+
+/// 按起点排序编号，并以下一章的起点作为本章终点
+fn number_chapters(mut marks: Vec<(u64, Option<String>)>) -> Vec<Chapter> {
+    marks.sort_by_key(|(start, _)| *start);
+    marks.dedup_by_key(|(start, _)| *start);
+    let ends: Vec<Option<u64>> = marks.iter().skip(1).map(|(start, _)| Some(*start)).chain([None]).collect();
+    marks.into_iter().zip(ends).enumerate()
+        .map(|(index, ((start_ms, title), end_ms))| Chapter {
+            index,
+            title: title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()),
+            start_ms,
+            end_ms,
+        })
+        .collect()
+}
+
+/// 替换曲目的全部章节
+pub fn save_chapters(conn: &Connection, music_id: usize, chapters: &[Chapter]) -> rusqlite::Result<()> {
+    execute_with_params(conn, "DELETE FROM chapters WHERE music_id = ?", &[&music_id])?;
+    for chapter in chapters {
+        execute_with_params(
+            conn,
+            "INSERT INTO chapters (music_id, chapter_index, title, start_ms, end_ms) VALUES (?, ?, ?, ?, ?)",
+            &[&music_id, &chapter.index, &chapter.title, &chapter.start_ms, &chapter.end_ms],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn get_chapters(music_id: usize) -> rusqlite::Result<Vec<Chapter>> {
+    query_with_params(
+        &connection(),
+        "SELECT chapter_index, title, start_ms, end_ms FROM chapters WHERE music_id = ? ORDER BY chapter_index",
+        &[&music_id],
+        Chapter::from_row
+    )
+}
+
+/// 位置所在的章节：起点不晚于该位置的最后一章
+pub fn chapter_at(chapters: &[Chapter], position: Duration) -> Option<&Chapter> {
+    let position_ms = position.as_millis() as u64;
+    chapters.iter().rev().find(|c| c.start_ms <= position_ms)
+}
+
+// ---------------------------------------------------------------- MP4
+
+/// box 的类型与内容范围（不含头部）
+#[derive(Debug, Clone, Copy)]
+struct Mp4Box {
+    kind: [u8; 4],
+    start: u64,
+    end: u64,
+}
+
+/// 列出 [start, end) 范围内的 box
+fn mp4_boxes(file: &mut File, start: u64, end: u64) -> io::Result<Vec<Mp4Box>> {
+    let mut boxes = Vec::new();
+    let mut pos = start;
+    while pos + 8 <= end {
+        file.seek(SeekFrom::Start(pos))?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        let kind = [header[4], header[5], header[6], header[7]];
+        let (size, header_len) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            0 => (end - pos, 8), // 延伸到父容器末尾
+            1 => {
+                let mut large = [0u8; 8];
+                file.read_exact(&mut large)?;
+                (u64::from_be_bytes(large), 16)
+            }
+            size => (size as u64, 8),
+        };
+        if size < header_len || pos + size > end {
+            break;
+        }
+        boxes.push(Mp4Box { kind, start: pos + header_len, end: pos + size });
+        pos += size;
+    }
+    Ok(boxes)
+}
+
+fn mp4_children(file: &mut File, parent: &Mp4Box) -> io::Result<Vec<Mp4Box>> {
+    mp4_boxes(file, parent.start, parent.end)
+}
+
+fn find_box(boxes: &[Mp4Box], kind: &[u8; 4]) -> Option<Mp4Box> {
+    boxes.iter().find(|b| &b.kind == kind).copied()
+}
+
+/// 沿路径逐层查找子 box
+fn find_path(file: &mut File, parent: &Mp4Box, path: &[&[u8; 4]]) -> io::Result<Option<Mp4Box>> {
+    let mut current = *parent;
+    for kind in path {
+        match find_box(&mp4_children(file, &current)?, kind) {
+            Some(child) => current = child,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
+fn read_range(file: &mut File, start: u64, len: u64) -> io::Result<Vec<u8>> {
+    if len > MAX_ELEMENT_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "element too large"));
+    }
+    file.seek(SeekFrom::Start(start))?;
+    let mut data = vec![0u8; len as usize];
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn read_box(file: &mut File, mp4_box: &Mp4Box) -> io::Result<Vec<u8>> {
+    read_range(file, mp4_box.start, mp4_box.end - mp4_box.start)
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// 章节轨优先（iTunes、多数有声书制作工具），其次 Nero 的 udta/chpl
+fn mp4_chapters(file: &mut File) -> io::Result<Vec<(u64, Option<String>)>> {
+    let end = file.metadata()?.len();
+    let Some(moov) = find_box(&mp4_boxes(file, 0, end)?, b"moov") else { return Ok(Vec::new()) };
+    let children = mp4_children(file, &moov)?;
+
+    let mut tracks = Vec::new();
+    for trak in children.iter().filter(|b| &b.kind == b"trak") {
+        tracks.push(Mp4Track::read(file, trak)?);
+    }
+    let chapter_track = tracks.iter()
+        .flat_map(|t| t.chapter_refs.iter())
+        .find_map(|id| tracks.iter().find(|t| t.id == Some(*id)));
+    if let Some(track) = chapter_track {
+        let marks = track.text_samples(file)?;
+        if !marks.is_empty() {
+            return Ok(marks);
+        }
+    }
+
+    match find_box(&children, b"udta") {
+        Some(udta) => match find_box(&mp4_children(file, &udta)?, b"chpl") {
+            Some(chpl) => Ok(parse_chpl(&read_box(file, &chpl)?)),
+            None => Ok(Vec::new()),
+        },
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Nero 章节：时间以 100 纳秒为单位，标题为带长度前缀的 UTF-8
+fn parse_chpl(data: &[u8]) -> Vec<(u64, Option<String>)> {
+    let mut marks = Vec::new();
+    let Some(&version) = data.first() else { return marks };
+    let mut at = if version > 0 { 8 } else { 4 };
+    let Some(&count) = data.get(at) else { return marks };
+    at += 1;
+    for _ in 0..count {
+        let Some(start) = be_u64(data, at) else { break };
+        let Some(&len) = data.get(at + 8) else { break };
+        let Some(title) = data.get(at + 9..at + 9 + len as usize) else { break };
+        marks.push((start / 10_000, Some(String::from_utf8_lossy(title).into_owned())));
+        at += 9 + len as usize;
+    }
+    marks
+}
+
+/// 章节轨所需的 trak 信息
+#[derive(Default)]
+struct Mp4Track {
+    id: Option<u32>,
+    chapter_refs: Vec<u32>,
+    timescale: u32,
+    stts: Vec<(u32, u32)>,   // (样本数, 每个样本的时长)
+    stsc: Vec<(u32, u32)>,   // (首个 chunk 序号，从 1 开始, 每 chunk 的样本数)
+    sizes: Vec<u32>,
+    chunk_offsets: Vec<u64>,
+}
+
+impl Mp4Track {
+    fn read(file: &mut File, trak: &Mp4Box) -> io::Result<Self> {
+        let mut track = Self::default();
+        let children = mp4_children(file, trak)?;
+
+        if let Some(tkhd) = find_box(&children, b"tkhd") {
+            let data = read_box(file, &tkhd)?;
+            let at = if data.first() == Some(&1) { 20 } else { 12 };
+            track.id = be_u32(&data, at);
+        }
+        if let Some(chap) = find_path(file, trak, &[b"tref", b"chap"])? {
+            let data = read_box(file, &chap)?;
+            track.chapter_refs = data.chunks_exact(4).map(|id| u32::from_be_bytes([id[0], id[1], id[2], id[3]])).collect();
+        }
+        // 只有被引用为章节轨的 trak 才需要样本表，但解析开销很小
+        let Some(mdia) = find_box(&children, b"mdia") else { return Ok(track) };
+        if let Some(mdhd) = find_box(&mp4_children(file, &mdia)?, b"mdhd") {
+            let data = read_box(file, &mdhd)?;
+            let at = if data.first() == Some(&1) { 20 } else { 12 };
+            track.timescale = be_u32(&data, at).unwrap_or(0);
+        }
+        let Some(stbl) = find_path(file, &mdia, &[b"minf", b"stbl"])? else { return Ok(track) };
+        for table in mp4_children(file, &stbl)? {
+            match &table.kind {
+                b"stts" | b"stsc" | b"stsz" | b"stco" | b"co64" => {}
+                _ => continue,
+            }
+            let data = read_box(file, &table)?;
+            let count = be_u32(&data, 4).unwrap_or(0) as usize;
+            match &table.kind {
+                b"stts" => track.stts = (0..count)
+                    .map_while(|i| Some((be_u32(&data, 8 + i * 8)?, be_u32(&data, 12 + i * 8)?)))
+                    .collect(),
+                b"stsc" => track.stsc = (0..count)
+                    .map_while(|i| Some((be_u32(&data, 8 + i * 12)?, be_u32(&data, 12 + i * 12)?)))
+                    .collect(),
+                b"stsz" => {
+                    let fixed = count as u32;
+                    let samples = be_u32(&data, 8).unwrap_or(0) as usize;
+                    track.sizes = if fixed > 0 {
+                        vec![fixed; samples]
+                    } else {
+                        (0..samples).map_while(|i| be_u32(&data, 12 + i * 4)).collect()
+                    };
+                }
+                b"stco" => track.chunk_offsets = (0..count)
+                    .map_while(|i| be_u32(&data, 8 + i * 4).map(u64::from))
+                    .collect(),
+                _ => track.chunk_offsets = (0..count)
+                    .map_while(|i| be_u64(&data, 8 + i * 8))
+                    .collect(),
+            }
+        }
+        Ok(track)
+    }
+
+    /// 章节轨的每个样本是一段文本：2 字节长度 + UTF-8（或带 BOM 的 UTF-16）
+    fn text_samples(&self, file: &mut File) -> io::Result<Vec<(u64, Option<String>)>> {
+        if self.timescale == 0 {
+            return Ok(Vec::new());
+        }
+        let starts = self.stts.iter()
+            .flat_map(|&(count, delta)| std::iter::repeat_n(delta as u64, count as usize))
+            .scan(0u64, |time, delta| {
+                let start = *time;
+                *time += delta;
+                Some(start)
+            });
+
+        let mut marks = Vec::new();
+        for ((offset, size), start) in self.sample_offsets().into_iter().zip(&self.sizes).zip(starts) {
+            let data = read_range(file, offset, *size as u64)?;
+            let len = data.get(..2).map_or(0, |l| u16::from_be_bytes([l[0], l[1]]) as usize);
+            let text = data.get(2..2 + len).map(decode_text);
+            marks.push((start * 1000 / self.timescale as u64, text));
+        }
+        Ok(marks)
+    }
+
+    /// 按 stsc 把样本分配到 chunk，得到每个样本在文件中的偏移
+    fn sample_offsets(&self) -> Vec<u64> {
+        let mut offsets = Vec::with_capacity(self.sizes.len());
+        let mut sample = 0;
+        for (chunk, &chunk_offset) in self.chunk_offsets.iter().enumerate() {
+            let per_chunk = self.stsc.iter()
+                .take_while(|(first, _)| *first as usize <= chunk + 1)
+                .last()
+                .map_or(1, |(_, n)| *n);
+            let mut offset = chunk_offset;
+            for _ in 0..per_chunk {
+                let Some(&size) = self.sizes.get(sample) else { return offsets };
+                offsets.push(offset);
+                offset += size as u64;
+                sample += 1;
+            }
+        }
+        offsets
+    }
+}
+
+fn decode_text(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xfe, 0xff]) {
+        let units: Vec<u16> = bytes[2..].chunks_exact(2).map(|u| u16::from_be_bytes([u[0], u[1]])).collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+// ---------------------------------------------------------------- Matroska
+
+const EBML_MAGIC: [u8; 4] = [0x1a, 0x45, 0xdf, 0xa3];
+const ID_EBML: u64 = 0x1a45dfa3;
+const ID_SEGMENT: u64 = 0x18538067;
+const ID_SEEK_HEAD: u64 = 0x114d9b74;
+const ID_SEEK: u64 = 0x4dbb;
+const ID_SEEK_ID: u64 = 0x53ab;
+const ID_SEEK_POSITION: u64 = 0x53ac;
+const ID_INFO: u64 = 0x1549a966;
+const ID_TIMESTAMP_SCALE: u64 = 0x2ad7b1;
+const ID_DURATION: u64 = 0x4489;
+const ID_CHAPTERS: u64 = 0x1043a770;
+const ID_EDITION_ENTRY: u64 = 0x45b9;
+const ID_EDITION_FLAG_DEFAULT: u64 = 0x45db;
+const ID_CHAPTER_ATOM: u64 = 0xb6;
+const ID_CHAPTER_TIME_START: u64 = 0x91;
+const ID_CHAPTER_FLAG_HIDDEN: u64 = 0x98;
+const ID_CHAPTER_FLAG_ENABLED: u64 = 0x4598;
+const ID_CHAPTER_DISPLAY: u64 = 0x80;
+const ID_CHAP_STRING: u64 = 0x85;
+
+#[derive(Default)]
+struct MatroskaSegment {
+    duration: Option<Duration>,
+    chapters: Vec<(u64, Option<String>)>,
+}
+
+/// EBML 变长整数；keep_marker 为 true 时保留长度标记位（元素 ID 的写法）
+fn ebml_vint(data: &[u8], keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let mut value = if keep_marker { first as u64 } else { first as u64 & (0xff >> len) };
+    for &byte in data.get(1..len)? {
+        value = (value << 8) | byte as u64;
+    }
+    Some((value, len))
+}
+
+/// 元素头：(ID, 内容长度，未知长度时为 None, 头部字节数)
+fn ebml_header(data: &[u8]) -> Option<(u64, Option<u64>, usize)> {
+    let (id, id_len) = ebml_vint(data, true)?;
+    let (size, size_len) = ebml_vint(&data[id_len..], false)?;
+    let unknown = size == (1u64 << (7 * size_len)) - 1;
+    Some((id, (!unknown).then_some(size), id_len + size_len))
+}
+
+/// 内存中的一组同级元素
+fn ebml_elements(mut data: &[u8]) -> Vec<(u64, &[u8])> {
+    let mut elements = Vec::new();
+    while let Some((id, size, header_len)) = ebml_header(data) {
+        let end = size
+            .and_then(|s| usize::try_from(s).ok())
+            .map_or(data.len(), |s| header_len.saturating_add(s).min(data.len()));
+        elements.push((id, &data[header_len..end]));
+        data = &data[end..];
+    }
+    elements
+}
+
+fn ebml_uint(data: &[u8]) -> u64 {
+    data.iter().take(8).fold(0, |acc, &b| (acc << 8) | b as u64)
+}
+
+fn ebml_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// 读取文件 pos 处的元素头：(ID, 内容长度, 内容起点)
+fn ebml_header_at(file: &mut File, pos: u64) -> io::Result<Option<(u64, Option<u64>, u64)>> {
+    file.seek(SeekFrom::Start(pos))?;
+    let mut header = Vec::with_capacity(12);
+    file.take(12).read_to_end(&mut header)?;
+    Ok(ebml_header(&header).map(|(id, size, len)| (id, size, pos + len as u64)))
+}
+
+/// 顺序遍历 Segment 的顶层元素读取 Info 与 Chapters；遇到未知长度的 Cluster 无法跳过时，
+/// 改用 SeekHead 记录的位置
+fn matroska_segment(file: &mut File) -> io::Result<MatroskaSegment> {
+    let file_len = file.metadata()?.len();
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a Matroska file");
+
+    let (id, size, body) = ebml_header_at(file, 0)?.ok_or_else(invalid)?;
+    if id != ID_EBML {
+        return Err(invalid());
+    }
+    let segment_pos = body + size.ok_or_else(invalid)?;
+    let (id, size, segment_start) = ebml_header_at(file, segment_pos)?.ok_or_else(invalid)?;
+    if id != ID_SEGMENT {
+        return Err(invalid());
+    }
+    let segment_end = size.map_or(file_len, |s| (segment_start + s).min(file_len));
+
+    let mut segment = MatroskaSegment::default();
+    let mut timestamp_scale = 1_000_000u64;
+    let mut raw_duration = None;
+    let (mut info_pos, mut chapters_pos) = (None, None);
+    let (mut info_done, mut chapters_done) = (false, false);
+
+    let mut pos = segment_start;
+    while pos < segment_end && !(info_done && chapters_done) {
+        let Some((id, size, body)) = ebml_header_at(file, pos)? else { break };
+        let Some(size) = size else { break };
+        match id {
+            ID_SEEK_HEAD => {
+                for (id, seek) in ebml_elements(&read_range(file, body, size)?) {
+                    if id != ID_SEEK {
+                        continue;
+                    }
+                    let fields = ebml_elements(seek);
+                    let target = fields.iter().find(|(id, _)| *id == ID_SEEK_ID).map(|(_, v)| ebml_uint(v));
+                    let position = fields.iter().find(|(id, _)| *id == ID_SEEK_POSITION).map(|(_, v)| segment_start + ebml_uint(v));
+                    match target {
+                        Some(ID_INFO) => info_pos = position,
+                        Some(ID_CHAPTERS) => chapters_pos = position,
+                        _ => {}
+                    }
+                }
+            }
+            ID_INFO => {
+                (timestamp_scale, raw_duration) = parse_info(&read_range(file, body, size)?);
+                info_done = true;
+            }
+            ID_CHAPTERS => {
+                segment.chapters = parse_chapters(&read_range(file, body, size)?);
+                chapters_done = true;
+            }
+            _ => {}
+        }
+        pos = body + size;
+    }
+
+    if !info_done {
+        if let Some((ID_INFO, Some(size), body)) = info_pos.map(|p| ebml_header_at(file, p)).transpose()?.flatten() {
+            (timestamp_scale, raw_duration) = parse_info(&read_range(file, body, size)?);
+        }
+    }
+    if !chapters_done {
+        if let Some((ID_CHAPTERS, Some(size), body)) = chapters_pos.map(|p| ebml_header_at(file, p)).transpose()?.flatten() {
+            segment.chapters = parse_chapters(&read_range(file, body, size)?);
+        }
+    }
+    segment.duration = raw_duration
+        .filter(|d: &f64| d.is_finite() && *d > 0.0)
+        .map(|d| Duration::from_nanos((d * timestamp_scale as f64) as u64));
+    Ok(segment)
+}
+
+/// (TimestampScale, Duration)；Duration 以 TimestampScale 纳秒为单位
+fn parse_info(data: &[u8]) -> (u64, Option<f64>) {
+    let mut scale = 1_000_000;
+    let mut duration = None;
+    for (id, value) in ebml_elements(data) {
+        match id {
+            ID_TIMESTAMP_SCALE => scale = ebml_uint(value).max(1),
+            ID_DURATION => duration = ebml_float(value),
+            _ => {}
+        }
+    }
+    (scale, duration)
+}
+
+/// 使用默认版本（没有标记默认时取第一个），跳过隐藏或禁用的章节；时间为纳秒
+fn parse_chapters(data: &[u8]) -> Vec<(u64, Option<String>)> {
+    let editions: Vec<&[u8]> = ebml_elements(data).into_iter()
+        .filter(|(id, _)| *id == ID_EDITION_ENTRY)
+        .map(|(_, body)| body)
+        .collect();
+    let is_default = |edition: &&&[u8]| ebml_elements(edition).iter()
+        .any(|(id, value)| *id == ID_EDITION_FLAG_DEFAULT && ebml_uint(value) == 1);
+    let Some(edition) = editions.iter().find(is_default).or(editions.first()) else { return Vec::new() };
+
+    ebml_elements(edition).into_iter()
+        .filter(|(id, _)| *id == ID_CHAPTER_ATOM)
+        .filter_map(|(_, atom)| {
+            let fields = ebml_elements(atom);
+            let flag = |flag_id: u64| fields.iter().find(|(id, _)| *id == flag_id).map(|(_, v)| ebml_uint(v));
+            if flag(ID_CHAPTER_FLAG_HIDDEN) == Some(1) || flag(ID_CHAPTER_FLAG_ENABLED) == Some(0) {
+                return None;
+            }
+            let start = flag(ID_CHAPTER_TIME_START)?;
+            let title = fields.iter()
+                .filter(|(id, _)| *id == ID_CHAPTER_DISPLAY)
+                .flat_map(|(_, display)| ebml_elements(display))
+                .find(|(id, _)| *id == ID_CHAP_STRING)
+                .map(|(_, s)| String::from_utf8_lossy(s).into_owned());
+            Some((start / 1_000_000, title))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn full_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        mp4_box(kind, &[&[0u8; 4][..], body].concat())
+    }
+
+    fn ebml(id: u64, body: &[u8]) -> Vec<u8> {
+        let id_bytes = id.to_be_bytes();
+        let skip = id_bytes.iter().position(|&b| b != 0).unwrap();
+        let mut data = id_bytes[skip..].to_vec();
+        data.push(0x01); // 8 字节长度
+        data.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn write(bytes: &[u8]) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), bytes).unwrap();
+        file
+    }
+
+    fn summary(chapters: &[Chapter]) -> Vec<(u64, Option<u64>, Option<&str>)> {
+        chapters.iter().map(|c| (c.start_ms, c.end_ms, c.title.as_deref())).collect()
+    }
+
+    #[test]
+    fn reads_nero_chapters() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0u64, "Opening"), (905_000_000, "Chapter 2")] {
+            chpl.extend_from_slice(&start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend_from_slice(title.as_bytes());
+        }
+        let moov = mp4_box(b"moov", &mp4_box(b"udta", &mp4_box(b"chpl", &chpl)));
+        let file = write(&[mp4_box(b"ftyp", b"M4B \0\0\0\0"), moov].concat());
+
+        let chapters = read_chapters(file.path());
+        assert_eq!(summary(&chapters), vec![(0, Some(90_500), Some("Opening")), (90_500, None, Some("Chapter 2"))]);
+    }
+
+    #[test]
+    fn reads_quicktime_chapter_track() {
+        let titles = ["Intro", "Part One", "Part Two"];
+        let ftyp = mp4_box(b"ftyp", b"M4B \0\0\0\0");
+        // 章节文本放在 mdat 中，偏移需要在构造 moov 之前确定
+        let mut samples = Vec::new();
+        for title in titles {
+            samples.extend_from_slice(&(title.len() as u16).to_be_bytes());
+            samples.extend_from_slice(title.as_bytes());
+        }
+        let mdat = mp4_box(b"mdat", &samples);
+        let first_offset = (ftyp.len() + 8) as u32;
+
+        let tkhd = |id: u32| full_box(b"tkhd", &[&[0u8; 8][..], &id.to_be_bytes(), &[0u8; 72]].concat());
+        let audio = mp4_box(b"trak", &[tkhd(1), mp4_box(b"tref", &mp4_box(b"chap", &2u32.to_be_bytes()))].concat());
+
+        let mdhd = full_box(b"mdhd", &[&[0u8; 8][..], &1000u32.to_be_bytes(), &[0u8; 8]].concat());
+        let stts = full_box(b"stts", &[&2u32.to_be_bytes()[..], &1u32.to_be_bytes(), &60_000u32.to_be_bytes(), &2u32.to_be_bytes(), &30_000u32.to_be_bytes()].concat());
+        let stsc = full_box(b"stsc", &[&1u32.to_be_bytes()[..], &1u32.to_be_bytes(), &3u32.to_be_bytes(), &1u32.to_be_bytes()].concat());
+        let sizes: Vec<u8> = titles.iter().flat_map(|t| (t.len() as u32 + 2).to_be_bytes()).collect();
+        let stsz = full_box(b"stsz", &[&0u32.to_be_bytes()[..], &3u32.to_be_bytes(), &sizes].concat());
+        let stco = full_box(b"stco", &[&1u32.to_be_bytes()[..], &first_offset.to_be_bytes()].concat());
+        let stbl = mp4_box(b"stbl", &[stts, stsc, stsz, stco].concat());
+        let mdia = mp4_box(b"mdia", &[mdhd, mp4_box(b"minf", &stbl)].concat());
+        let text = mp4_box(b"trak", &[tkhd(2), mdia].concat());
+
+        let moov = mp4_box(b"moov", &[audio, text].concat());
+        let file = write(&[ftyp, mdat, moov].concat());
+
+        let chapters = read_chapters(file.path());
+        assert_eq!(summary(&chapters), vec![
+            (0, Some(60_000), Some("Intro")),
+            (60_000, Some(90_000), Some("Part One")),
+            (90_000, None, Some("Part Two")),
+        ]);
+    }
+
+    #[test]
+    fn reads_matroska_chapters_and_duration() {
+        let atom = |start_ms: u64, title: &str, hidden: bool| ebml(ID_CHAPTER_ATOM, &[
+            ebml(ID_CHAPTER_TIME_START, &(start_ms * 1_000_000).to_be_bytes()),
+            ebml(ID_CHAPTER_FLAG_HIDDEN, &[hidden as u8]),
+            ebml(ID_CHAPTER_DISPLAY, &ebml(ID_CHAP_STRING, title.as_bytes())),
+        ].concat());
+        let edition = ebml(ID_EDITION_ENTRY, &[
+            atom(0, "One", false),
+            atom(1_000, "Hidden", true),
+            atom(125_000, "Two", false),
+        ].concat());
+        let info = ebml(ID_INFO, &[
+            ebml(ID_TIMESTAMP_SCALE, &1_000_000u32.to_be_bytes()),
+            ebml(ID_DURATION, &300_000f64.to_be_bytes()),
+        ].concat());
+        let segment = ebml(ID_SEGMENT, &[info, ebml(ID_CHAPTERS, &edition)].concat());
+        let file = write(&[ebml(ID_EBML, &[]), segment].concat());
+
+        let chapters = read_chapters(file.path());
+        assert_eq!(summary(&chapters), vec![(0, Some(125_000), Some("One")), (125_000, None, Some("Two"))]);
+        assert_eq!(matroska_duration(file.path()), Some(Duration::from_secs(300)));
+        assert_eq!(chapter_at(&chapters, Duration::from_secs(200)).map(|c| c.index), Some(1));
+    }
+}
@@ -0,0 +1,187 @@
+/*
+* CUE Sheet
+* 解析整轨镜像附带的 .cue 文件，每个 TRACK 对应镜像文件中的一段。
+*/
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 一个 CUE 文件；FILE 路径已按 .cue 所在目录解析为绝对路径
+#[derive(Debug, Clone, Default)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub songwriter: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    pub replaygain_album_gain: Option<f32>,
+    pub replaygain_album_peak: Option<f32>,
+    pub files: Vec<CueFile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CueFile {
+    pub path: PathBuf,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CueTrack {
+    pub number: u16,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub songwriter: Option<String>,
+    pub start: Duration,       // INDEX 01
+    pub end: Option<Duration>, // 同一文件中下一轨的 INDEX 01，最后一轨为 None
+    pub replaygain_track_gain: Option<f32>,
+    pub replaygain_track_peak: Option<f32>,
+}
+
+impl CueSheet {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Ok(Self::parse(&decode_text(&bytes), dir))
+    }
+
+    /// 解析 CUE 文本，dir 为 FILE 相对路径的基准目录
+    pub fn parse(text: &str, dir: &Path) -> Self {
+        let mut sheet = CueSheet::default();
+        let mut track: Option<CueTrack> = None;
+
+        for line in text.lines() {
+            let (command, rest) = split_command(line.trim());
+            match command.to_ascii_uppercase().as_str() {
+                "FILE" => {
+                    push_track(&mut sheet, track.take());
+                    let (name, _) = split_value(rest);
+                    sheet.files.push(CueFile { path: dir.join(name), tracks: Vec::new() });
+                }
+                "TRACK" => {
+                    push_track(&mut sheet, track.take());
+                    let (number, kind) = split_value(rest);
+                    // 只收音轨，数据轨跳过
+                    if kind.eq_ignore_ascii_case("AUDIO") {
+                        track = Some(CueTrack {
+                            number: number.parse().unwrap_or(0),
+                            ..Default::default()
+                        });
+                    }
+                }
+                "INDEX" => {
+                    let (number, time) = split_value(rest);
+                    if let (Some(track), Ok(1)) = (track.as_mut(), number.parse::<u32>()) {
+                        track.start = parse_msf(time).unwrap_or_default();
+                    }
+                }
+                "TITLE" => {
+                    let value = Some(split_value(rest).0.to_string());
+                    match track.as_mut() {
+                        Some(track) => track.title = value,
+                        None => sheet.title = value,
+                    }
+                }
+                "PERFORMER" => {
+                    let value = Some(split_value(rest).0.to_string());
+                    match track.as_mut() {
+                        Some(track) => track.performer = value,
+                        None => sheet.performer = value,
+                    }
+                }
+                "SONGWRITER" => {
+                    let value = Some(split_value(rest).0.to_string());
+                    match track.as_mut() {
+                        Some(track) => track.songwriter = value,
+                        None => sheet.songwriter = value,
+                    }
+                }
+                "REM" => {
+                    let (key, value) = split_command(rest);
+                    let (value, _) = split_value(value);
+                    match (key.to_ascii_uppercase().as_str(), track.as_mut()) {
+                        ("GENRE", None) => sheet.genre = Some(value.to_string()),
+                        ("DATE", None) => sheet.date = Some(value.to_string()),
+                        ("REPLAYGAIN_ALBUM_GAIN", _) => sheet.replaygain_album_gain = parse_gain(value),
+                        ("REPLAYGAIN_ALBUM_PEAK", _) => sheet.replaygain_album_peak = parse_gain(value),
+                        ("REPLAYGAIN_TRACK_GAIN", Some(track)) => track.replaygain_track_gain = parse_gain(value),
+                        ("REPLAYGAIN_TRACK_PEAK", Some(track)) => track.replaygain_track_peak = parse_gain(value),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        push_track(&mut sheet, track);
+
+        // 每轨的终点为同一文件中下一轨的起点
+        for file in &mut sheet.files {
+            let starts: Vec<Duration> = file.tracks.iter().map(|t| t.start).collect();
+            for (track, next) in file.tracks.iter_mut().zip(starts.into_iter().skip(1).map(Some).chain([None])) {
+                track.end = next;
+            }
+        }
+        sheet.files.retain(|file| !file.tracks.is_empty());
+        sheet
+    }
+
+    /// CUE 引用的所有镜像文件
+    pub fn image_paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|file| file.path.as_path())
+    }
+}
+
+pub fn is_cue_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("cue"))
+}
+
+fn push_track(sheet: &mut CueSheet, track: Option<CueTrack>) {
+    if let (Some(track), Some(file)) = (track, sheet.files.last_mut()) {
+        file.tracks.push(track);
+    }
+}
+
+/// CUE 多为 UTF-8（可能带 BOM），否则按 GBK 处理
+fn decode_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => encoding_rs::GBK.decode(bytes).0.into_owned(),
+    }
+}
+
+/// 拆出行首命令字与剩余部分
+fn split_command(line: &str) -> (&str, &str) {
+    match line.split_once(char::is_whitespace) {
+        Some((command, rest)) => (command, rest.trim_start()),
+        None => (line, ""),
+    }
+}
+
+/// 取出一个值（可带引号）与剩余部分
+fn split_value(rest: &str) -> (&str, &str) {
+    if let Some(quoted) = rest.strip_prefix('"') {
+        match quoted.split_once('"') {
+            Some((value, tail)) => (value, tail.trim()),
+            None => (quoted, ""),
+        }
+    } else {
+        let (value, tail) = split_command(rest);
+        (value, tail.trim())
+    }
+}
+
+/// mm:ss:ff，每秒 75 帧
+fn parse_msf(value: &str) -> Option<Duration> {
+    let mut parts = value.split(':').map(|p| p.trim().parse::<u64>());
+    let (Some(Ok(m)), Some(Ok(s)), Some(Ok(f))) = (parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    Some(Duration::from_millis((m * 60 + s) * 1000 + f * 1000 / 75))
+}
+
+fn parse_gain(value: &str) -> Option<f32> {
+    let number = value.trim().trim_end_matches(|c: char| c.is_alphabetic() || c.is_whitespace());
+    number.parse::<f32>().ok().filter(|v| v.is_finite())
+}
@@ -0,0 +1,279 @@
+/*
+* Audio Format
+* 按文件内容识别音频格式，不依赖扩展名；扩展名错误或缺失的文件同样能被识别。
+*/
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// 读取文件头部的字节数（ID3v2 标签之后），足以跨过常见的填充找到首个 MP3 帧
+const SNIFF_LEN: usize = 64 * 1024;
+
+/// 可解码的音频格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Mp3,
+    Aac,     // ADTS 裸流
+    Mp4,     // AAC / ALAC
+    Flac,
+    Ogg,     // Vorbis / FLAC
+    Opus,
+    Wav,
+    Aiff,
+    WavPack,
+    Ape,
+    Matroska, // MKA / WebM 音频
+}
+
+impl AudioFormat {
+    /// 规范扩展名，用作解码时的格式提示与 audio_format 字段
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Mp4 => "m4a",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Aiff => "aiff",
+            AudioFormat::WavPack => "wv",
+            AudioFormat::Ape => "ape",
+            AudioFormat::Matroska => "mka",
+        }
+    }
+}
+
+/// 读取文件头部识别格式；无法读取或不是支持的音频时返回 None
+pub fn sniff(path: &Path) -> Option<AudioFormat> {
+    let mut file = File::open(path).ok()?;
+    let mut head = read_head(&mut file)?;
+    // ID3v2 可能出现在 MP3、FLAC、APE 等文件之前，内嵌封面时常大于 SNIFF_LEN
+    let tag_len = id3v2_len(&head);
+    if tag_len > 0 {
+        file.seek(SeekFrom::Start(tag_len as u64)).ok()?;
+        head = read_head(&mut file)?;
+    }
+    sniff_bytes(&head)
+}
+
+fn read_head(file: &mut File) -> Option<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    file.take(SNIFF_LEN as u64).read_to_end(&mut head).ok()?;
+    Some(head)
+}
+
+fn sniff_bytes(data: &[u8]) -> Option<AudioFormat> {
+    let magic = |at: usize, bytes: &[u8]| data.get(at..at + bytes.len()) == Some(bytes);
+
+    if magic(0, b"fLaC") {
+        Some(AudioFormat::Flac)
+    } else if magic(0, b"OggS") {
+        sniff_ogg(data)
+    } else if magic(0, b"RIFF") && magic(8, b"WAVE") {
+        Some(AudioFormat::Wav)
+    } else if magic(0, b"FORM") && (magic(8, b"AIFF") || magic(8, b"AIFC")) {
+        Some(AudioFormat::Aiff)
+    } else if magic(4, b"ftyp") {
+        sniff_mp4(data)
+    } else if magic(0, b"wvpk") {
+        Some(AudioFormat::WavPack)
+    } else if magic(0, b"MAC ") {
+        Some(AudioFormat::Ape)
+    } else if magic(0, &[0x1a, 0x45, 0xdf, 0xa3]) {
+        sniff_matroska(data)
+    } else if is_adts(data) {
+        Some(AudioFormat::Aac)
+    } else if find_mp3_frames(data) {
+        Some(AudioFormat::Mp3)
+    } else {
+        None
+    }
+}
+
+/// 文件开头 ID3v2 标签（含尾部）的总长度，没有标签时为 0
+fn id3v2_len(head: &[u8]) -> usize {
+    if head.len() < 10 || &head[0..3] != b"ID3" {
+        return 0;
+    }
+    let size = head[6..10].iter().fold(0usize, |acc, &b| (acc << 7) | (b & 0x7f) as usize);
+    let footer = if head[5] & 0x10 != 0 { 10 } else { 0 };
+    10 + size + footer
+}
+
+/// Ogg 首页的第一个包决定编码
+fn sniff_ogg(data: &[u8]) -> Option<AudioFormat> {
+    let segments = *data.get(26)? as usize;
+    let packet = data.get(27 + segments..)?;
+    if packet.starts_with(b"OpusHead") {
+        Some(AudioFormat::Opus)
+    } else if packet.starts_with(b"\x01vorbis") || packet.starts_with(b"\x7fFLAC") {
+        Some(AudioFormat::Ogg)
+    } else {
+        None
+    }
+}
+
+/// 排除同样使用 ftyp 的图片格式（HEIF/AVIF 等）
+fn sniff_mp4(data: &[u8]) -> Option<AudioFormat> {
+    let brand = data.get(8..12)?;
+    let images: [&[u8]; 9] = [b"heic", b"heix", b"hevc", b"heim", b"heis", b"mif1", b"msf1", b"avif", b"avis"];
+    (!images.contains(&brand)).then_some(AudioFormat::Mp4)
+}
+
+/// EBML 头中的 DocType 区分 Matroska/WebM 与其他 EBML 文件
+fn sniff_matroska(data: &[u8]) -> Option<AudioFormat> {
+    let header = data.get(..64.min(data.len()))?;
+    let has = |doc_type: &[u8]| header.windows(doc_type.len()).any(|w| w == doc_type);
+    (has(b"matroska") || has(b"webm")).then_some(AudioFormat::Matroska)
+}
+
+fn is_adts(data: &[u8]) -> bool {
+    // 同步字 0xFFF，layer 固定为 0，采样率索引不超过 12
+    data.len() >= 7 && data[0] == 0xff && data[1] & 0xf6 == 0xf0 && (data[2] >> 2) & 0xf <= 12
+}
+
+/// 寻找连续三个首尾相接的 MPEG 音频帧，避免把偶然出现的同步字当成 MP3
+fn find_mp3_frames(data: &[u8]) -> bool {
+    (0..data.len().saturating_sub(4)).any(|start| {
+        let mut at = start;
+        for _ in 0..3 {
+            match data.get(at..).and_then(mp3_frame_len) {
+                Some(len) => at += len,
+                None => return false,
+            }
+        }
+        true
+    })
+}
+
+/// 解析 MPEG 音频帧头，返回整帧字节数
+fn mp3_frame_len(header: &[u8]) -> Option<usize> {
+    const BITRATES: [[u32; 15]; 5] = [
+        [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448], // MPEG-1 Layer I
+        [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],    // MPEG-1 Layer II
+        [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],     // MPEG-1 Layer III
+        [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],    // MPEG-2/2.5 Layer I
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],         // MPEG-2/2.5 Layer II/III
+    ];
+    const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+    let &[b0, b1, b2, _] = header.get(..4)? else { return None };
+    if b0 != 0xff || b1 & 0xe0 != 0xe0 {
+        return None;
+    }
+    let version = (b1 >> 3) & 0x3; // 0: 2.5, 2: 2, 3: 1
+    let layer = (b1 >> 1) & 0x3;   // 1: III, 2: II, 3: I
+    let bitrate_index = (b2 >> 4) as usize;
+    let rate_index = ((b2 >> 2) & 0x3) as usize;
+    if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+        return None;
+    }
+
+    let mpeg1 = version == 3;
+    let table = match (mpeg1, layer) {
+        (true, 3) => 0,
+        (true, 2) => 1,
+        (true, _) => 2,
+        (false, 3) => 3,
+        (false, _) => 4,
+    };
+    let bitrate = BITRATES[table][bitrate_index] * 1000;
+    let sample_rate = SAMPLE_RATES[rate_index] >> match version { 3 => 0, 2 => 1, _ => 2 };
+    let padding = ((b2 >> 1) & 0x1) as u32;
+
+    let len = match layer {
+        3 => (12 * bitrate / sample_rate + padding) * 4,
+        1 if !mpeg1 => 72 * bitrate / sample_rate + padding,
+        _ => 144 * bitrate / sample_rate + padding,
+    };
+    Some(len as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 首页只含一个包的 Ogg 页
+    fn ogg_page(packet: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS".to_vec();
+        page.resize(26, 0);
+        page.extend_from_slice(&[1, packet.len() as u8]);
+        page.extend_from_slice(packet);
+        page
+    }
+
+    /// MPEG-1 Layer III 128 kbps 44.1 kHz，每帧 417 字节
+    fn mp3_frames(count: usize) -> Vec<u8> {
+        let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+        frame.resize(417, 0);
+        frame.repeat(count)
+    }
+
+    fn with_header(magic: &[u8], at: usize, len: usize) -> Vec<u8> {
+        let mut data = vec![0; len];
+        data[at..at + magic.len()].copy_from_slice(magic);
+        data
+    }
+
+    #[test]
+    fn containers_by_magic() {
+        assert_eq!(sniff_bytes(b"fLaC\0\0\0\x22"), Some(AudioFormat::Flac));
+        assert_eq!(sniff_bytes(b"RIFF\0\0\0\0WAVEfmt "), Some(AudioFormat::Wav));
+        assert_eq!(sniff_bytes(b"RIFF\0\0\0\0AVI LIST"), None);
+        assert_eq!(sniff_bytes(b"FORM\0\0\0\0AIFC"), Some(AudioFormat::Aiff));
+        assert_eq!(sniff_bytes(b"wvpk\0\0\0\0"), Some(AudioFormat::WavPack));
+        assert_eq!(sniff_bytes(b"MAC \x96\x0f"), Some(AudioFormat::Ape));
+        assert_eq!(sniff_bytes(&with_header(b"ftypM4A ", 4, 16)), Some(AudioFormat::Mp4));
+        // HEIF 图片同样以 ftyp 开头
+        assert_eq!(sniff_bytes(&with_header(b"ftypheic", 4, 16)), None);
+    }
+
+    #[test]
+    fn ogg_by_first_packet() {
+        assert_eq!(sniff_bytes(&ogg_page(b"OpusHead\x01\x02")), Some(AudioFormat::Opus));
+        assert_eq!(sniff_bytes(&ogg_page(b"\x01vorbis\0\0")), Some(AudioFormat::Ogg));
+        assert_eq!(sniff_bytes(&ogg_page(b"\x7fFLAC\x01\0")), Some(AudioFormat::Ogg));
+        // Theora 视频
+        assert_eq!(sniff_bytes(&ogg_page(b"\x80theora")), None);
+    }
+
+    #[test]
+    fn matroska_by_doc_type() {
+        let ebml = [0x1a, 0x45, 0xdf, 0xa3, 0x9f, 0x42, 0x82, 0x88];
+        assert_eq!(sniff_bytes(&[&ebml[..], b"matroska"].concat()), Some(AudioFormat::Matroska));
+        assert_eq!(sniff_bytes(&[&ebml[..4], &[0x42, 0x82, 0x84], b"webm"].concat()), Some(AudioFormat::Matroska));
+        assert_eq!(sniff_bytes(&[&ebml[..], b"unknown!"].concat()), None);
+    }
+
+    #[test]
+    fn raw_streams_need_consistent_frames() {
+        assert_eq!(sniff_bytes(&[0xff, 0xf1, 0x50, 0x80, 0x02, 0x1f, 0xfc]), Some(AudioFormat::Aac));
+        assert_eq!(sniff_bytes(&mp3_frames(3)), Some(AudioFormat::Mp3));
+        // 前面有垃圾数据时仍能找到
+        assert_eq!(sniff_bytes(&[&[0u8; 100][..], &mp3_frames(3)].concat()), Some(AudioFormat::Mp3));
+        // 单个同步字之后不是下一帧
+        assert_eq!(sniff_bytes(&mp3_frames(1)), None);
+        assert_eq!(sniff_bytes(b""), None);
+    }
+
+    #[test]
+    fn sniff_skips_id3v2_and_ignores_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        // 内嵌封面的 ID3v2 标签大于 SNIFF_LEN，扩展名也是错的
+        let tag_len = SNIFF_LEN + 1000;
+        let size = (0..4).rev().map(|i| ((tag_len >> (7 * i)) & 0x7f) as u8).collect::<Vec<_>>();
+        let mut data = [b"ID3\x04\0\0".as_slice(), &size].concat();
+        data.resize(10 + tag_len, 0);
+        data.extend_from_slice(b"fLaC\0\0\0\x22");
+        let path = dir.path().join("song.mp3");
+        std::fs::write(&path, data).unwrap();
+        assert_eq!(sniff(&path), Some(AudioFormat::Flac));
+
+        let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/player/codecs/testdata");
+        assert_eq!(sniff(&testdata.join("stereo16.wv")), Some(AudioFormat::WavPack));
+        assert_eq!(sniff(&testdata.join("stereo16.ape")), Some(AudioFormat::Ape));
+        assert_eq!(sniff(&testdata.join("stereo.opus")), Some(AudioFormat::Opus));
+        assert_eq!(sniff(&dir.path().join("missing.flac")), None);
+    }
+}
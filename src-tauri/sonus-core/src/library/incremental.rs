@@ -0,0 +1,271 @@
+/*
+* Incremental
+* 增量扫描：按文件大小与修改时间跳过未变化的文件，统计一次扫描中新增、更新、移动与消失的曲目，
+* 所有文件处理完后发布扫描报告。
+*/
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use serde::Serialize;
+use tracing::info;
+use crate::database::{connection, query_with_params};
+use crate::events::{EventBus, LibraryEvent};
+
+/// 判断文件是否变化的依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    pub mtime: i64, // 修改时间，Unix 毫秒
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64;
+        Some(Self { size: metadata.len(), mtime })
+    }
+
+    /// CUE 拆分的曲目以镜像的大小、CUE 与镜像中较晚的修改时间为准，任一变化都重新拆分
+    pub fn of_cue(cue: &Path, image: &Path) -> Option<Self> {
+        let cue = Self::of(cue)?;
+        let image = Self::of(image)?;
+        Some(Self { size: image.size, mtime: image.mtime.max(cue.mtime) })
+    }
+}
+
+/// 曲库中已索引的文件；CUE 镜像对应多行，取汇总值
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedFile {
+    pub stamp: Option<FileStamp>, // 旧版本索引的曲目没有修改时间
+    pub missing: bool,
+    pub tracks: usize,
+    pub cue: bool, // 由 CUE 拆分出的曲目
+}
+
+impl IndexedFile {
+    /// 文件未变化，可以跳过
+    pub fn unchanged(&self, stamp: Option<FileStamp>, cue: bool) -> bool {
+        !self.missing && self.cue == cue && stamp.is_some() && self.stamp == stamp
+    }
+}
+
+/// 按文件汇总的查询，条件中以 ?1 代表路径
+fn indexed_sql(condition: &str) -> String {
+    format!(
+        "SELECT file_path, MAX(audio_size), MAX(mtime), MAX(missing), COUNT(*), MAX(start_offset IS NOT NULL)
+         FROM music WHERE {} GROUP BY file_path",
+        condition
+    )
+}
+
+fn indexed_row(row: &rusqlite::Row) -> rusqlite::Result<(String, IndexedFile)> {
+    let size: Option<u64> = row.get(1)?;
+    let mtime: Option<i64> = row.get(2)?;
+    Ok((row.get::<_, String>(0)?, IndexedFile {
+        stamp: size.zip(mtime).map(|(size, mtime)| FileStamp { size, mtime }),
+        missing: row.get::<_, Option<bool>>(3)?.unwrap_or(false),
+        tracks: row.get(4)?,
+        cue: row.get(5)?,
+    }))
+}
+
+/// root 目录下已索引的本地文件；root 为文件时即该文件本身
+pub fn indexed_files(root: &str) -> rusqlite::Result<HashMap<String, IndexedFile>> {
+    let rows = query_with_params(
+        &connection(),
+        &indexed_sql("substr(file_path, 1, length(?1)) = ?1"),
+        &[&root],
+        indexed_row
+    )?;
+    // 前缀相同但不在该目录下的路径（如 D:\Music2 之于 D:\Music）排除在外
+    Ok(rows.into_iter().filter(|(path, _)| Path::new(path).starts_with(root)).collect())
+}
+
+/// 单个文件的索引情况，未索引时返回 None
+pub fn indexed_file(path: &str) -> rusqlite::Result<Option<IndexedFile>> {
+    let rows = query_with_params(&connection(), &indexed_sql("file_path = ?1"), &[&path], indexed_row)?;
+    Ok(rows.into_iter().next().map(|(_, entry)| entry))
+}
+
+/// 一个文件或一首 CUE 曲目的处理结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanOutcome {
+    Added,
+    Updated,
+    Moved { from: String }, // 哈希与已消失的曲目相同，沿用原来的记录
+    Skipped,                // 不是支持的音频
+    Failed,
+}
+
+/// 扫描结束时发布的汇总
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanReport {
+    pub root: String,
+    pub added: usize,
+    pub updated: usize,
+    pub moved: usize,
+    pub removed: usize, // 文件已不存在，曲目被标记为 missing
+    pub unchanged: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// 一次目录扫描的进度；每个待处理的文件持有一张 ScanTicket，全部结束后发布报告
+pub struct ScanSession {
+    pending: AtomicUsize,
+    report: Mutex<ScanReport>,
+    removed: Mutex<Vec<String>>, // 本次扫描标记为消失的路径，之后被识别为移动时不再计入 removed
+    events: EventBus,
+}
+
+impl ScanSession {
+    /// 扫描任务本身占一个名额，提交完所有文件后调用 finish_scan，避免文件处理得比提交快时提前结束
+    pub fn new(root: &str, events: EventBus) -> Arc<Self> {
+        Arc::new(Self {
+            pending: AtomicUsize::new(1),
+            report: Mutex::new(ScanReport { root: root.to_string(), ..Default::default() }),
+            removed: Mutex::new(Vec::new()),
+            events,
+        })
+    }
+
+    /// 为一个待处理的文件领取凭据
+    pub fn ticket(self: &Arc<Self>) -> ScanTicket {
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        ScanTicket { session: Some(self.clone()) }
+    }
+
+    pub fn unchanged(&self, tracks: usize) {
+        self.report.lock().unwrap_or_else(|e| e.into_inner()).unchanged += tracks;
+    }
+
+    pub fn removed(&self, path: &str, tracks: usize) {
+        self.removed.lock().unwrap_or_else(|e| e.into_inner()).push(path.to_string());
+        self.report.lock().unwrap_or_else(|e| e.into_inner()).removed += tracks;
+    }
+
+    pub fn finish_scan(&self) {
+        self.release();
+    }
+
+    fn record(&self, outcome: ScanOutcome) {
+        let mut report = self.report.lock().unwrap_or_else(|e| e.into_inner());
+        match outcome {
+            ScanOutcome::Added => report.added += 1,
+            ScanOutcome::Updated => report.updated += 1,
+            ScanOutcome::Moved { from } => {
+                report.moved += 1;
+                if self.removed.lock().unwrap_or_else(|e| e.into_inner()).contains(&from) {
+                    report.removed = report.removed.saturating_sub(1);
+                }
+            }
+            ScanOutcome::Skipped => report.skipped += 1,
+            ScanOutcome::Failed => report.failed += 1,
+        }
+    }
+
+    fn release(&self) {
+        if self.pending.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        let report = self.report.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        info!(
+            "扫描完成: {}，新增 {}，更新 {}，移动 {}，消失 {}，未变化 {}，跳过 {}，失败 {}",
+            report.root, report.added, report.updated, report.moved,
+            report.removed, report.unchanged, report.skipped, report.failed
+        );
+        self.events.publish(LibraryEvent::ScanFinished(report));
+    }
+}
+
+impl fmt::Debug for ScanSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScanSession")
+            .field("pending", &self.pending)
+            .field("report", &self.report)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 一个文件在扫描流水线中的凭据，随任务传递；未给出结果就被丢弃（任务失败或 panic）时记为失败
+#[derive(Debug)]
+pub struct ScanTicket {
+    session: Option<Arc<ScanSession>>,
+}
+
+impl ScanTicket {
+    /// 任务之间以 Arc<ScanSession> 传递凭据（任务需要可克隆），执行时再恢复为凭据，不重复计数
+    pub fn resume(session: Arc<ScanSession>) -> Self {
+        Self { session: Some(session) }
+    }
+
+    /// 交给下一个任务
+    pub fn forward(mut self) -> Arc<ScanSession> {
+        self.session.take().expect("scan ticket already used")
+    }
+
+    /// 为同一文件拆分出的曲目领取新凭据
+    pub fn split(&self) -> ScanTicket {
+        self.session.as_ref().expect("scan ticket already used").ticket()
+    }
+
+    pub fn finish(mut self, outcome: ScanOutcome) {
+        if let Some(session) = self.session.take() {
+            session.record(outcome);
+            session.release();
+        }
+    }
+
+    /// 处理完毕但不计入报告，如已拆分成曲目的 CUE
+    pub fn done(mut self) {
+        if let Some(session) = self.session.take() {
+            session.release();
+        }
+    }
+}
+
+impl Drop for ScanTicket {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take() {
+            session.record(ScanOutcome::Failed);
+            session.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_is_published_after_last_ticket() {
+        let events = EventBus::new();
+        let mut receiver = events.subscribe();
+        let session = ScanSession::new("D:\\Music", events);
+
+        let added = session.ticket();
+        let moved = session.ticket();
+        let lost = session.ticket();
+        session.unchanged(3);
+        session.removed("D:\\Music\\old.flac", 1);
+        session.removed("D:\\Music\\gone.flac", 1);
+        session.finish_scan();
+
+        added.finish(ScanOutcome::Added);
+        moved.finish(ScanOutcome::Moved { from: "D:\\Music\\old.flac".to_string() });
+        assert!(receiver.try_recv().is_err());
+
+        // 任务 panic 时凭据被丢弃，记为失败并结束扫描
+        drop(lost);
+        let Ok(crate::events::Event::Library(LibraryEvent::ScanFinished(report))) = receiver.try_recv() else {
+            panic!("scan report not published");
+        };
+        assert_eq!(
+            (report.added, report.moved, report.removed, report.unchanged, report.failed),
+            (1, 1, 1, 3, 1)
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use chrono::{DateTime, NaiveDate, NaiveDateTime, ParseError, TimeZone, Utc};
-use rusqlite::{Row};
+use rusqlite::{Connection, Row};
 use rusqlite::types::{Type, ValueRef};
 use serde::{Deserialize, Serialize};
 use tracing::warn;
-use crate::app::database::{connection, query_with_params};
+use crate::database::{connection, execute_with_params, query_with_params};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Track {
@@ -35,6 +35,40 @@ pub struct Track {
     pub hash: String,
     pub disc_total: Option<u16>,
     pub lyrics: Option<String>,
+    pub replaygain_track_gain: Option<f32>,
+    pub replaygain_track_peak: Option<f32>,
+    pub replaygain_album_gain: Option<f32>,
+    pub replaygain_album_peak: Option<f32>,
+    pub start_offset: Option<u64>, // CUE 虚拟曲目在整轨文件中的起点（毫秒）
+    pub end_offset: Option<u64>,   // CUE 虚拟曲目的终点（毫秒），None 表示到文件末尾
+    #[serde(default)]
+    pub unavailable: bool,         // 上次播放时无法打开或解码
+    #[serde(default)]
+    pub mtime: Option<i64>,        // 索引时文件的修改时间（Unix 毫秒），增量扫描据此跳过未变化的文件
+    #[serde(default)]
+    pub missing: bool,             // 重新扫描时文件已不存在
+}
+
+/// Track.path_type 的取值：非本地曲目的 file_path 保存远程地址，由播放器以 Range 请求流式读取
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathType {
+    Local = 0,
+    Http = 1,   // http:// 或 https://
+    WebDav = 2, // dav:// 或 davs://，也可直接使用 http(s):// 地址
+}
+
+impl PathType {
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => PathType::Http,
+            2 => PathType::WebDav,
+            _ => PathType::Local,
+        }
+    }
 }
 
 impl Track {
@@ -68,9 +102,34 @@ impl Track {
             hash: String::new(),
             disc_total: None,
             lyrics: None,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            start_offset: None,
+            end_offset: None,
+            unavailable: false,
+            mtime: None,
+            missing: false,
         }
     }
 
+    /// 远程曲目：以地址中的文件名作为标题，时长等信息在播放时由解码器得到
+    pub fn from_url(url: &str) -> Self {
+        let mut track = Self::new();
+        let scheme = url.split("://").next().unwrap_or("").to_ascii_lowercase();
+        track.path_type = match scheme.as_str() {
+            "dav" | "davs" => PathType::WebDav,
+            _ => PathType::Http,
+        }.as_u8();
+        track.title = url.split(['?', '#']).next()
+            .and_then(|path| path.rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string());
+        track.file_path = url.to_string();
+        track
+    }
+
     fn from_row(row: &Row) -> rusqlite::Result<Self> {
         // 辅助函数：将逗号分隔的字符串转换为 Vec<String>
         fn str_to_vec(s: Option<String>) -> Option<Vec<String>> {
@@ -179,24 +238,101 @@ impl Track {
             hash: row.get(25)?,
             disc_total: row.get(26)?,
             lyrics: row.get(27)?,
+            replaygain_track_gain: row.get(28)?,
+            replaygain_track_peak: row.get(29)?,
+            replaygain_album_gain: row.get(30)?,
+            replaygain_album_peak: row.get(31)?,
+            start_offset: row.get(32)?,
+            end_offset: row.get(33)?,
+            unavailable: row.get::<_, Option<bool>>(34)?.unwrap_or(false),
+            mtime: row.get(35)?,
+            missing: row.get::<_, Option<bool>>(36)?.unwrap_or(false),
         })
     }
 }
 
-pub fn get_all_songs(limit: usize, offset: usize) -> rusqlite::Result<Vec<Track>> {
-    use crate::app::database::connection;
+/// Track::from_row 对应的列顺序；path_type 列声明为 TEXT，写入的数字会存成文本，读取时转回整数
+const TRACK_COLUMNS: &str = r#"
+            id, title, album, artist, album_artist, composer, lyricist, genre,
+            release_date, track_number, disc_number, bpm, duration, cover_art,
+            audio_format, audio_size, bitrate, sample_rate, file_path, create_time,
+            update_time, copyright, remark, CAST(path_type AS INTEGER), is_love, hash, disc_total, lyrics,
+            replaygain_track_gain, replaygain_track_peak, replaygain_album_gain, replaygain_album_peak,
+            start_offset, end_offset, unavailable, mtime, missing"#;
 
+pub fn get_all_songs(limit: usize, offset: usize) -> rusqlite::Result<Vec<Track>> {
     let conn = connection();
     query_with_params(
         &conn,
-        r#"SELECT
-            id, title, album, artist, album_artist, composer, lyricist, genre,
-            release_date, track_number, disc_number, bpm, duration, cover_art,
-            audio_format, audio_size, bitrate, sample_rate, file_path, create_time,
-            update_time, copyright, remark, path_type, is_love, hash, disc_total, lyrics
-           FROM music
-           LIMIT ? OFFSET ?"#,
+        &format!("SELECT {TRACK_COLUMNS} FROM music LIMIT ? OFFSET ?"),
         &[&limit, &offset],
         Track::from_row
     )
-}
\ No newline at end of file
+}
+
+/// 按 id 批量读取曲目，结果保持传入顺序；已不存在的 id 会被跳过
+pub fn get_songs_by_ids(ids: &[usize]) -> rusqlite::Result<Vec<Track>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    songs_by_ids(&connection(), ids)
+}
+
+/// 每条查询最多绑定的 id 数，低于 SQLite 旧版本 999 个参数的上限
+const IDS_PER_QUERY: usize = 500;
+
+fn songs_by_ids(conn: &Connection, ids: &[usize]) -> rusqlite::Result<Vec<Track>> {
+    let mut by_id = std::collections::HashMap::with_capacity(ids.len());
+    for chunk in ids.chunks(IDS_PER_QUERY) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let params: Vec<&dyn rusqlite::ToSql> = chunk.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
+        let found = query_with_params(
+            conn,
+            &format!("SELECT {TRACK_COLUMNS} FROM music WHERE id IN ({placeholders})"),
+            &params,
+            Track::from_row
+        )?;
+        by_id.extend(found.into_iter().filter_map(|track| track.id.map(|id| (id, track))));
+    }
+    Ok(ids.iter().filter_map(|id| by_id.get(id).cloned()).collect())
+}
+
+/// 标记曲目是否可播放：播放失败时置位，之后成功播放时清除
+pub fn set_unavailable(id: usize, unavailable: bool) -> rusqlite::Result<usize> {
+    let conn = connection();
+    execute_with_params(
+        &conn,
+        "UPDATE music SET unavailable = ? WHERE id = ?",
+        &[&unavailable, &id]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::writer::{write_batch, WriteOp};
+
+    #[test]
+    fn songs_by_ids_spans_chunks_in_requested_order() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::database::init_schema(&conn).unwrap();
+        let ops: Vec<WriteOp> = (1..=1200)
+            .map(|id| {
+                let mut track = Track::new();
+                track.file_path = format!("{id}.flac");
+                WriteOp::Upsert { track: Box::new(track), chapters: Vec::new(), ticket: None }
+            })
+            .collect();
+        write_batch(&mut conn, &ops).unwrap();
+
+        // 跨越多个分块，含重复与不存在的 id
+        let mut ids: Vec<usize> = (1..=1200).rev().collect();
+        ids.insert(600, 5000);
+        ids.push(1200);
+        let tracks = songs_by_ids(&conn, &ids).unwrap();
+        let found: Vec<usize> = tracks.iter().map(|track| track.id.unwrap()).collect();
+        let expected: Vec<usize> = ids.into_iter().filter(|&id| id != 5000).collect();
+        assert_eq!(found, expected);
+        assert_eq!(tracks[0].file_path, "1200.flac");
+    }
+}
@@ -0,0 +1,408 @@
+/*
+* Integrity
+* 无损文件完整性校验：用 symphonia 把文件完整解码一遍，FLAC 与 STREAMINFO 中的 MD5 比对，
+* 并报告解码错误与截断，在备份复制之前找出位衰减的文件。
+*/
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use anyhow::anyhow;
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use symphonia::core::{
+    codecs::{DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::FormatOptions,
+    io::{MediaSourceStream, MediaSourceStreamOptions},
+    meta::MetadataOptions,
+    probe::Hint,
+};
+use tracing::{info, warn};
+use super::super::task_queue::task::{Task, TaskType, TaskResult, TaskData, TaskContext, BaseTask};
+use super::format::sniff;
+use crate::database::{connection, execute_with_params, query_with_params};
+use crate::events::LibraryEvent;
+use crate::player::codecs;
+use crate::player::remote;
+use crate::task_queue::TaskStatus;
+
+/// 校验结论
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegrityStatus {
+    Verified,    // 完整解码，且与 FLAC STREAMINFO 中的 MD5 一致
+    Decoded,     // 完整解码无错误，格式不带 MD5 无法比对
+    Mismatch,    // 解码无错误但 MD5 不一致
+    Truncated,   // 解码出的帧数少于文件头声明的帧数
+    DecodeError, // 存在无法解码的数据
+    Unreadable,  // 无法打开或识别
+}
+
+impl IntegrityStatus {
+    fn from_db(value: &str) -> Self {
+        match value {
+            "verified" => IntegrityStatus::Verified,
+            "decoded" => IntegrityStatus::Decoded,
+            "mismatch" => IntegrityStatus::Mismatch,
+            "truncated" => IntegrityStatus::Truncated,
+            "decode_error" => IntegrityStatus::DecodeError,
+            _ => IntegrityStatus::Unreadable,
+        }
+    }
+
+    fn db_value(self) -> &'static str {
+        match self {
+            IntegrityStatus::Verified => "verified",
+            IntegrityStatus::Decoded => "decoded",
+            IntegrityStatus::Mismatch => "mismatch",
+            IntegrityStatus::Truncated => "truncated",
+            IntegrityStatus::DecodeError => "decode_error",
+            IntegrityStatus::Unreadable => "unreadable",
+        }
+    }
+
+    /// 文件是否完好
+    pub fn is_ok(self) -> bool {
+        matches!(self, IntegrityStatus::Verified | IntegrityStatus::Decoded)
+    }
+}
+
+/// 一个文件的校验结果，校验完成后随 LibraryEvent::IntegrityChecked 发布
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub file_path: String,
+    pub status: IntegrityStatus,
+    pub detail: Option<String>,
+}
+
+/// 曲库中保存的校验结果，附带曲目信息以便展示
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityResult {
+    pub track_id: usize,
+    pub title: Option<String>,
+    pub file_path: String,
+    pub status: IntegrityStatus,
+    pub detail: Option<String>,
+    pub checked_at: String,
+}
+
+impl IntegrityResult {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            track_id: row.get(0)?,
+            title: row.get(1)?,
+            file_path: row.get(2)?,
+            status: IntegrityStatus::from_db(&row.get::<_, String>(3)?),
+            detail: row.get(4)?,
+            checked_at: row.get(5)?,
+        })
+    }
+}
+
+/// 完整解码一遍得到的统计
+#[derive(Debug, Default)]
+struct DecodeStats {
+    sample_rate: u32,
+    frames: u64,                  // 实际解码出的帧数
+    expected_frames: Option<u64>, // 文件头声明的帧数
+    tolerance: u64,               // 帧数允许的误差
+    errors: usize,
+    first_error: Option<String>,
+    md5_ok: Option<bool>,         // 解码器没有可比对的 MD5 时为 None
+}
+
+impl DecodeStats {
+    fn record_error(&mut self, error: impl ToString) {
+        self.errors += 1;
+        if self.first_error.is_none() {
+            self.first_error = Some(format!("{} 处: {}", format_frames(self.frames, self.sample_rate), error.to_string()));
+        }
+    }
+
+    /// 按解码错误、截断、MD5 的顺序给出结论，前者通常也会导致后者
+    fn verdict(&self) -> (IntegrityStatus, Option<String>) {
+        if self.errors > 0 {
+            let first = self.first_error.as_deref().unwrap_or_default();
+            return (IntegrityStatus::DecodeError, Some(format!("{} 个数据包解码失败，首个错误在 {}", self.errors, first)));
+        }
+        if let Some(expected) = self.expected_frames {
+            if self.frames + self.tolerance < expected {
+                return (IntegrityStatus::Truncated, Some(format!(
+                    "只能解码到 {}，文件头声明时长 {}",
+                    format_frames(self.frames, self.sample_rate),
+                    format_frames(expected, self.sample_rate)
+                )));
+            }
+        }
+        match self.md5_ok {
+            Some(true) => (IntegrityStatus::Verified, None),
+            Some(false) => (IntegrityStatus::Mismatch, Some("解码结果与 STREAMINFO 中的 MD5 不一致".to_string())),
+            None => (IntegrityStatus::Decoded, None),
+        }
+    }
+}
+
+/// 帧数换算为 分:秒
+fn format_frames(frames: u64, sample_rate: u32) -> String {
+    let secs = frames / sample_rate.max(1) as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// 完整解码文件，统计帧数与错误；无法打开或识别时返回 Err
+fn decode_file(path: &Path) -> anyhow::Result<DecodeStats> {
+    let mss = MediaSourceStream::new(Box::new(File::open(path)?), MediaSourceStreamOptions::default());
+
+    // 扩展名可能与内容不符，以嗅探结果作为格式提示
+    let mut hint = Hint::new();
+    let ext = sniff(path)
+        .map(|f| f.extension())
+        .or_else(|| path.extension().and_then(|e| e.to_str()));
+    if let Some(ext) = ext {
+        hint.with_extension(ext);
+    }
+
+    let mut format = codecs::probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| anyhow!("no default track"))?;
+    if track.codec_params.codec == CODEC_TYPE_NULL {
+        return Err(anyhow!("unsupported codec"));
+    }
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    // verify 让 FLAC 解码器累计解码结果的 MD5，finalize 时与 STREAMINFO 比对
+    let mut decoder = codecs::codecs().make(&params, &DecoderOptions { verify: true })?;
+
+    // MP3 等格式的帧数可能是估算值，允许一个数据包或 1% 的误差
+    let expected_frames = params.n_frames;
+    let tolerance = expected_frames.map_or(0, |n| (n / 100).max(params.max_frames_per_packet.unwrap_or(0)));
+    let mut stats = DecodeStats {
+        sample_rate: params.sample_rate.unwrap_or(0),
+        expected_frames,
+        tolerance,
+        ..Default::default()
+    };
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // 读到文件末尾；文件被截断时同样在这里结束，由帧数判断
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => {
+                stats.record_error(e);
+                break;
+            }
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(buffer) => stats.frames += buffer.frames() as u64,
+            // 单个数据包损坏时跳过，继续检查后面的部分
+            Err(SymphoniaError::DecodeError(e)) => stats.record_error(e),
+            Err(SymphoniaError::IoError(e)) => stats.record_error(e),
+            Err(e) => {
+                stats.record_error(e);
+                break;
+            }
+        }
+    }
+    stats.md5_ok = decoder.finalize().verify_ok;
+    Ok(stats)
+}
+
+/// 校验一个本地文件
+pub fn verify_file(path: &Path) -> (IntegrityStatus, Option<String>) {
+    match decode_file(path) {
+        Ok(stats) => stats.verdict(),
+        Err(e) => (IntegrityStatus::Unreadable, Some(e.to_string())),
+    }
+}
+
+/// 把校验结果写入引用该文件的所有曲目（CUE 拆出的曲目共用同一个镜像文件）
+pub fn save_result(report: &IntegrityReport) -> rusqlite::Result<usize> {
+    execute_with_params(
+        &connection(),
+        "INSERT INTO integrity_check (music_id, status, detail, checked_at)
+         SELECT id, ?, ?, CURRENT_TIMESTAMP FROM music WHERE file_path = ?
+         ON CONFLICT(music_id) DO UPDATE SET status = excluded.status, detail = excluded.detail, checked_at = excluded.checked_at",
+        &[&report.status.db_value(), &report.detail, &report.file_path],
+    )
+}
+
+pub fn get_result(track_id: usize) -> rusqlite::Result<Option<IntegrityResult>> {
+    let found = query_with_params(
+        &connection(),
+        "SELECT i.music_id, m.title, m.file_path, i.status, i.detail, i.checked_at
+         FROM integrity_check i JOIN music m ON m.id = i.music_id
+         WHERE i.music_id = ?",
+        &[&track_id],
+        IntegrityResult::from_row
+    )?;
+    Ok(found.into_iter().next())
+}
+
+/// 所有校验结果，最近校验的在前；problems_only 时只返回有问题的文件
+pub fn get_results(problems_only: bool) -> rusqlite::Result<Vec<IntegrityResult>> {
+    query_with_params(
+        &connection(),
+        "SELECT i.music_id, m.title, m.file_path, i.status, i.detail, i.checked_at
+         FROM integrity_check i JOIN music m ON m.id = i.music_id
+         WHERE ? = 0 OR i.status NOT IN ('verified', 'decoded')
+         ORDER BY i.checked_at DESC, i.music_id",
+        &[&problems_only],
+        IntegrityResult::from_row
+    )
+}
+
+/// 曲库中所有需要校验的本地文件，同一文件只出现一次；远程曲目不在本地备份范围内，跳过
+pub fn library_files() -> rusqlite::Result<Vec<String>> {
+    let paths = query_with_params(
+        &connection(),
+        "SELECT DISTINCT file_path FROM music ORDER BY file_path",
+        &[],
+        |row| row.get::<_, String>(0)
+    )?;
+    Ok(paths.into_iter().filter(|path| !remote::is_remote(path)).collect())
+}
+
+/// 完整性校验任务：完整解码一个文件并保存结果
+#[derive(Debug)]
+pub struct IntegrityCheckTask {
+    base: BaseTask,
+}
+
+impl IntegrityCheckTask {
+    /// 创建新的完整性校验任务
+    pub fn new(path: String) -> Self {
+        Self {
+            base: BaseTask::new(TaskType::IntegrityCheck, Some(path)),
+        }
+    }
+}
+
+impl Task for IntegrityCheckTask {
+    fn id(&self) -> &str {
+        self.base.id()
+    }
+
+    fn task_type(&self) -> TaskType {
+        self.base.task_type()
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.base.path()
+    }
+
+    fn execute(&mut self, context: &TaskContext) -> tokio::task::JoinHandle<TaskResult> {
+        self.set_status(TaskStatus::InProgress);
+        let path = self.base.path().unwrap_or("").to_string();
+        let events = context.events().clone();
+
+        tokio::spawn(async move {
+            // 完整解码耗时较长，放到阻塞线程池中进行
+            let file = path.clone();
+            let (status, detail) = match tokio::task::spawn_blocking(move || verify_file(Path::new(&file))).await {
+                Ok(verdict) => verdict,
+                Err(e) => return TaskResult::Failure(format!("校验 {} 失败: {}", path, e)),
+            };
+            if status.is_ok() {
+                info!("完整性校验通过: {:?}, 文件: {}", status, path);
+            } else {
+                warn!("完整性校验发现问题: {:?} {:?}, 文件: {}", status, detail, path);
+            }
+
+            let report = IntegrityReport { file_path: path.clone(), status, detail };
+            if let Err(e) = save_result(&report) {
+                return TaskResult::Failure(format!("保存校验结果失败: {}", e));
+            }
+            events.publish(LibraryEvent::IntegrityChecked(report));
+            TaskResult::Success(TaskData::String(format!("{} 校验结果: {:?}", path, status)))
+        })
+    }
+
+    fn status(&self) -> TaskStatus {
+        self.base.status()
+    }
+
+    fn set_status(&mut self, status: TaskStatus) {
+        self.base.set_status(status);
+    }
+
+    fn clone_box(&self) -> Box<dyn Task> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for IntegrityCheckTask {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16 位立体声 PCM WAV，文件头声明 declared 帧，实际写入 written 帧
+    fn wav(declared: u32, written: u32) -> tempfile::NamedTempFile {
+        let data_len = declared * 4;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&44100u32.to_le_bytes());
+        bytes.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for i in 0..written {
+            let sample = ((i % 100) as i16 * 300).to_le_bytes();
+            bytes.extend_from_slice(&sample);
+            bytes.extend_from_slice(&sample);
+        }
+        let file = tempfile::Builder::new().suffix(".wav").tempfile().unwrap();
+        std::fs::write(file.path(), bytes).unwrap();
+        file
+    }
+
+    #[test]
+    fn complete_and_truncated_files() {
+        let complete = wav(44100, 44100);
+        assert_eq!(verify_file(complete.path()), (IntegrityStatus::Decoded, None));
+
+        let truncated = wav(44100 * 10, 44100);
+        let (status, detail) = verify_file(truncated.path());
+        assert_eq!(status, IntegrityStatus::Truncated);
+        assert_eq!(detail.as_deref(), Some("只能解码到 0:01，文件头声明时长 0:10"));
+
+        let garbage = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(garbage.path(), [0u8; 64]).unwrap();
+        assert_eq!(verify_file(garbage.path()).0, IntegrityStatus::Unreadable);
+    }
+
+    #[test]
+    fn verdict_prefers_decode_errors_over_checksum() {
+        let mut stats = DecodeStats { sample_rate: 44100, frames: 44100 * 75, expected_frames: Some(44100 * 75), ..Default::default() };
+        stats.md5_ok = Some(true);
+        assert_eq!(stats.verdict().0, IntegrityStatus::Verified);
+        stats.md5_ok = Some(false);
+        assert_eq!(stats.verdict().0, IntegrityStatus::Mismatch);
+
+        stats.record_error("invalid crc");
+        stats.record_error("invalid sync");
+        assert_eq!(
+            stats.verdict(),
+            (IntegrityStatus::DecodeError, Some("2 个数据包解码失败，首个错误在 1:15 处: invalid crc".to_string()))
+        );
+    }
+}
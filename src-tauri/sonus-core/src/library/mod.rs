@@ -0,0 +1,10 @@
+pub mod scanner;
+pub mod chapters;
+pub mod cue;
+pub mod format;
+pub mod incremental;
+pub mod index;
+pub mod integrity;
+pub mod search;
+pub mod watcher;
+pub mod writer;
\ No newline at end of file
@@ -0,0 +1,1020 @@
+/*
+* Scanner
+* This module is responsible for scanning the device, WebDAV and NAS for new tracks.
+*/
+use super::super::task_queue::task::{Task, TaskType, TaskResult, TaskData, TaskContext, BaseTask, FileMetadata};
+use super::chapters::{matroska_duration, read_chapters, Chapter};
+use super::cue::{is_cue_file, CueSheet, CueTrack};
+use super::format::{sniff, AudioFormat};
+use super::incremental::{indexed_files, FileStamp, ScanOutcome, ScanSession, ScanTicket};
+use super::index::Track;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+use tracing::{info, debug};
+use async_recursion::async_recursion;
+use crate::task_queue::TaskStatus;
+use base64::engine::{general_purpose, Engine as _};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+#[derive(Debug)]
+pub struct DirectoryScanTask {
+    base: BaseTask,
+}
+
+impl DirectoryScanTask {
+    /// 创建新的目录扫描任务
+    pub fn new(path: String) -> Self {
+        Self {
+            base: BaseTask::new(TaskType::DirectoryScan, Some(path)),
+        }
+    }
+
+    /// 递归扫描目录
+    #[async_recursion]
+    async fn scan_directory(path: &str) -> Vec<String> {
+        info!("准备扫描目录: {:?}", path);
+        let mut result = Vec::new();
+        let path_buf = PathBuf::from(path);
+
+        if !path_buf.exists() {
+            info!("Path not exists: {}", path);
+            return result;
+        }
+        info!("Path exists: {}", path);
+        info!("准备读取目录内容");
+        // 读取目录内容（返回ReadDir，它实现了Stream）
+        match fs::read_dir(&path_buf).await {
+            Ok(mut entries) => {
+                info!("目录内容读取成功");
+                // 异步遍历：使用StreamExt的next()方法
+                while let Ok(Some(entry)) = entries.next_entry().await {
+                    let entry_path = entry.path();
+                    info!("准备获取元数据: {:?}", entry_path);
+                    let metadata = match entry.metadata().await {
+                        Ok(m) => {
+                            info!("元数据获取成功: {:?}", m);
+                            m
+                        },
+                        Err(e) => {
+                            eprintln!("获取元数据失败: {:?}, 错误: {}", entry_path, e);
+                            continue;
+                        }
+                    };
+
+                    let path_str = entry_path.to_string_lossy().to_string();
+                    if metadata.is_dir() {
+                        // 递归扫描子目录
+                        info!("准备递归扫描子目录: {:?}", path_str);
+                        let sub_files = DirectoryScanTask::scan_directory(&path_str).await;
+                        info!("子目录扫描完成，共发现 {} 个文件", sub_files.len());
+                        result.extend(sub_files);
+                    } else if metadata.is_file() {
+                        info!("发现文件: {:?}", path_str);
+                        result.push(path_str);
+                    }
+                }
+            }
+            Err(e) => eprintln!("读取目录失败: {}, 错误: {}", path, e),
+        }
+
+        result
+    }
+}
+
+impl Task for DirectoryScanTask {
+    fn id(&self) -> &str {
+        self.base.id()
+    }
+
+    fn task_type(&self) -> TaskType {
+        self.base.task_type()
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.base.path()
+    }
+
+    fn execute(&mut self, context: &TaskContext) -> tokio::task::JoinHandle<TaskResult> {
+        self.set_status(TaskStatus::InProgress);
+        info!("目录扫描任务开始执行");
+        let path = self.base.path().unwrap_or("").to_string();
+        let context = context.clone();
+        // let self_clone = self.clone();
+        info!("目录扫描任务路径: {:?}", path);
+        tokio::spawn(async move {
+            // 目录无法访问（如移动硬盘未连接）时不能把其中的曲目都当作已删除
+            if !Path::new(&path).is_dir() {
+                return TaskResult::Failure(format!("目录不存在或无法访问: {}", path));
+            }
+
+            // 执行目录扫描
+            let files = DirectoryScanTask::scan_directory(&path).await;
+            info!("目录扫描任务完成，共发现 {} 个文件", files.len());
+            // 克隆一份用于循环，避免所有权转移
+            let files_clone = files.clone();
+
+            // 已索引的文件按大小与修改时间判断是否变化，未变化的不再提取元数据
+            let indexed = match indexed_files(&path) {
+                Ok(indexed) => indexed,
+                Err(e) => return TaskResult::Failure(format!("读取曲库失败: {}", e)),
+            };
+            let scan = ScanSession::new(&path, context.events().clone());
+
+            // CUE 引用的整轨镜像由 CueSheetTask 拆分成虚拟曲目，镜像本身不再单独建索引；
+            // CUE 中的文件名大小写常与实际不符，按小写比较
+            let (cue_files, files): (Vec<String>, Vec<String>) = files.into_iter().partition(|f| is_cue_file(f));
+            let mut images = HashSet::new();
+            for cue_path in cue_files {
+                match CueSheet::load(Path::new(&cue_path)) {
+                    Ok(sheet) => {
+                        let existing: Vec<&Path> = sheet.image_paths()
+                            .filter(|p| p.is_file())
+                            .collect();
+                        if existing.is_empty() {
+                            info!("CUE 引用的文件均不存在，跳过: {:?}", cue_path);
+                            continue;
+                        }
+                        images.extend(existing.iter().map(|p| p.to_string_lossy().to_lowercase()));
+
+                        // 所有镜像都已拆分索引且未变化时跳过
+                        let unchanged: Option<usize> = existing.iter()
+                            .map(|image| {
+                                let entry = indexed.get(image.to_string_lossy().as_ref())?;
+                                entry.unchanged(FileStamp::of_cue(Path::new(&cue_path), image), true).then_some(entry.tracks)
+                            })
+                            .sum();
+                        match unchanged {
+                            Some(tracks) => scan.unchanged(tracks),
+                            None => {
+                                let cue_task = CueSheetTask::new(cue_path).with_scan(Some(scan.ticket()));
+                                context.submit_task(Box::new(cue_task)).await;
+                            }
+                        }
+                    }
+                    Err(e) => eprintln!("读取 CUE 失败: {}, 错误: {}", cue_path, e),
+                }
+            }
+
+            // 使用克隆体进行循环（转移克隆体的所有权）
+            for file_path in files.into_iter().filter(|f| !images.contains(&f.to_lowercase())) {
+                if let Some(entry) = indexed.get(&file_path) {
+                    if entry.unchanged(FileStamp::of(Path::new(&file_path)), false) {
+                        scan.unchanged(entry.tracks);
+                        continue;
+                    }
+                }
+                info!("准备提交扩展名检查任务: {:?}", file_path);
+                let ext_check_task = Box::new(ExtensionCheckTask::new(file_path).with_scan(Some(scan.ticket())));
+
+                info!("扩展名检查任务创建成功");
+                context.submit_task(ext_check_task).await;
+                info!("扩展名检查任务提交成功");
+            }
+
+            // 曲库中有、磁盘上已没有的文件标记为 missing；之后在别处出现时识别为移动
+            let found: HashSet<&String> = files_clone.iter().collect();
+            for (file_path, entry) in &indexed {
+                if !entry.missing && !found.contains(file_path) {
+                    scan.removed(file_path, entry.tracks);
+                    context.writer().mark_missing(file_path.clone());
+                }
+            }
+            scan.finish_scan();
+
+            // 原始files未被移动，可正常使用
+            TaskResult::Success(TaskData::PathList(files_clone))
+        })
+    }
+
+    fn status(&self) -> TaskStatus {
+        self.base.status()
+    }
+
+    fn set_status(&mut self, status: TaskStatus) {
+        self.base.set_status(status);
+    }
+
+    fn clone_box(&self) -> Box<dyn Task> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for DirectoryScanTask {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone()
+        }
+    }
+}
+
+/// 格式检查任务：按文件内容判断是否为支持的音频，扩展名错误或缺失时同样识别
+#[derive(Debug)]
+pub struct ExtensionCheckTask {
+    base: BaseTask,
+    scan: Option<Arc<ScanSession>>, // 所属的目录扫描，执行时恢复为该文件的 ScanTicket
+}
+
+impl ExtensionCheckTask {
+    /// 创建新的格式检查任务
+    pub fn new(path: String) -> Self {
+        Self {
+            base: BaseTask::new(TaskType::ExtensionCheck, Some(path)),
+            scan: None,
+        }
+    }
+
+    /// 作为目录扫描的一部分执行，结果计入扫描报告
+    pub fn with_scan(mut self, ticket: Option<ScanTicket>) -> Self {
+        self.scan = ticket.map(ScanTicket::forward);
+        self
+    }
+}
+
+impl Task for ExtensionCheckTask {
+    fn id(&self) -> &str {
+        self.base.id()
+    }
+
+    fn task_type(&self) -> TaskType {
+        self.base.task_type()
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.base.path()
+    }
+
+    fn execute(&mut self, context: &TaskContext) -> tokio::task::JoinHandle<TaskResult> {
+        self.set_status(TaskStatus::InProgress);
+        let path = self.base.path().unwrap_or("").to_string();
+        let ticket = self.scan.take().map(ScanTicket::resume);
+        let context = context.clone();
+
+        tokio::spawn(async move {
+            // 读取文件头识别格式
+            let format = sniff(Path::new(&path));
+            info!("格式检查结果: {:?}, 文件: {:?}", format, path);
+            if format.is_some() {
+                // 如果是支持的音频，创建元数据提取任务
+                let header_check_task = Box::new(MetadataExtractionTask::new(path.clone()).with_scan(ticket));
+                context.submit_task(header_check_task).await;
+
+                TaskResult::Continue(TaskData::Path(path))
+            } else {
+                if let Some(ticket) = ticket {
+                    ticket.finish(ScanOutcome::Skipped);
+                }
+                TaskResult::Success(TaskData::String(format!("不支持的文件类型: {}", path)))
+            }
+        })
+    }
+
+    fn status(&self) -> TaskStatus {
+        self.base.status()
+    }
+
+    fn set_status(&mut self, status: TaskStatus) {
+        self.base.set_status(status);
+    }
+
+    fn clone_box(&self) -> Box<dyn Task> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for ExtensionCheckTask {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            scan: self.scan.clone(),
+        }
+    }
+}
+
+/// 元数据提取任务：获取音频文件的元数据
+#[derive(Debug)]
+pub struct MetadataExtractionTask {
+    base: BaseTask,
+    scan: Option<Arc<ScanSession>>, // 所属的目录扫描，执行时恢复为该文件的 ScanTicket
+}
+
+impl MetadataExtractionTask {
+    /// 创建新的元数据提取任务
+    pub fn new(path: String) -> Self {
+        Self {
+            base: BaseTask::new(TaskType::MetadataExtraction, Some(path)),
+            scan: None,
+        }
+    }
+
+    /// 作为目录扫描的一部分执行，结果计入扫描报告
+    pub fn with_scan(mut self, ticket: Option<ScanTicket>) -> Self {
+        self.scan = ticket.map(ScanTicket::forward);
+        self
+    }
+
+    /// 提取音频文件的元数据；allow_untagged 为 true 时没有标签的文件也返回结果（CUE 镜像常无标签）
+    async fn extract_metadata(path: &str, allow_untagged: bool) -> TaskResult {
+        use lofty::prelude::*;
+        use lofty::probe::Probe;
+        use std::path::Path;
+
+        let path_str = Path::new(&path);
+
+        // 监视到的文件可能在处理前已被删除或改名
+        if !path_str.is_file() {
+            let err_msg = format!("文件不存在: {}", path);
+            info!("{}", err_msg);
+            return TaskResult::Failure(err_msg);
+        }
+
+        // lofty 不支持 Matroska，按无标签文件建立索引
+        if sniff(path_str) == Some(AudioFormat::Matroska) {
+            return Self::untagged_matroska(path);
+        }
+
+        // 扩展名可能与内容不符，按文件内容判断类型；仍在写入或已损坏的文件读取失败
+        let read = || -> lofty::error::Result<lofty::file::TaggedFile> {
+            Probe::open(path)?.guess_file_type()?.read()
+        };
+        let tagged_file = match read() {
+            Ok(tagged_file) => tagged_file,
+            Err(e) => {
+                let err_msg = format!("无法读取文件: {}, 错误: {}", path, e);
+                info!("{}", err_msg);
+                return TaskResult::Failure(err_msg);
+            }
+        };
+
+        let empty_tag;
+        let tag = match tagged_file.primary_tag() {
+            Some(primary_tag) => primary_tag,
+            None if allow_untagged => {
+                empty_tag = lofty::tag::Tag::new(tagged_file.primary_tag_type());
+                &empty_tag
+            },
+            None => {
+                let err_msg = format!("文件没有任何标签: {}", path);
+                info!("{}", err_msg);
+                return TaskResult::Failure(err_msg);
+            },
+        };
+
+        let title = tag.title().map(|s| s.to_string());
+        let album = tag.album().map(|s| s.to_string());
+        let artist = {
+            // 优先获取 TrackArtists 列表
+            let v: Vec<String> = tag.get_strings(&ItemKey::TrackArtists)
+                .map(|s| s.to_string())
+                .collect();
+            if !v.is_empty() {
+                Some(v)
+            } else {
+                // 其次获取 TrackArtists 列表（这里可能和v是同一来源，根据实际情况确认）
+                let v1: Vec<String> = tag.get_strings(&ItemKey::TrackArtists)
+                    .map(|s| s.to_string())
+                    .collect();
+                if !v1.is_empty() {
+                    Some(v1)
+                } else {
+                    // 最后获取 TrackArtist 单个值
+                    let v2: String = tag.get_string(&ItemKey::TrackArtist)
+                        .map(|s| s.to_string())
+                        .unwrap_or_default();
+                    if !v2.is_empty() {
+                        Some(vec![v2])  // 统一返回Vec<String>类型
+                    } else {
+                        None
+                    }
+                }
+            }
+        };
+        let album_artist = {
+            let v: String = tag.get_string(&ItemKey::AlbumArtist).map(|s| s.to_string()).unwrap_or_default();
+            if v.is_empty() { None } else { Some(v) }
+        };
+        let composer = {
+            let v: Vec<String> = tag.get_strings(&ItemKey::Composer).map(|s| s.to_string()).collect();
+            if v.is_empty() { None } else { Some(v) }
+        };
+        let lyricist = {
+            let v: Vec<String> = tag.get_strings(&ItemKey::Lyricist).map(|s| s.to_string()).collect();
+            if v.is_empty() { None } else { Some(v) }
+        };
+        let genre = {
+            let v: Vec<String> = tag.get_strings(&ItemKey::Genre).map(|s| s.to_string()).collect();
+            if v.is_empty() { None } else { Some(v) }
+        };
+        let release_date = tag.get_string(&ItemKey::ReleaseDate).map(|s| s.to_string()).and_then(|date_str| DateTime::parse_from_rfc3339(&date_str).map(|dt| dt.with_timezone(&Utc)).ok());
+        // 数字标签可能写成 "3/12"、"120.5" 等形式，无法解析时留空
+        let track_number = tag.get_string(&ItemKey::TrackNumber).and_then(|s| s.trim().parse::<u16>().ok());
+        let disc_number = tag.get_string(&ItemKey::DiscNumber).and_then(|s| s.trim().parse().ok());
+        let disc_total = tag.get_string(&ItemKey::DiscTotal).and_then(|s| s.trim().parse().ok());
+        let bpm = tag.get_string(&ItemKey::Bpm).and_then(|s| s.trim().parse::<u16>().ok());
+        let duration = tagged_file.properties().duration().as_secs() as u32;
+        let cover_art = {
+            let pictures = tag.pictures();
+            if pictures.is_empty() {
+                None
+            } else {
+                let mut s = String::from("data:image/png;base64,");
+                s.push_str(&bytes_to_base64(pictures[0].data()));
+                Some(vec!(s))
+            }
+        };
+        let audio_format = sniff(path_str).map(|f| f.extension().to_string());
+        let bitrate = tagged_file.properties().audio_bitrate();
+        let sample_rate = tagged_file.properties().sample_rate();
+        let file_path = path.to_string();
+        let create_time = None;
+        let update_time = None;
+        let copyright = tag.get_string(&ItemKey::CopyrightMessage).map(|s| s.to_string());
+        let remark = tag.get_string(&ItemKey::Comment).map(|s| s.to_string());
+        let path_type = 0;
+        let is_love = 0;
+        let lyrics = tag.get_string(&ItemKey::Lyrics).map(|s| s.to_string());
+        let (audio_size, hash) = match get_file_size(path).and_then(|size| Ok((size, file_hash(path_str)?))) {
+            Ok(read) => read,
+            Err(e) => return TaskResult::Failure(format!("无法读取文件: {}, 错误: {}", path, e)),
+        };
+        let replaygain_track_gain = read_replay_gain(&tagged_file, ItemKey::ReplayGainTrackGain, "REPLAYGAIN_TRACK_GAIN");
+        let replaygain_track_peak = read_replay_gain(&tagged_file, ItemKey::ReplayGainTrackPeak, "REPLAYGAIN_TRACK_PEAK");
+        let replaygain_album_gain = read_replay_gain(&tagged_file, ItemKey::ReplayGainAlbumGain, "REPLAYGAIN_ALBUM_GAIN");
+        let replaygain_album_peak = read_replay_gain(&tagged_file, ItemKey::ReplayGainAlbumPeak, "REPLAYGAIN_ALBUM_PEAK");
+
+        let metadata = TaskData::FileMetadata(Box::new(FileMetadata {
+            title,
+            album,
+            artist,
+            album_artist,
+            composer,
+            lyricist,
+            genre,
+            release_date,
+            track_number,
+            disc_number,
+            disc_total,
+            bpm,
+            duration,
+            cover_art,
+            audio_format,
+            audio_size,
+            bitrate,
+            sample_rate,
+            file_path,
+            create_time,
+            update_time,
+            copyright,
+            remark,
+            path_type,
+            is_love,
+            lyrics,
+            hash,
+            replaygain_track_gain,
+            replaygain_track_peak,
+            replaygain_album_gain,
+            replaygain_album_peak,
+            start_offset: None,
+            end_offset: None,
+            chapters: read_chapters(path_str),
+            mtime: FileStamp::of(path_str).map(|stamp| stamp.mtime),
+        }));
+
+        info!("Metadata: {:?}", metadata);
+
+        TaskResult::Success(metadata)
+    }
+
+    /// Matroska 音频：标题取文件名，时长取自 Segment Info，章节照常读取
+    fn untagged_matroska(path: &str) -> TaskResult {
+        let path_str = Path::new(path);
+        let (audio_size, hash) = match get_file_size(path).and_then(|size| Ok((size, file_hash(path_str)?))) {
+            Ok(read) => read,
+            Err(e) => return TaskResult::Failure(format!("无法读取文件: {}, 错误: {}", path, e)),
+        };
+        let metadata = TaskData::FileMetadata(Box::new(FileMetadata {
+            title: path_str.file_stem().map(|s| s.to_string_lossy().to_string()),
+            album: None,
+            artist: None,
+            album_artist: None,
+            composer: None,
+            lyricist: None,
+            genre: None,
+            release_date: None,
+            track_number: None,
+            disc_number: None,
+            disc_total: None,
+            bpm: None,
+            duration: matroska_duration(path_str).map_or(0, |d| d.as_secs() as u32),
+            cover_art: None,
+            audio_format: Some(AudioFormat::Matroska.extension().to_string()),
+            audio_size,
+            bitrate: None,
+            sample_rate: None,
+            file_path: path.to_string(),
+            create_time: None,
+            update_time: None,
+            copyright: None,
+            remark: None,
+            path_type: 0,
+            is_love: 0,
+            lyrics: None,
+            hash,
+            replaygain_track_gain: None,
+            replaygain_track_peak: None,
+            replaygain_album_gain: None,
+            replaygain_album_peak: None,
+            start_offset: None,
+            end_offset: None,
+            chapters: read_chapters(path_str),
+            mtime: FileStamp::of(path_str).map(|stamp| stamp.mtime),
+        }));
+
+        info!("Metadata: {:?}", metadata);
+
+        TaskResult::Success(metadata)
+    }
+}
+
+/// 读取 ReplayGain 标签（ID3 TXXX / Vorbis Comment / MP4 freeform），
+/// 先按 lofty 的通用键查找，再按原始键名忽略大小写匹配；值形如 "-6.54 dB" 或 "0.988"
+fn read_replay_gain(tagged_file: &lofty::file::TaggedFile, key: lofty::tag::ItemKey, raw_key: &str) -> Option<f32> {
+    use lofty::prelude::*;
+
+    tagged_file.tags().iter().find_map(|tag| {
+        let value = tag.get_string(&key).or_else(|| {
+            tag.items().find_map(|item| match item.key() {
+                ItemKey::Unknown(name) if name.to_ascii_uppercase().ends_with(raw_key) => item.value().text(),
+                _ => None,
+            })
+        })?;
+        let number = value.trim().trim_end_matches(|c: char| c.is_alphabetic() || c.is_whitespace());
+        number.parse::<f32>().ok().filter(|v| v.is_finite())
+    })
+}
+
+fn bytes_to_base64(bytes: &[u8]) -> String {
+    general_purpose::STANDARD.encode(bytes)
+}
+
+/// 分块计算文件内容的 MD5，不把整个文件读入内存
+fn file_hash(path: &Path) -> std::io::Result<String> {
+    let mut context = md5::Context::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut context)?;
+    Ok(format!("{:x}", context.finalize()))
+}
+
+fn get_file_size(filepath: &str) -> std::io::Result<u64> {
+    Ok(std::fs::metadata(filepath)?.len())
+}
+
+impl Task for MetadataExtractionTask {
+    fn id(&self) -> &str {
+        self.base.id()
+    }
+
+    fn task_type(&self) -> TaskType {
+        self.base.task_type()
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.base.path()
+    }
+
+    fn execute(&mut self, context: &TaskContext) -> tokio::task::JoinHandle<TaskResult> {
+        self.set_status(TaskStatus::InProgress);
+        let path = self.base.path().unwrap_or("").to_string();
+        let ticket = self.scan.take().map(ScanTicket::resume);
+        let context = context.clone();
+
+        // 提取过程中 panic 时 ticket 随之丢弃，在扫描报告中记为失败
+        tokio::spawn(async move {
+            // 提取元数据
+            match Self::extract_metadata(&path, false).await {
+                TaskResult::Success(metadata) => {
+                    // 提取成功，交给写入线程与其他曲目一起批量写入
+                    if let Some((track, chapters)) = into_track(metadata.clone()) {
+                        context.writer().upsert(track, chapters, ticket);
+                    }
+                    TaskResult::Success(metadata)
+                }
+                TaskResult::Failure(err) => {
+                    // 提取失败，直接返回错误
+                    TaskResult::Failure(err)
+                }
+                TaskResult::Continue(_) => {
+                    // 理论上不会走到这里，保持完整性
+                    TaskResult::Failure("Unexpected Continue result".to_string())
+                }
+            }
+        })
+    }
+
+    fn status(&self) -> TaskStatus {
+        self.base.status()
+    }
+
+    fn set_status(&mut self, status: TaskStatus) {
+        self.base.set_status(status);
+    }
+
+    fn clone_box(&self) -> Box<dyn Task> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for MetadataExtractionTask {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            scan: self.scan.clone(),
+        }
+    }
+}
+
+/// CUE 拆分任务：把整轨镜像按 CUE 拆成带起止偏移的虚拟曲目
+#[derive(Debug)]
+pub struct CueSheetTask {
+    base: BaseTask,
+    scan: Option<Arc<ScanSession>>, // 所属的目录扫描，执行时恢复为该文件的 ScanTicket
+}
+
+impl CueSheetTask {
+    /// 创建新的 CUE 拆分任务
+    pub fn new(path: String) -> Self {
+        Self {
+            base: BaseTask::new(TaskType::CueSheet, Some(path)),
+            scan: None,
+        }
+    }
+
+    /// 作为目录扫描的一部分执行，结果计入扫描报告
+    pub fn with_scan(mut self, ticket: Option<ScanTicket>) -> Self {
+        self.scan = ticket.map(ScanTicket::forward);
+        self
+    }
+
+    /// 读取 CUE 及其引用的镜像文件，返回镜像路径与每一轨的元数据
+    async fn split_tracks(path: &str) -> Result<(Vec<String>, Vec<TaskData>), String> {
+        let sheet = CueSheet::load(Path::new(path)).map_err(|e| e.to_string())?;
+        let mut images = Vec::new();
+        let mut tracks = Vec::new();
+
+        for file in &sheet.files {
+            let image_path = file.path.to_string_lossy().to_string();
+            if !file.path.is_file() {
+                info!("CUE 引用的文件不存在: {:?}", image_path);
+                continue;
+            }
+            let mut image = match MetadataExtractionTask::extract_metadata(&image_path, true).await {
+                TaskResult::Success(metadata) => metadata,
+                other => {
+                    info!("读取镜像元数据失败: {:?}, 结果: {:?}", image_path, other);
+                    continue;
+                }
+            };
+            // 修改 CUE 也要重新拆分，修改时间取两者中较晚的
+            if let TaskData::FileMetadata(image) = &mut image {
+                image.mtime = FileStamp::of_cue(Path::new(path), &file.path).map(|stamp| stamp.mtime);
+            }
+            tracks.extend(file.tracks.iter().map(|track| Self::track_metadata(&sheet, track, &image)));
+            images.push(image_path);
+        }
+
+        Ok((images, tracks))
+    }
+
+    /// 标题、艺术家等取自 CUE，格式、码率、封面、哈希等沿用镜像文件
+    fn track_metadata(sheet: &CueSheet, track: &CueTrack, image: &TaskData) -> TaskData {
+        use chrono::TimeZone;
+
+        let mut metadata = image.clone();
+        if let TaskData::FileMetadata(file) = &mut metadata {
+            let FileMetadata {
+                title,
+                album,
+                artist,
+                album_artist,
+                composer,
+                genre,
+                release_date,
+                track_number,
+                duration,
+                replaygain_track_gain,
+                replaygain_track_peak,
+                replaygain_album_gain,
+                replaygain_album_peak,
+                start_offset,
+                end_offset,
+                chapters,
+                ..
+            } = file.as_mut();
+            // 镜像的章节时间相对于整个文件，不适用于拆分出的曲目
+            chapters.clear();
+            *title = Some(track.title.clone().unwrap_or_else(|| format!("Track {:02}", track.number)));
+            if sheet.title.is_some() {
+                *album = sheet.title.clone();
+            }
+            if let Some(performer) = track.performer.as_ref().or(sheet.performer.as_ref()) {
+                *artist = Some(vec![performer.clone()]);
+            }
+            if sheet.performer.is_some() {
+                *album_artist = sheet.performer.clone();
+            }
+            if let Some(songwriter) = track.songwriter.as_ref().or(sheet.songwriter.as_ref()) {
+                *composer = Some(vec![songwriter.clone()]);
+            }
+            if let Some(value) = &sheet.genre {
+                *genre = Some(vec![value.clone()]);
+            }
+            // DATE 通常只有年份
+            if let Some(date) = sheet.date.as_deref()
+                .and_then(|d| d.get(..4)?.parse::<i32>().ok())
+                .and_then(|year| Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).single())
+            {
+                *release_date = Some(date);
+            }
+            *track_number = Some(track.number);
+
+            let end = track.end.unwrap_or(std::time::Duration::from_secs(*duration as u64));
+            *duration = end.saturating_sub(track.start).as_secs() as u32;
+
+            // 镜像自身的 ReplayGain 覆盖整张专辑，作为专辑增益
+            *replaygain_album_gain = sheet.replaygain_album_gain.or(*replaygain_album_gain).or(*replaygain_track_gain);
+            *replaygain_album_peak = sheet.replaygain_album_peak.or(*replaygain_album_peak).or(*replaygain_track_peak);
+            *replaygain_track_gain = track.replaygain_track_gain;
+            *replaygain_track_peak = track.replaygain_track_peak;
+
+            *start_offset = Some(track.start.as_millis() as u64);
+            *end_offset = track.end.map(|e| e.as_millis() as u64);
+        }
+        metadata
+    }
+}
+
+impl Task for CueSheetTask {
+    fn id(&self) -> &str {
+        self.base.id()
+    }
+
+    fn task_type(&self) -> TaskType {
+        self.base.task_type()
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.base.path()
+    }
+
+    fn execute(&mut self, context: &TaskContext) -> tokio::task::JoinHandle<TaskResult> {
+        self.set_status(TaskStatus::InProgress);
+        let path = self.base.path().unwrap_or("").to_string();
+        let ticket = self.scan.take().map(ScanTicket::resume);
+        let context = context.clone();
+
+        tokio::spawn(async move {
+            match Self::split_tracks(&path).await {
+                Ok((images, tracks)) => {
+                    // 之前作为普通曲目索引过的镜像不再单独显示；写入线程按顺序执行，先删除再插入
+                    for image in images {
+                        context.writer().remove_cue_image(image);
+                    }
+
+                    let count = tracks.len();
+                    // 拆分出的每首曲目分别计入扫描报告
+                    for (track, chapters) in tracks.into_iter().filter_map(into_track) {
+                        context.writer().upsert(track, chapters, ticket.as_ref().map(ScanTicket::split));
+                    }
+                    if let Some(ticket) = ticket {
+                        ticket.done();
+                    }
+                    TaskResult::Success(TaskData::String(format!("{} 拆分出 {} 首曲目", path, count)))
+                }
+                Err(e) => TaskResult::Failure(format!("解析 CUE 失败: {}, 错误: {}", path, e)),
+            }
+        })
+    }
+
+    fn status(&self) -> TaskStatus {
+        self.base.status()
+    }
+
+    fn set_status(&mut self, status: TaskStatus) {
+        self.base.set_status(status);
+    }
+
+    fn clone_box(&self) -> Box<dyn Task> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for CueSheetTask {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            scan: self.scan.clone(),
+        }
+    }
+}
+
+/// 移除任务：文件或文件夹已被删除或移走，其下已索引的曲目标记为 missing，之后在别处出现时识别为移动
+#[derive(Debug)]
+pub struct FileRemovalTask {
+    base: BaseTask,
+}
+
+impl FileRemovalTask {
+    /// 创建新的移除任务
+    pub fn new(path: String) -> Self {
+        Self {
+            base: BaseTask::new(TaskType::FileRemoval, Some(path)),
+        }
+    }
+}
+
+impl Task for FileRemovalTask {
+    fn id(&self) -> &str {
+        self.base.id()
+    }
+
+    fn task_type(&self) -> TaskType {
+        self.base.task_type()
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.base.path()
+    }
+
+    fn execute(&mut self, context: &TaskContext) -> tokio::task::JoinHandle<TaskResult> {
+        self.set_status(TaskStatus::InProgress);
+        let path = self.base.path().unwrap_or("").to_string();
+        let context = context.clone();
+
+        tokio::spawn(async move {
+            // 提交后又被放回原处
+            if Path::new(&path).exists() {
+                return TaskResult::Success(TaskData::String(format!("路径仍然存在: {}", path)));
+            }
+            let indexed = match indexed_files(&path) {
+                Ok(indexed) => indexed,
+                Err(e) => return TaskResult::Failure(format!("读取曲库失败: {}", e)),
+            };
+            let mut tracks = 0;
+            for (file_path, entry) in indexed {
+                if !entry.missing {
+                    tracks += entry.tracks;
+                    context.writer().mark_missing(file_path);
+                }
+            }
+            info!("{} 首曲目的文件已不存在: {:?}", tracks, path);
+            TaskResult::Success(TaskData::Path(path))
+        })
+    }
+
+    fn status(&self) -> TaskStatus {
+        self.base.status()
+    }
+
+    fn set_status(&mut self, status: TaskStatus) {
+        self.base.set_status(status);
+    }
+
+    fn clone_box(&self) -> Box<dyn Task> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for FileRemovalTask {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone()
+        }
+    }
+}
+
+/// 把元数据转换为写入曲库的曲目与章节
+fn into_track(metadata: TaskData) -> Option<(Track, Vec<Chapter>)> {
+    let TaskData::FileMetadata(metadata) = metadata else {
+        return None;
+    };
+    let FileMetadata {
+        title,
+        album,
+        artist,
+        album_artist,
+        composer,
+        lyricist,
+        genre,
+        release_date,
+        track_number,
+        disc_number,
+        disc_total,
+        bpm,
+        duration,
+        cover_art,
+        audio_format,
+        audio_size,
+        bitrate,
+        sample_rate,
+        file_path,
+        create_time,
+        update_time,
+        copyright,
+        remark,
+        path_type,
+        is_love,
+        lyrics,
+        hash,
+        replaygain_track_gain,
+        replaygain_track_peak,
+        replaygain_album_gain,
+        replaygain_album_peak,
+        start_offset,
+        end_offset,
+        chapters,
+        mtime,
+    } = *metadata;
+
+    let track = Track {
+        id: None,
+        title,
+        album,
+        artist,
+        album_artist,
+        composer,
+        lyricist,
+        genre,
+        release_date,
+        track_number,
+        disc_number,
+        bpm,
+        duration,
+        cover_art,
+        audio_format,
+        audio_size,
+        bitrate,
+        sample_rate,
+        file_path,
+        create_time,
+        update_time,
+        copyright,
+        remark,
+        path_type,
+        is_love,
+        hash,
+        disc_total,
+        lyrics,
+        replaygain_track_gain,
+        replaygain_track_peak,
+        replaygain_album_gain,
+        replaygain_album_peak,
+        start_offset,
+        end_offset,
+        unavailable: false,
+        mtime,
+        missing: false,
+    };
+    Some((track, chapters))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(path: &Path) -> TaskResult {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(MetadataExtractionTask::extract_metadata(&path.to_string_lossy(), false))
+    }
+
+    #[test]
+    fn unreadable_files_fail_instead_of_panicking() {
+        let dir = tempfile::tempdir().unwrap();
+        // 仍在复制中的文件只有部分文件头
+        let partial = dir.path().join("partial.wav");
+        std::fs::write(&partial, b"RIFF\x24\x00\x00\x00WAVEfmt ").unwrap();
+        assert!(matches!(extract(&partial), TaskResult::Failure(_)));
+        assert!(matches!(extract(&dir.path().join("deleted.flac")), TaskResult::Failure(_)));
+
+        // 无法解析的数字标签留空，其余标签照常读取；Vorbis Comment 按原样保留标签文本
+        let tagged = dir.path().join("tagged.flac");
+        let mut flac = b"fLaC\x00\x00\x00\x22\x10\x00\x10\x00\x00\x00\x00\x00\x00\x00".to_vec();
+        flac.extend_from_slice(&[0x0a, 0xc4, 0x42, 0xf0, 0x00, 0x00, 0x00, 0x00]); // 44100 Hz，2 声道，16 位
+        flac.extend_from_slice(&[0; 16]);
+        let comments = ["TITLE=Title", "TRACKNUMBER=A1", "BPM=120.5"];
+        let mut block = vec![0, 0, 0, 0];
+        block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            block.extend_from_slice(comment.as_bytes());
+        }
+        flac.extend_from_slice(&[0x84, 0, 0, block.len() as u8]);
+        flac.extend_from_slice(&block);
+        std::fs::write(&tagged, flac).unwrap();
+        let TaskResult::Success(TaskData::FileMetadata(metadata)) = extract(&tagged) else {
+            panic!("metadata not extracted");
+        };
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!((metadata.track_number, metadata.bpm), (None, None));
+        assert_eq!(metadata.audio_size, std::fs::metadata(&tagged).unwrap().len());
+    }
+}
@@ -0,0 +1,331 @@
+/*
+* Watcher
+* 实时监视曲库文件夹：合并短时间内的文件系统事件，新增、修改或改名得到的文件交给格式检查任务，
+* 新建或移入的文件夹交给目录扫描任务，已删除或移走的路径交给移除任务。
+*/
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use notify_debouncer_full::notify::event::ModifyKind;
+use notify_debouncer_full::notify::{self, EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, DebouncedEvent, Debouncer, RecommendedCache};
+use tracing::{error, info, warn};
+use super::cue::{is_cue_file, CueSheet};
+use super::incremental::{indexed_file, FileStamp};
+use super::scanner::{CueSheetTask, DirectoryScanTask, ExtensionCheckTask, FileRemovalTask};
+use crate::database::{connection, get_config_value, set_config_value};
+use crate::task_queue::TaskQueueHandle;
+
+/// 事件合并的时长；下载或复制文件时的一连串事件只处理一次
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 文件在这段时间内大小与修改时间都不变才视为写入完成
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// 曲库文件夹，对应 library_folders 配置（JSON 数组）
+pub fn library_folders() -> Vec<String> {
+    get_config_value(&connection(), "library_folders")
+        .ok()
+        .and_then(|c| serde_json::from_str(&c.value).ok())
+        .unwrap_or_default()
+}
+
+/// 加入曲库文件夹；已在某个曲库文件夹之下时不重复加入，返回 false
+pub fn add_library_folder(path: &str) -> anyhow::Result<bool> {
+    let mut folders = library_folders();
+    if folders.iter().any(|folder| Path::new(path).starts_with(folder)) {
+        return Ok(false);
+    }
+    folders.push(path.to_string());
+    set_config_value(&connection(), "library_folders", &serde_json::to_string(&folders)?)?;
+    Ok(true)
+}
+
+/// 移出曲库文件夹，已索引的曲目保留
+pub fn remove_library_folder(path: &str) -> anyhow::Result<bool> {
+    let mut folders = library_folders();
+    let len = folders.len();
+    folders.retain(|folder| folder != path);
+    if folders.len() == len {
+        return Ok(false);
+    }
+    set_config_value(&connection(), "library_folders", &serde_json::to_string(&folders)?)?;
+    Ok(true)
+}
+
+/// 一批事件归并后的变化，同一路径的多次事件只处理一次
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub files: BTreeSet<PathBuf>,   // 新增或修改的文件
+    pub dirs: BTreeSet<PathBuf>,    // 新建或移入的文件夹，其中的文件不一定逐个产生事件
+    pub removed: BTreeSet<PathBuf>, // 已不存在的文件或文件夹
+}
+
+impl Changes {
+    /// 按处理时磁盘上的状态归类：先创建又删除的临时文件被忽略，改名的旧路径视为删除、新路径视为新增
+    pub fn collect(events: &[DebouncedEvent]) -> Self {
+        let mut changes = Self::default();
+        for event in events {
+            // 文件夹内容变化时文件夹本身也会产生修改事件，只有新建或改名才需要扫描
+            let arrived = match event.kind {
+                EventKind::Access(_) | EventKind::Other => continue,
+                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => true,
+                _ => false,
+            };
+            for path in &event.paths {
+                if path.is_dir() {
+                    if arrived {
+                        changes.dirs.insert(path.clone());
+                    }
+                } else if path.is_file() {
+                    changes.files.insert(path.clone());
+                } else if !path.exists() {
+                    changes.removed.insert(path.clone());
+                }
+            }
+        }
+        // 文件夹整体处理，其下的路径不再单独处理
+        changes.dirs = outermost(&changes.dirs);
+        changes.removed = outermost(&changes.removed);
+        let dirs = &changes.dirs;
+        changes.files.retain(|file| !dirs.iter().any(|dir| file.starts_with(dir)));
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.dirs.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// 去掉位于集合中其他路径之下的路径
+fn outermost(paths: &BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+    paths.iter()
+        .filter(|path| !paths.iter().any(|other| other != *path && path.starts_with(other)))
+        .cloned()
+        .collect()
+}
+
+/// 曲库文件夹监视句柄，克隆后共享同一个监视器；事件在单独的线程中处理并提交到任务队列
+#[derive(Clone)]
+pub struct LibraryWatcher {
+    debouncer: Arc<Mutex<Option<Debouncer<RecommendedWatcher, RecommendedCache>>>>,
+    roots: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl LibraryWatcher {
+    /// 系统不支持文件监视时仍返回句柄，曲库只能手动扫描
+    pub fn new(queue: TaskQueueHandle) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let debouncer = new_debouncer(DEBOUNCE_TIMEOUT, None, sender)
+            .inspect_err(|e| error!("创建文件监视器失败: {}", e))
+            .ok();
+        let roots = Arc::new(Mutex::new(Vec::new()));
+        let thread_roots = roots.clone();
+        thread::Builder::new()
+            .name("library-watcher".to_string())
+            .spawn(move || run(receiver, thread_roots, queue))
+            .expect("failed to spawn library watcher thread");
+        Self { debouncer: Arc::new(Mutex::new(debouncer)), roots }
+    }
+
+    /// 递归监视文件夹；移动硬盘未连接等情况下返回错误
+    pub fn watch(&self, root: &str) -> notify::Result<()> {
+        let root = PathBuf::from(root);
+        let mut roots = self.roots.lock().unwrap_or_else(|e| e.into_inner());
+        if roots.contains(&root) {
+            return Ok(());
+        }
+        let mut debouncer = self.debouncer.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(debouncer) = debouncer.as_mut() else {
+            return Err(notify::Error::generic("文件监视不可用"));
+        };
+        debouncer.watch(&root, RecursiveMode::Recursive)?;
+        info!("开始监视曲库文件夹: {:?}", root);
+        roots.push(root);
+        Ok(())
+    }
+
+    pub fn unwatch(&self, root: &str) -> notify::Result<()> {
+        let root = PathBuf::from(root);
+        let mut roots = self.roots.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(index) = roots.iter().position(|r| *r == root) else {
+            return Ok(());
+        };
+        roots.remove(index);
+        if let Some(debouncer) = self.debouncer.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            debouncer.unwatch(&root)?;
+        }
+        info!("停止监视曲库文件夹: {:?}", root);
+        Ok(())
+    }
+}
+
+fn run(receiver: Receiver<DebounceEventResult>, roots: Arc<Mutex<Vec<PathBuf>>>, queue: TaskQueueHandle) {
+    // 监视器释放后通道关闭，线程退出
+    for result in receiver {
+        match result {
+            Ok(events) => {
+                let mut changes = Changes::collect(&events);
+                settle(&mut changes.files);
+                if !changes.is_empty() {
+                    let roots = roots.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                    dispatch(changes, &roots, &queue);
+                }
+            }
+            Err(errors) => {
+                for e in errors {
+                    warn!("文件监视出错: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// 去掉仍在写入的文件；写入产生的后续事件会在之后的批次中再次带上它们
+fn settle(files: &mut BTreeSet<PathBuf>) {
+    if files.is_empty() {
+        return;
+    }
+    let stamps: BTreeMap<PathBuf, Option<FileStamp>> = files.iter()
+        .map(|file| (file.clone(), FileStamp::of(file)))
+        .collect();
+    thread::sleep(SETTLE_TIME);
+    files.retain(|file| {
+        let settled = stamps[file].is_some() && FileStamp::of(file) == stamps[file];
+        if !settled {
+            info!("文件仍在写入，等待后续事件: {:?}", file);
+        }
+        settled
+    });
+}
+
+/// 把变化交给任务队列中对应的任务
+fn dispatch(changes: Changes, roots: &[PathBuf], queue: &TaskQueueHandle) {
+    for dir in changes.dirs {
+        info!("曲库中出现新文件夹: {:?}", dir);
+        queue.blocking_submit_task(Box::new(DirectoryScanTask::new(dir.to_string_lossy().to_string())));
+    }
+
+    for path in changes.removed {
+        // 曲库文件夹本身不可访问（如移动硬盘被拔出）时不能把其中的曲目都当作已删除
+        let available = roots.iter().any(|root| path.starts_with(root) && root.is_dir());
+        if !available {
+            info!("曲库文件夹不可访问，忽略删除: {:?}", path);
+            continue;
+        }
+        queue.blocking_submit_task(Box::new(FileRemovalTask::new(path.to_string_lossy().to_string())));
+    }
+
+    // 镜像或 CUE 变化时重新拆分整张 CUE，同一张 CUE 只提交一次
+    let mut cue_sheets = BTreeSet::new();
+    for file in changes.files {
+        let path = file.to_string_lossy().to_string();
+        if is_cue_file(&path) {
+            cue_sheets.insert(file);
+            continue;
+        }
+        let referencing = cue_sheets_for(&file);
+        if !referencing.is_empty() {
+            cue_sheets.extend(referencing);
+            continue;
+        }
+        // 只有访问时间等变化时不重新索引
+        if let Ok(Some(entry)) = indexed_file(&path) {
+            if entry.unchanged(FileStamp::of(&file), false) {
+                continue;
+            }
+        }
+        queue.blocking_submit_task(Box::new(ExtensionCheckTask::new(path)));
+    }
+    for cue in cue_sheets {
+        queue.blocking_submit_task(Box::new(CueSheetTask::new(cue.to_string_lossy().to_string())));
+    }
+}
+
+/// 同一文件夹中引用该文件作为镜像的 CUE；CUE 中的文件名大小写常与实际不符，按小写比较
+fn cue_sheets_for(image: &Path) -> Vec<PathBuf> {
+    let Some(entries) = image.parent().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let image = image.to_string_lossy().to_lowercase();
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| is_cue_file(&path.to_string_lossy()))
+        .filter(|path| CueSheet::load(path).is_ok_and(|sheet| {
+            sheet.image_paths().any(|p| p.to_string_lossy().to_lowercase() == image)
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use notify_debouncer_full::notify::event::{CreateKind, DataChange, RemoveKind, RenameMode};
+    use notify_debouncer_full::notify::Event;
+
+    fn event(kind: EventKind, paths: &[&Path]) -> DebouncedEvent {
+        let event = paths.iter().fold(Event::new(kind), |event, path| event.add_path(path.to_path_buf()));
+        DebouncedEvent::new(event, Instant::now())
+    }
+
+    #[test]
+    fn changes_follow_disk_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let album = root.join("album");
+        std::fs::create_dir(&album).unwrap();
+        for file in ["album/01.flac", "album/02.flac", "renamed.mp3", "edited.mp3"] {
+            std::fs::write(root.join(file), b"").unwrap();
+        }
+
+        let changes = Changes::collect(&[
+            // 移入的文件夹整体扫描，其中文件的事件并入
+            event(EventKind::Create(CreateKind::Folder), &[&album]),
+            event(EventKind::Create(CreateKind::File), &[&album.join("01.flac")]),
+            event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &[&root.join("old.mp3"), &root.join("renamed.mp3")]),
+            event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), &[&root.join("edited.mp3")]),
+            // 文件夹内容变化引起的修改事件不触发扫描
+            event(EventKind::Modify(ModifyKind::Any), &[root]),
+            // 下载过程中的临时文件已被删除
+            event(EventKind::Create(CreateKind::File), &[&root.join("edited.mp3.part")]),
+            event(EventKind::Remove(RemoveKind::Folder), &[&root.join("gone")]),
+            event(EventKind::Remove(RemoveKind::File), &[&root.join("gone/a.flac")]),
+        ]);
+
+        assert_eq!(changes.dirs, BTreeSet::from([album]));
+        assert_eq!(changes.files, BTreeSet::from([root.join("edited.mp3"), root.join("renamed.mp3")]));
+        assert_eq!(
+            changes.removed,
+            BTreeSet::from([root.join("edited.mp3.part"), root.join("gone"), root.join("old.mp3")])
+        );
+    }
+
+    #[test]
+    fn files_still_being_written_wait_for_later_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let done = dir.path().join("done.flac");
+        let copying = dir.path().join("copying.flac");
+        std::fs::write(&done, b"fLaC").unwrap();
+        std::fs::write(&copying, b"fLaC").unwrap();
+
+        let writer = {
+            let copying = copying.clone();
+            thread::spawn(move || {
+                use std::io::Write;
+                let mut file = std::fs::OpenOptions::new().append(true).open(copying).unwrap();
+                for _ in 0..20 {
+                    file.write_all(&[0; 4096]).unwrap();
+                    thread::sleep(Duration::from_millis(50));
+                }
+            })
+        };
+        let mut files = BTreeSet::from([done.clone(), copying, dir.path().join("deleted.flac")]);
+        settle(&mut files);
+        writer.join().unwrap();
+        assert_eq!(files, BTreeSet::from([done]));
+    }
+}
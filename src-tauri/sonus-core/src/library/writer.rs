@@ -0,0 +1,424 @@
+/*
+* Writer
+* 曲库写入：扫描得到的曲目交给单独的写入线程，以绑定参数的预编译语句批量写入，
+* 每批在一个事务中提交，避免每首曲目各开一次连接、各提交一次。
+* 已索引的文件原地更新，哈希相同的已消失曲目视为移动，保留原来的 id、收藏与歌单。
+*/
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, ToSql};
+use tracing::{info, warn};
+use super::chapters::{save_chapters, Chapter};
+use super::incremental::{ScanOutcome, ScanTicket};
+use super::index::Track;
+use crate::database::connection;
+use crate::events::{EventBus, LibraryEvent};
+
+/// 每个事务最多写入的操作数
+const BATCH_SIZE: usize = 500;
+/// 收到第一个操作后最多等待这么久再提交，扫描结束时剩余的曲目也能及时写入
+const FLUSH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 扫描得到的列，插入与更新共用；id、is_love 与创建时间只在插入时写入
+const TRACK_FIELDS: [&str; 31] = [
+    "title", "album", "artist", "album_artist", "composer", "lyricist", "genre", "release_date",
+    "track_number", "disc_number", "bpm", "duration", "cover_art", "audio_format", "audio_size",
+    "bitrate", "sample_rate", "file_path", "copyright", "remark", "path_type", "hash", "disc_total",
+    "lyrics", "replaygain_track_gain", "replaygain_track_peak", "replaygain_album_gain",
+    "replaygain_album_peak", "start_offset", "end_offset", "mtime",
+];
+
+/// 冲突目标与 idx_music_track 的表达式一致；已有记录时只更新扫描得到的列
+fn upsert_sql() -> &'static str {
+    static SQL: OnceLock<String> = OnceLock::new();
+    SQL.get_or_init(|| format!(
+        "INSERT INTO music ({}, is_love, create_time, update_time)
+         VALUES ({}, ?, COALESCE(?, CURRENT_TIMESTAMP), COALESCE(?, CURRENT_TIMESTAMP))
+         ON CONFLICT (file_path, IFNULL(start_offset, -1)) DO UPDATE SET {},
+         missing = 0, unavailable = 0, update_time = CURRENT_TIMESTAMP",
+        TRACK_FIELDS.join(", "),
+        vec!["?"; TRACK_FIELDS.len()].join(", "),
+        TRACK_FIELDS.map(|field| format!("{0} = excluded.{0}", field)).join(", ")
+    ))
+}
+
+fn update_sql() -> &'static str {
+    static SQL: OnceLock<String> = OnceLock::new();
+    SQL.get_or_init(|| format!(
+        "UPDATE music SET {}, missing = 0, unavailable = 0, update_time = CURRENT_TIMESTAMP WHERE id = ?",
+        TRACK_FIELDS.map(|field| format!("{} = ?", field)).join(", ")
+    ))
+}
+
+/// 写入线程按提交顺序执行的操作
+#[derive(Debug)]
+pub enum WriteOp {
+    Upsert { track: Box<Track>, chapters: Vec<Chapter>, ticket: Option<ScanTicket> },
+    RemoveCueImage(String), // 删除此前作为普通曲目索引的 CUE 镜像，拆分出的曲目不受影响
+    MarkMissing(String),    // 文件已不存在，保留记录以便之后识别为移动
+}
+
+impl WriteOp {
+    fn path(&self) -> &str {
+        match self {
+            WriteOp::Upsert { track, .. } => &track.file_path,
+            WriteOp::RemoveCueImage(path) | WriteOp::MarkMissing(path) => path,
+        }
+    }
+}
+
+/// 曲库写入句柄，克隆后共享同一个写入线程；所有句柄释放后线程写完剩余操作后退出
+#[derive(Clone)]
+pub struct LibraryWriter {
+    sender: Sender<WriteOp>,
+}
+
+impl LibraryWriter {
+    pub fn new(events: EventBus) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("library-writer".to_string())
+            .spawn(move || run(receiver, events))
+            .expect("failed to spawn library writer thread");
+        Self { sender }
+    }
+
+    /// 写入或更新曲目；ticket 在提交后记录结果
+    pub fn upsert(&self, track: Track, chapters: Vec<Chapter>, ticket: Option<ScanTicket>) {
+        self.send(WriteOp::Upsert { track: Box::new(track), chapters, ticket });
+    }
+
+    pub fn remove_cue_image(&self, path: String) {
+        self.send(WriteOp::RemoveCueImage(path));
+    }
+
+    pub fn mark_missing(&self, path: String) {
+        self.send(WriteOp::MarkMissing(path));
+    }
+
+    fn send(&self, op: WriteOp) {
+        if let Err(e) = self.sender.send(op) {
+            warn!("曲库写入线程已退出，丢弃: {}", e.0.path());
+        }
+    }
+}
+
+fn run(receiver: Receiver<WriteOp>, events: EventBus) {
+    // 第一次写入时才打开数据库
+    let mut conn: Option<Connection> = None;
+
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + FLUSH_INTERVAL;
+        while batch.len() < BATCH_SIZE {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(op) => batch.push(op),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        let conn = conn.get_or_insert_with(connection);
+        let outcomes = match write_batch(conn, &batch) {
+            Ok(outcomes) => outcomes,
+            Err(e) => {
+                // 整批回滚后逐条重试，一首曲目出错不影响同批的其他曲目
+                warn!("批量写入 {} 项失败，逐条重试: {}", batch.len(), e);
+                batch.iter()
+                    .map(|op| match write_batch(conn, std::slice::from_ref(op)) {
+                        Ok(mut outcomes) => outcomes.pop().flatten(),
+                        Err(e) => {
+                            warn!("写入曲库失败: {}, 错误: {}", op.path(), e);
+                            Some(ScanOutcome::Failed)
+                        }
+                    })
+                    .collect()
+            }
+        };
+        info!("曲库写入 {} 项", batch.len());
+
+        for (op, outcome) in batch.into_iter().zip(outcomes) {
+            match op {
+                WriteOp::Upsert { track, ticket, .. } => {
+                    let outcome = outcome.unwrap_or(ScanOutcome::Failed);
+                    if outcome != ScanOutcome::Failed {
+                        events.publish(LibraryEvent::TrackIndexed { path: track.file_path });
+                    }
+                    if let Some(ticket) = ticket {
+                        ticket.finish(outcome);
+                    }
+                }
+                WriteOp::MarkMissing(path) if outcome.is_none() => {
+                    events.publish(LibraryEvent::TrackMissing { path });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// 在一个事务中执行一批操作，返回每个写入曲目操作的结果
+pub fn write_batch(conn: &mut Connection, ops: &[WriteOp]) -> rusqlite::Result<Vec<Option<ScanOutcome>>> {
+    let tx = conn.transaction()?;
+    let mut outcomes = Vec::with_capacity(ops.len());
+    for op in ops {
+        let outcome = match op {
+            WriteOp::Upsert { track, chapters, .. } => Some(upsert_track(&tx, track, chapters)?),
+            WriteOp::RemoveCueImage(path) => {
+                tx.prepare_cached("DELETE FROM music WHERE file_path = ? AND start_offset IS NULL")?
+                    .execute([path])?;
+                None
+            }
+            WriteOp::MarkMissing(path) => {
+                tx.prepare_cached("UPDATE music SET missing = 1 WHERE file_path = ?")?
+                    .execute([path])?;
+                None
+            }
+        };
+        outcomes.push(outcome);
+    }
+    tx.commit()?;
+    Ok(outcomes)
+}
+
+/// 同一路径（CUE 曲目还需起点相同）已有记录时更新，否则尝试识别为移动，都不是则插入
+fn upsert_track(conn: &Connection, track: &Track, chapters: &[Chapter]) -> rusqlite::Result<ScanOutcome> {
+    let existing = conn
+        .prepare_cached("SELECT id FROM music WHERE file_path = ? AND IFNULL(start_offset, -1) = IFNULL(?, -1)")?
+        .query_row(params![track.file_path, track.start_offset], |row| row.get::<_, usize>(0))
+        .optional()?;
+
+    if existing.is_none() {
+        if let Some((id, from)) = moved_from(conn, track)? {
+            write_track(conn, update_sql(), track, &[&id])?;
+            save_chapters(conn, id, chapters)?;
+            return Ok(ScanOutcome::Moved { from });
+        }
+    }
+
+    let (create_time, update_time) = (timestamp(track.create_time), timestamp(track.update_time));
+    write_track(conn, upsert_sql(), track, &[&track.is_love, &create_time, &update_time])?;
+    match existing {
+        Some(id) => {
+            // 内容已变化，之前的完整性校验结果不再适用
+            conn.prepare_cached("DELETE FROM integrity_check WHERE music_id = ?")?.execute([id])?;
+            save_chapters(conn, id, chapters)?;
+            Ok(ScanOutcome::Updated)
+        }
+        None => {
+            if !chapters.is_empty() {
+                save_chapters(conn, conn.last_insert_rowid() as usize, chapters)?;
+            }
+            Ok(ScanOutcome::Added)
+        }
+    }
+}
+
+/// 哈希相同、文件已不存在的曲目视为被移动到新路径
+fn moved_from(conn: &Connection, track: &Track) -> rusqlite::Result<Option<(usize, String)>> {
+    if track.hash.is_empty() {
+        return Ok(None);
+    }
+    let candidates = conn
+        .prepare_cached(
+            "SELECT id, file_path, missing FROM music
+             WHERE hash = ? AND IFNULL(start_offset, -1) = IFNULL(?, -1) AND file_path != ? ORDER BY id"
+        )?
+        .query_map(params![track.hash, track.start_offset, track.file_path], |row| {
+            Ok((row.get::<_, usize>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<bool>>(2)?.unwrap_or(false)))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(candidates.into_iter()
+        .find(|(_, path, missing)| *missing || !Path::new(path).exists())
+        .map(|(id, path, _)| (id, path)))
+}
+
+/// 按 TRACK_FIELDS 的顺序绑定曲目的值，extra 接在其后；缺失的字段写入 NULL
+fn write_track(conn: &Connection, sql: &str, track: &Track, extra: &[&dyn ToSql]) -> rusqlite::Result<usize> {
+    let artist = joined(&track.artist);
+    let composer = joined(&track.composer);
+    let lyricist = joined(&track.lyricist);
+    let genre = joined(&track.genre);
+    let cover_art = joined(&track.cover_art);
+    let release_date = timestamp(track.release_date);
+    let mut values: Vec<&dyn ToSql> = vec![
+        &track.title,
+        &track.album,
+        &artist,
+        &track.album_artist,
+        &composer,
+        &lyricist,
+        &genre,
+        &release_date,
+        &track.track_number,
+        &track.disc_number,
+        &track.bpm,
+        &track.duration,
+        &cover_art,
+        &track.audio_format,
+        &track.audio_size,
+        &track.bitrate,
+        &track.sample_rate,
+        &track.file_path,
+        &track.copyright,
+        &track.remark,
+        &track.path_type,
+        &track.hash,
+        &track.disc_total,
+        &track.lyrics,
+        &track.replaygain_track_gain,
+        &track.replaygain_track_peak,
+        &track.replaygain_album_gain,
+        &track.replaygain_album_peak,
+        &track.start_offset,
+        &track.end_offset,
+        &track.mtime,
+    ];
+    values.extend_from_slice(extra);
+    conn.prepare_cached(sql)?.execute(&*values)
+}
+
+/// 多值字段以逗号分隔保存，与 Track::from_row 的拆分方式对应
+fn joined(values: &Option<Vec<String>>) -> Option<String> {
+    values.as_ref().filter(|v| !v.is_empty()).map(|v| v.join(", "))
+}
+
+/// 与 CURRENT_TIMESTAMP 相同的格式，Track::from_row 可以解析
+fn timestamp(value: Option<DateTime<Utc>>) -> Option<String> {
+    value.map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn track(path: &str, hash: &str) -> Track {
+        let mut track = Track::new();
+        track.file_path = path.to_string();
+        track.hash = hash.to_string();
+        track
+    }
+
+    fn upsert(track: Track) -> WriteOp {
+        WriteOp::Upsert { track: Box::new(track), chapters: Vec::new(), ticket: None }
+    }
+
+    #[test]
+    fn inserts_with_real_nulls_and_chapters() {
+        let mut conn = library();
+        let mut tagged = track("D:\\Music\\It's.flac", "a");
+        tagged.title = Some("Don't Stop".to_string());
+        tagged.artist = Some(vec!["A".to_string(), "B".to_string()]);
+        let chapters = vec![Chapter { index: 0, title: None, start_ms: 0, end_ms: None }];
+        let ops = vec![
+            WriteOp::Upsert { track: Box::new(tagged), chapters, ticket: None },
+            upsert(track("D:\\Music\\untagged.mp3", "b")),
+        ];
+        assert_eq!(write_batch(&mut conn, &ops).unwrap(), vec![Some(ScanOutcome::Added); 2]);
+
+        let (title, artist, nulls, created): (String, String, bool, bool) = conn.query_row(
+            "SELECT title, artist, album IS NULL AND track_number IS NULL, create_time IS NOT NULL FROM music WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        ).unwrap();
+        assert_eq!((title.as_str(), artist.as_str()), ("Don't Stop", "A, B"));
+        assert!(nulls && created);
+
+        let chapter_owner: usize = conn.query_row("SELECT music_id FROM chapters", [], |row| row.get(0)).unwrap();
+        assert_eq!(chapter_owner, 1);
+    }
+
+    #[test]
+    fn rescans_update_in_place_and_detect_moves() {
+        let mut conn = library();
+        write_batch(&mut conn, &[upsert(track("old/a.flac", "h1"))]).unwrap();
+        conn.execute("UPDATE music SET is_love = 1", []).unwrap();
+
+        // 同一路径再次写入时更新原记录，收藏保留
+        let mut retagged = track("old/a.flac", "h2");
+        retagged.title = Some("Retagged".to_string());
+        assert_eq!(write_batch(&mut conn, &[upsert(retagged)]).unwrap(), vec![Some(ScanOutcome::Updated)]);
+
+        // 原文件消失后，在新路径出现的相同内容视为移动
+        let ops = vec![WriteOp::MarkMissing("old/a.flac".to_string()), upsert(track("new/a.flac", "h2"))];
+        assert_eq!(
+            write_batch(&mut conn, &ops).unwrap(),
+            vec![None, Some(ScanOutcome::Moved { from: "old/a.flac".to_string() })]
+        );
+
+        let rows: Vec<(usize, String, bool, bool)> = conn
+            .prepare("SELECT id, file_path, is_love, missing FROM music").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(rows, vec![(1, "new/a.flac".to_string(), true, false)]);
+    }
+
+    #[test]
+    fn failed_batch_rolls_back() {
+        let mut conn = library();
+        conn.execute_batch(
+            "CREATE TRIGGER reject BEFORE INSERT ON music WHEN NEW.file_path = 'bad.flac'
+             BEGIN SELECT RAISE(ABORT, 'rejected'); END"
+        ).unwrap();
+        let ops = vec![upsert(track("a.flac", "a")), upsert(track("bad.flac", "b"))];
+        assert!(write_batch(&mut conn, &ops).is_err());
+        let count: usize = conn.query_row("SELECT COUNT(*) FROM music", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+
+        // 逐条写入时第一条成功
+        assert_eq!(write_batch(&mut conn, &ops[..1]).unwrap(), vec![Some(ScanOutcome::Added)]);
+    }
+
+    #[test]
+    fn cue_tracks_share_a_path_but_not_a_row() {
+        let mut conn = library();
+        let mut first = track("disc.flac", "");
+        first.start_offset = Some(0);
+        let mut second = track("disc.flac", "");
+        second.start_offset = Some(180_000);
+        let ops = vec![upsert(first.clone()), upsert(second), upsert(first)];
+        assert_eq!(
+            write_batch(&mut conn, &ops).unwrap(),
+            vec![Some(ScanOutcome::Added), Some(ScanOutcome::Added), Some(ScanOutcome::Updated)]
+        );
+        let count: usize = conn.query_row("SELECT COUNT(*) FROM music", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+
+        // 唯一索引拒绝绕过写入线程的重复记录
+        assert!(conn.execute("INSERT INTO music (file_path, start_offset) VALUES ('disc.flac', 0)", []).is_err());
+    }
+
+    #[test]
+    fn migration_merges_duplicate_tracks() {
+        let conn = library();
+        conn.execute_batch(
+            "DROP INDEX idx_music_track;
+            INSERT INTO music (id, file_path, is_love) VALUES (1, 'a.flac', 0), (2, 'a.flac', 1), (3, 'b.flac', 0);
+            INSERT INTO music (id, file_path, start_offset) VALUES (4, 'disc.flac', 0), (5, 'disc.flac', 9000);
+            INSERT INTO playlist_music (playlist_id, music_id) VALUES (1, 2);
+            INSERT INTO chapters (music_id, chapter_index, start_ms) VALUES (2, 0, 0);"
+        ).unwrap();
+        crate::database::init_schema(&conn).unwrap();
+
+        let rows: Vec<(usize, bool)> = conn
+            .prepare("SELECT id, IFNULL(is_love, 0) FROM music ORDER BY id").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(rows, vec![(1, true), (3, false), (4, false), (5, false)]);
+        let (playlist, chapters): (usize, usize) = conn.query_row(
+            "SELECT (SELECT music_id FROM playlist_music), (SELECT COUNT(*) FROM chapters)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((playlist, chapters), (1, 0));
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+use rusqlite::Row;
+use serde::Serialize;
+use crate::database::{connection, execute_with_params, query_with_params};
+
+/// 按曲目保存的命名 A–B 循环
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedLoop {
+    pub id: i64,
+    pub track_id: usize,
+    pub name: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+impl SavedLoop {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            track_id: row.get(1)?,
+            name: row.get(2)?,
+            start_ms: row.get(3)?,
+            end_ms: row.get(4)?,
+        })
+    }
+
+    pub fn start(&self) -> Duration {
+        Duration::from_millis(self.start_ms)
+    }
+
+    pub fn end(&self) -> Duration {
+        Duration::from_millis(self.end_ms)
+    }
+}
+
+pub fn save_loop(track_id: usize, name: &str, start: Duration, end: Duration) -> rusqlite::Result<SavedLoop> {
+    let conn = connection();
+    let start_ms = start.as_millis() as u64;
+    let end_ms = end.as_millis() as u64;
+    execute_with_params(
+        &conn,
+        "INSERT INTO ab_loop (music_id, name, start_ms, end_ms) VALUES (?, ?, ?, ?)",
+        &[&track_id, &name, &start_ms, &end_ms],
+    )?;
+    Ok(SavedLoop {
+        id: conn.last_insert_rowid(),
+        track_id,
+        name: name.to_string(),
+        start_ms,
+        end_ms,
+    })
+}
+
+pub fn get_loops(track_id: usize) -> rusqlite::Result<Vec<SavedLoop>> {
+    query_with_params(
+        &connection(),
+        "SELECT id, music_id, name, start_ms, end_ms FROM ab_loop WHERE music_id = ? ORDER BY start_ms, id",
+        &[&track_id],
+        SavedLoop::from_row
+    )
+}
+
+pub fn get_loop(id: i64) -> rusqlite::Result<Option<SavedLoop>> {
+    let found = query_with_params(
+        &connection(),
+        "SELECT id, music_id, name, start_ms, end_ms FROM ab_loop WHERE id = ?",
+        &[&id],
+        SavedLoop::from_row
+    )?;
+    Ok(found.into_iter().next())
+}
+
+pub fn delete_loop(id: i64) -> rusqlite::Result<()> {
+    execute_with_params(&connection(), "DELETE FROM ab_loop WHERE id = ?", &[&id])?;
+    Ok(())
+}
@@ -0,0 +1,1553 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use serde::Serialize;
+use anyhow::Result;
+use rodio::{queue::SourcesQueueOutput, source::UniformSourceIterator, Sink, Source};
+use symphonia::core::{
+    audio::{SampleBuffer, SignalSpec},
+    codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::{MediaSource, MediaSourceStream, MediaSourceStreamOptions},
+    meta::MetadataOptions,
+    probe::Hint,
+    units::{Time, TimeBase},
+};
+
+use crate::database::{connection, get_config_value, set_config_value};
+use crate::events::{EventBus, PlayerEvent};
+use crate::library::format::sniff;
+use crate::library::index::Track;
+use crate::player::codecs;
+use crate::player::equalizer::{EqSource, Equalizer};
+use crate::player::fade::{FadeSource, Fader};
+use crate::player::output::AudioOutput;
+use crate::player::output_device::{open_output_stream, OutputDeviceChanged};
+use crate::player::remote::{self, HttpMediaSource};
+use crate::player::replay_gain::{ReplayGain, ReplayGainMode};
+use crate::player::spectrum::{SpectrumAnalyzer, SpectrumSource};
+use crate::player::state::{PlaybackState, SharedState, StateSnapshot};
+use crate::player::stereo::{Crossfeed, StereoImage, StereoSettings, StereoSource};
+use crate::player::time_stretch::{PlaybackRate, TimeStretchSource, MAX_PLAYBACK_RATE, MIN_PLAYBACK_RATE};
+
+/// TrackChanged 事件负载：无缝切换到预排曲目时发布
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackChanged {
+    pub file_path: String,
+    pub total_duration: Option<u64>, // 以毫秒为单位
+}
+
+/// 曲目在文件中的播放区间：CUE 虚拟曲目只播放整轨文件的一段，普通曲目为整个文件
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TrackRange {
+    pub start: Duration,
+    pub end: Option<Duration>, // None 表示播放到文件末尾
+}
+
+impl From<&Track> for TrackRange {
+    fn from(track: &Track) -> Self {
+        Self {
+            start: Duration::from_millis(track.start_offset.unwrap_or(0)),
+            end: track.end_offset.map(Duration::from_millis),
+        }
+    }
+}
+
+/// 已追加到 sink（或其槽位）但尚未开始播放的曲目
+struct QueuedTrack {
+    path: PathBuf,
+    total_duration: Option<Duration>,
+    replay_gain: ReplayGain,
+    range: TrackRange,
+    ctl: Arc<TrackCtl>,
+}
+
+type UpcomingQueue = Arc<Mutex<VecDeque<QueuedTrack>>>;
+
+/// rodio 输出 + sink 生命周期，配合 symphonia 解码与精准 seek。
+pub struct AudioBackend {
+    output: Box<dyn AudioOutput>, // 保持输出生命周期，防止被 drop
+    sink: Sink,                   // 第一次追加曲目时接入 output.mixer()
+    sink_queue: Option<SourcesQueueOutput>, // 尚未接入 mixer 的 sink 队列，见 PrimedQueue
+    output_device: Option<String>, // 用户选择的输出设备，None 表示系统默认
+    state: SharedState,
+    progress: ProgressClock,
+    playing: PlayingTrack,   // 正在播放曲目的帧计数，位置由它换算
+    upcoming: UpcomingQueue, // 预排的后续曲目，按播放顺序排列
+    next_slot: NextSlot,     // 最近追加到 sink 的 CrossfadeSource 的下一首槽位
+    fader: Arc<Fader>,
+    sleep_fader: Arc<Fader>, // 睡眠定时的淡出增益，与播放/暂停淡入淡出独立
+    equalizer: Arc<Equalizer>,
+    stereo: Arc<StereoImage>,        // 平衡、单声道混合与交叉馈送
+    spectrum: Arc<SpectrumAnalyzer>, // 可视化用的频谱分析，未开启时不做任何计算
+    playback_rate: Arc<PlaybackRate>,
+    fade_duration: Duration,      // 播放/暂停/停止/跳转时的淡入淡出时长，0 表示关闭
+    crossfade_ms: Arc<AtomicU64>, // 曲目间交叉淡化时长，音频线程实时读取
+    replay_gain_mode: Arc<AtomicU8>,        // ReplayGainMode，音频线程实时读取
+    playing_gain: Arc<Mutex<ReplayGain>>,   // 正在播放曲目的 ReplayGain 标签，seek 重建时沿用
+    playing_range: Arc<Mutex<TrackRange>>,  // 正在播放曲目的文件区间，seek 重建时沿用
+    events: EventBus,
+}
+
+impl AudioBackend {
+    pub fn new(state: SharedState, events: EventBus) -> Result<Self> {
+        // 打开用户选择的输出设备，不存在时回退到默认设备
+        let output_device = get_config_value(&connection(), "output_device")
+            .ok()
+            .map(|c| c.value)
+            .filter(|v| !v.is_empty());
+        let (stream, _) = open_output_stream(output_device.as_deref(), &events)?;
+        let mut backend = Self::with_output(state, Box::new(stream), events);
+        backend.output_device = output_device;
+        Ok(backend)
+    }
+
+    /// 使用给定的音频输出，无声卡的测试环境可传入 NullOutput 或 WavFileOutput
+    pub fn with_output(state: SharedState, output: Box<dyn AudioOutput>, events: EventBus) -> Self {
+        // 创建 Sink，追加第一首曲目时再接入 mixer
+        let (sink, sink_queue) = Sink::new();
+
+        sink.set_volume(state.lock().unwrap_or_else(|e| e.into_inner()).volume());
+
+        // fade_in_out 以毫秒、crossfade 以秒为单位
+        let fade_ms = read_config_number("fade_in_out").unwrap_or(0.0);
+        let crossfade_secs = read_config_number("crossfade").unwrap_or(0.0);
+        let replay_gain_mode = get_config_value(&connection(), "loudness_balance")
+            .map(|c| ReplayGainMode::from_config(&c.value))
+            .unwrap_or(ReplayGainMode::Off);
+
+        let stereo = Arc::new(StereoImage::load());
+        {
+            let settings = stereo.settings();
+            let mut s = state.lock().unwrap_or_else(|e| e.into_inner());
+            s.set_balance(settings.balance);
+            s.set_mono(settings.mono);
+            s.set_crossfeed(settings.crossfeed);
+        }
+
+        let playing: PlayingTrack = Arc::new(Mutex::new(None));
+        let mut progress = ProgressClock::new();
+        progress.start(playing.clone(), state.clone(), events.clone());
+
+        Self {
+            output,
+            sink,
+            sink_queue: Some(sink_queue),
+            output_device: None,
+            state,
+            progress,
+            playing,
+            upcoming: Arc::new(Mutex::new(VecDeque::new())),
+            next_slot: new_slot(),
+            fader: Arc::new(Fader::new()),
+            sleep_fader: Arc::new(Fader::new()),
+            equalizer: Arc::new(Equalizer::load()),
+            stereo,
+            spectrum: Arc::new(SpectrumAnalyzer::new(events.clone())),
+            playback_rate: Arc::new(PlaybackRate::new()),
+            fade_duration: Duration::from_millis(fade_ms.max(0.0) as u64),
+            crossfade_ms: Arc::new(AtomicU64::new((crossfade_secs.max(0.0) * 1000.0) as u64)),
+            replay_gain_mode: Arc::new(AtomicU8::new(replay_gain_mode.as_u8())),
+            playing_gain: Arc::new(Mutex::new(ReplayGain::default())),
+            playing_range: Arc::new(Mutex::new(TrackRange::default())),
+            events,
+        }
+    }
+
+    /// 加载并从指定位置播放（position 可为 0，相对于 range 起点）
+    pub fn load_and_play<P: AsRef<Path>>(&mut self, path: P, position: Duration, replay_gain: ReplayGain, range: TrackRange) -> Result<()> {
+        tracing::info!("load_and_play: {:?} {:?}", path.as_ref(), range);
+        let path_buf = path.as_ref().to_path_buf();
+        let built = SymphoniaSource::from_path_range(&path_buf, range, position)?;
+        let total = built.total_duration;
+        let start_pos = built.start_position;
+
+        self.clear_queued();
+        self.replace_sink(true, None);
+
+        {
+            let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            s.set_current_file(Some(path_buf.to_string_lossy().to_string()));
+            s.set_total_duration(total);
+            s.set_current_position(start_pos);
+            s.set_ab_loop(None);
+            s.set_playback_state(PlaybackState::Playing);
+            let snapshot = StateSnapshot::from(&*s);
+            self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+        }
+
+        *self.playing_gain.lock().unwrap_or_else(|e| e.into_inner()) = replay_gain;
+        *self.playing_range.lock().unwrap_or_else(|e| e.into_inner()) = range;
+        self.fade_in();
+        let source = self.track_source(built, replay_gain);
+        self.set_playing(source.ctl());
+        self.append_track(source);
+        self.sink.play();
+
+        Ok(())
+    }
+
+    /// 预先解码下一首：优先放入当前 CrossfadeSource 的槽位，否则追加到同一个 sink，
+    /// 当前曲目结束时在采样边界上无缝衔接（或按设置交叉淡化）
+    pub fn queue_next<P: AsRef<Path>>(&mut self, path: P, replay_gain: ReplayGain, range: TrackRange) -> Result<()> {
+        tracing::info!("queue_next: {:?} {:?}", path.as_ref(), range);
+        let path_buf = path.as_ref().to_path_buf();
+        let mut built = SymphoniaSource::from_path_range(&path_buf, range, Duration::ZERO)?;
+        built.prime()?;
+        let total = built.total_duration;
+
+        let source = self.track_source(built, replay_gain);
+        self.upcoming.lock().unwrap_or_else(|e| e.into_inner()).push_back(QueuedTrack {
+            path: path_buf,
+            total_duration: total,
+            replay_gain,
+            range,
+            ctl: source.ctl(),
+        });
+
+        let rejected = {
+            let mut slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+            if slot.closed || slot.track.is_some() {
+                Some(source)
+            } else {
+                slot.track = Some(source);
+                None
+            }
+        };
+        if let Some(source) = rejected {
+            self.append_track(source);
+        }
+
+        Ok(())
+    }
+
+    /// 作废所有预排曲目；已在 sink 中的 Source 播放到时会直接跳过
+    pub fn clear_queued(&mut self) {
+        let mut upcoming = self.upcoming.lock().unwrap_or_else(|e| e.into_inner());
+        for queued in upcoming.drain(..) {
+            queued.ctl.cancel();
+        }
+        let mut slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.track.as_ref().is_some_and(|track| track.ctl.is_cancelled()) {
+            slot.track = None;
+        }
+    }
+
+    /// 设置播放/暂停/停止/跳转时的淡入淡出时长
+    pub fn set_fade_duration(&mut self, duration: Duration) {
+        self.fade_duration = duration;
+        _ = set_config_value(&connection(), "fade_in_out", &duration.as_millis().to_string());
+    }
+
+    /// 设置曲目间交叉淡化时长，正在播放的曲目立即生效
+    pub fn set_crossfade(&mut self, duration: Duration) {
+        self.crossfade_ms.store(duration.as_millis() as u64, Ordering::Relaxed);
+        _ = set_config_value(&connection(), "crossfade", &duration.as_secs_f32().to_string());
+    }
+
+    /// 设置响度均衡模式，已解码的曲目立即按新模式调整增益
+    pub fn set_replay_gain_mode(&mut self, mode: ReplayGainMode) {
+        self.replay_gain_mode.store(mode.as_u8(), Ordering::Relaxed);
+        _ = set_config_value(&connection(), "loudness_balance", mode.config_value());
+    }
+
+    pub fn replay_gain_mode(&self) -> ReplayGainMode {
+        ReplayGainMode::from_u8(self.replay_gain_mode.load(Ordering::Relaxed))
+    }
+
+    pub fn fade_duration(&self) -> Duration {
+        self.fade_duration
+    }
+
+    pub fn crossfade(&self) -> Duration {
+        Duration::from_millis(self.crossfade_ms.load(Ordering::Relaxed))
+    }
+
+    /// 设置播放速率（0.5–3.0），正在播放的曲目立即生效；位置按曲目内容时间计算，不受速率影响
+    pub fn set_playback_rate(&mut self, rate: f32) -> Result<()> {
+        if !(MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE).contains(&rate) {
+            return Err(anyhow::anyhow!("playback rate must be between {MIN_PLAYBACK_RATE} and {MAX_PLAYBACK_RATE}"));
+        }
+        self.playback_rate.set_rate(rate);
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_playback_rate(rate);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+        Ok(())
+    }
+
+    /// 变速时是否保持音调（WSOLA），关闭时按重采样变速
+    pub fn set_preserve_pitch(&mut self, enabled: bool) {
+        self.playback_rate.set_preserve_pitch(enabled);
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_preserve_pitch(enabled);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+    }
+
+    /// 在当前曲目上设置 A–B 循环，当前位置不在区间内时跳到 A 点
+    pub fn set_ab_loop(&mut self, start: Duration, end: Duration) -> Result<()> {
+        if start >= end {
+            return Err(anyhow::anyhow!("loop start must be before loop end"));
+        }
+        let ctl = self.playing.lock().unwrap_or_else(|e| e.into_inner()).clone()
+            .filter(|ctl| !ctl.is_cancelled() && !ctl.is_finished())
+            .ok_or_else(|| anyhow::anyhow!("cannot set loop: no track playing"))?;
+        let end = ctl.total_duration().map_or(end, |total| end.min(total));
+        if start >= end {
+            return Err(anyhow::anyhow!("loop start is beyond the end of the track"));
+        }
+
+        ctl.set_ab_loop(Some((start, end)));
+        let position = ctl.position();
+        if position < start || position >= end {
+            ctl.request_seek(start);
+        }
+
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_ab_loop(Some((start, end)));
+        if position < start || position >= end {
+            s.set_current_position(start);
+        }
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+        Ok(())
+    }
+
+    pub fn clear_ab_loop(&mut self) {
+        if let Some(ctl) = self.playing.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            ctl.set_ab_loop(None);
+        }
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_ab_loop(None);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+    }
+
+    pub fn sleep_fader(&self) -> Arc<Fader> {
+        self.sleep_fader.clone()
+    }
+
+    /// 当前曲目按播放速率换算后的剩余播放时间
+    pub fn track_remaining(&self) -> Option<Duration> {
+        let remaining = self.playing.lock().unwrap_or_else(|e| e.into_inner()).as_ref()?.remaining()?;
+        Some(remaining.div_f32(self.playback_rate.rate()))
+    }
+
+    pub fn equalizer(&self) -> Arc<Equalizer> {
+        self.equalizer.clone()
+    }
+
+    pub fn stereo_image(&self) -> StereoSettings {
+        self.stereo.settings()
+    }
+
+    /// 设置左右平衡（-1 只有左声道，1 只有右声道），正在播放的曲目立即生效
+    pub fn set_balance(&mut self, balance: f32) -> Result<()> {
+        if !balance.is_finite() || !(-1.0..=1.0).contains(&balance) {
+            return Err(anyhow::anyhow!("balance must be between -1 and 1"));
+        }
+        self.stereo.set_balance(balance);
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_balance(balance);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+        Ok(())
+    }
+
+    /// 把所有声道混合为单声道，开启时不做交叉馈送
+    pub fn set_mono(&mut self, enabled: bool) {
+        self.stereo.set_mono(enabled);
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_mono(enabled);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+    }
+
+    /// 耳机交叉馈送强度，仅对立体声曲目生效
+    pub fn set_crossfeed(&mut self, crossfeed: Crossfeed) {
+        self.stereo.set_crossfeed(crossfeed);
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_crossfeed(crossfeed);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+    }
+
+    pub fn spectrum(&self) -> Arc<SpectrumAnalyzer> {
+        self.spectrum.clone()
+    }
+
+    /// 作废正在播放的曲目，被丢弃的 Source 不会再触发结束回调
+    fn cancel_playing(&mut self) {
+        if let Some(ctl) = self.playing.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            ctl.cancel();
+        }
+    }
+
+    fn set_playing(&mut self, ctl: Arc<TrackCtl>) {
+        let previous = self.playing.lock().unwrap_or_else(|e| e.into_inner()).replace(ctl);
+        if let Some(previous) = previous {
+            previous.cancel();
+        }
+    }
+
+    fn track_source(&self, built: SymphoniaSource, replay_gain: ReplayGain) -> TrackSource {
+        TrackSource::new(built, replay_gain, self.replay_gain_mode.clone(), self.track_end_callback())
+    }
+
+    /// 以新的 CrossfadeSource 追加到 sink，之后预排的曲目优先进入它的槽位
+    fn append_track(&mut self, track: TrackSource) {
+        let slot = new_slot();
+        let source = CrossfadeSource::new(track, slot.clone(), self.crossfade_ms.clone(), self.playback_rate.clone());
+        let source = TimeStretchSource::new(source, self.playback_rate.clone());
+        let source = EqSource::new(source, self.equalizer.clone());
+        let source = StereoSource::new(source, self.stereo.clone());
+        let source = FadeSource::new(source, self.fader.clone());
+        let source = FadeSource::new(source, self.sleep_fader.clone());
+        self.sink.append(SpectrumSource::new(source, self.spectrum.clone()));
+        if let Some(queue) = self.sink_queue.take() {
+            self.output.mixer().add(PrimedQueue::new(queue));
+        }
+        self.next_slot = slot;
+    }
+
+    /// 换用新的 sink 与淡入淡出包络，追加第一首曲目时才接入 mixer。
+    /// 需要淡出且正在播放时，旧 sink 在后台线程上淡出后再停止，控制线程不等待包络走完；
+    /// 换下的输出（如有）随旧 sink 一起释放。正在播放的曲目不再触发结束回调。
+    fn replace_sink(&mut self, fade_out: bool, output: Option<Box<dyn AudioOutput>>) {
+        let ctl = self.playing.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let (playing, volume) = {
+            let s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            (s.is_playing(), s.volume())
+        };
+        let (sink, queue) = Sink::new();
+        sink.set_volume(volume);
+        let old_sink = std::mem::replace(&mut self.sink, sink);
+        let old_fader = std::mem::replace(&mut self.fader, Arc::new(Fader::new()));
+        self.sink_queue = Some(queue);
+
+        let fade = self.fade_duration;
+        if !fade_out || fade.is_zero() || !playing {
+            if let Some(ctl) = ctl {
+                ctl.cancel();
+            }
+            old_sink.stop();
+            return;
+        }
+        if let Some(ctl) = &ctl {
+            ctl.detach();
+        }
+        old_fader.fade_to(0.0, fade);
+        std::thread::spawn(move || {
+            std::thread::sleep(fade);
+            if let Some(ctl) = ctl {
+                ctl.cancel();
+            }
+            old_sink.stop();
+            drop(output);
+        });
+    }
+
+    /// sink 重建后把预排曲目重新追加回去
+    fn requeue_upcoming(&mut self) {
+        let queued: Vec<(PathBuf, ReplayGain, TrackRange)> = {
+            let mut upcoming = self.upcoming.lock().unwrap_or_else(|e| e.into_inner());
+            upcoming.drain(..)
+                .map(|queued| {
+                    queued.ctl.cancel();
+                    (queued.path, queued.replay_gain, queued.range)
+                })
+                .collect()
+        };
+        for (path, replay_gain, range) in queued {
+            if let Err(e) = self.queue_next(&path, replay_gain, range) {
+                tracing::warn!("requeue {:?} failed: {}", path, e);
+            }
+        }
+    }
+
+    fn fade_in(&self) {
+        if self.fade_duration.is_zero() {
+            self.fader.set(1.0);
+        } else {
+            self.fader.set(0.0);
+            self.fader.fade_to(1.0, self.fade_duration);
+        }
+    }
+
+    /// 暂停的淡出走完后在音频线程上执行：把状态里的位置更新为曲目实际停下的位置；
+    /// 其间位置已被跳转等操作改动时保留改动后的位置
+    fn paused_position_action(&self, paused_at: Duration) -> impl FnOnce(&Fader) + Send + 'static {
+        let state = self.state.clone();
+        let playing = self.playing.clone();
+        let events = self.events.clone();
+        move |_| {
+            let Some(ctl) = playing.lock().unwrap_or_else(|e| e.into_inner()).clone() else { return; };
+            let mut s = state.lock().unwrap_or_else(|e| e.into_inner());
+            if !s.is_paused() || s.current_position() != paused_at {
+                return;
+            }
+            s.set_current_position(ctl.position());
+            let snapshot = StateSnapshot::from(&*s);
+            events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+        }
+    }
+
+    /// 曲目的 Source 耗尽时在音频线程上执行：切换到下一首预排曲目并通知前端，
+    /// 没有预排曲目时停止并发布 TrackEnded
+    fn track_end_callback(&self) -> Box<dyn FnOnce() + Send> {
+        let state = self.state.clone();
+        let upcoming = self.upcoming.clone();
+        let playing = self.playing.clone();
+        let playing_gain = self.playing_gain.clone();
+        let playing_range = self.playing_range.clone();
+        let events = self.events.clone();
+
+        Box::new(move || {
+            let next = upcoming.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+            let Some(next) = next else {
+                let mut s = state.lock().unwrap_or_else(|e| e.into_inner());
+                if !s.is_playing() {
+                    return;
+                }
+                if let Some(total) = s.total_duration() {
+                    s.set_current_position(total);
+                }
+                s.set_playback_state(PlaybackState::Stopped);
+                let snapshot = StateSnapshot::from(&*s);
+                events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+                events.publish(PlayerEvent::TrackEnded);
+                return;
+            };
+            *playing_gain.lock().unwrap_or_else(|e| e.into_inner()) = next.replay_gain;
+            *playing_range.lock().unwrap_or_else(|e| e.into_inner()) = next.range;
+            *playing.lock().unwrap_or_else(|e| e.into_inner()) = Some(next.ctl.clone());
+
+            let file_path = next.path.to_string_lossy().to_string();
+            let mut s = state.lock().unwrap_or_else(|e| e.into_inner());
+            s.set_current_file(Some(file_path.clone()));
+            s.set_total_duration(next.total_duration);
+            s.set_current_position(Duration::ZERO);
+            s.set_ab_loop(None);
+            let snapshot = StateSnapshot::from(&*s);
+            events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+            events.publish(PlayerEvent::TrackChanged(TrackChanged {
+                file_path,
+                total_duration: next.total_duration.map(|d| d.as_millis() as u64),
+            }));
+        })
+    }
+
+    /// 淡出后由音频线程停在静音处，不等待包络走完
+    pub fn pause(&mut self) {
+        let paused_at = {
+            let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            s.set_playback_state(PlaybackState::Paused);
+            if let Some(ctl) = self.playing.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+                s.set_current_position(ctl.position());
+            }
+            let snapshot = StateSnapshot::from(&*s);
+            self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+            s.current_position()
+        };
+        self.fader.pause(self.fade_duration);
+        self.fader.then(self.paused_position_action(paused_at));
+    }
+
+    pub fn resume(&mut self) {
+        self.fader.resume(self.fade_duration);
+        self.sink.play();
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_playback_state(PlaybackState::Playing);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+    }
+
+    pub fn stop(&mut self) {
+        self.clear_queued();
+        self.replace_sink(true, None);
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_playback_state(PlaybackState::Stopped);
+        s.set_current_position(Duration::ZERO);
+        s.set_current_file(None);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_volume(volume);
+        self.sink.set_volume(volume);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+    }
+
+    /// 切换输出设备（None 为系统默认），保持当前曲目、位置与音量
+    pub fn set_output_device(&mut self, name: Option<String>) -> Result<()> {
+        let (stream, _) = open_output_stream(name.as_deref(), &self.events)?;
+        self.output_device = name;
+        _ = set_config_value(&connection(), "output_device", self.output_device.as_deref().unwrap_or(""));
+        self.switch_output(Box::new(stream), true)?;
+        self.events.publish(PlayerEvent::OutputDeviceChanged(OutputDeviceChanged {
+            name: self.output_device.clone(),
+            fallback: false,
+        }));
+        Ok(())
+    }
+
+    pub fn output_device(&self) -> Option<String> {
+        self.output_device.clone()
+    }
+
+    /// 当前输出设备丢失后重新打开：用户选择的设备仍在则继续使用，否则回退到默认设备。
+    /// 不修改持久化的选择，设备重新接入后可再次切回。
+    pub fn recover_output_device(&mut self) -> Result<()> {
+        let (stream, opened) = open_output_stream(self.output_device.as_deref(), &self.events)?;
+        self.switch_output(Box::new(stream), false)?;
+        tracing::info!("output device recovered on {:?}", opened);
+        self.events.publish(PlayerEvent::OutputDeviceChanged(OutputDeviceChanged {
+            fallback: opened != self.output_device,
+            name: opened,
+        }));
+        Ok(())
+    }
+
+    /// 换用新的输出，正在播放或暂停的曲目从当前位置重建
+    fn switch_output(&mut self, output: Box<dyn AudioOutput>, fade_out: bool) -> Result<()> {
+        let position = self.playing.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|ctl| ctl.position());
+        let active = !self.state.lock().unwrap_or_else(|e| e.into_inner()).is_stopped();
+        let previous = std::mem::replace(&mut self.output, output);
+        self.replace_sink(fade_out, Some(previous));
+
+        match position {
+            Some(position) if active => self.rebuild_at(position, false),
+            _ => Ok(()),
+        }
+    }
+
+    /// 在正在播放的 Source 内原地跳转，保留 sink 与输出流；
+    /// 曲目已播完或尚未加载时退回到重建 Source
+    pub fn seek(&mut self, position: Duration) -> Result<()> {
+        let ctl = self.playing.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let live = ctl.filter(|ctl| !ctl.is_cancelled() && !ctl.is_finished());
+        let Some(ctl) = live else {
+            return self.rebuild_at(position, true);
+        };
+
+        let was_playing = self.state.lock().unwrap_or_else(|e| e.into_inner()).is_playing();
+        if was_playing && !self.fade_duration.is_zero() {
+            // 淡出走完后由音频线程跳转并淡入，其间进度停在目标位置
+            let fade = self.fade_duration;
+            ctl.freeze_at(position);
+            self.fader.fade_to(0.0, fade);
+            self.fader.then(move |fader| {
+                ctl.request_seek(position);
+                if !fader.is_paused() {
+                    fader.fade_to(1.0, fade);
+                }
+            });
+        } else {
+            ctl.request_seek(position);
+        }
+
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_current_position(position);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+
+        Ok(())
+    }
+
+    /// 重新打开文件并重建 sink 来跳转（兼容大多数压缩/封装）
+    fn rebuild_at(&mut self, position: Duration, fade_out: bool) -> Result<()> {
+        let target_path = {
+            let s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            s.current_file()
+                .map(|p| p.to_string())
+                .ok_or_else(|| anyhow::anyhow!("cannot seek: no current file loaded"))?
+        };
+
+        let range = *self.playing_range.lock().unwrap_or_else(|e| e.into_inner());
+        let built = SymphoniaSource::from_path_range(Path::new(&target_path), range, position)?;
+        let total = built.total_duration;
+
+        let was_playing = self.state.lock().unwrap_or_else(|e| e.into_inner()).is_playing();
+        self.replace_sink(fade_out, None);
+        if was_playing {
+            self.fade_in();
+        }
+        let replay_gain = *self.playing_gain.lock().unwrap_or_else(|e| e.into_inner());
+        let source = self.track_source(built, replay_gain);
+        // 重建后沿用原来的 A–B 循环
+        let ab_loop = self.state.lock().unwrap_or_else(|e| e.into_inner()).ab_loop();
+        source.ctl.set_ab_loop(ab_loop);
+        self.set_playing(source.ctl());
+        self.append_track(source);
+        self.requeue_upcoming();
+
+        if self.state.lock().unwrap_or_else(|e| e.into_inner()).is_paused() {
+            self.sink.pause();
+        } else {
+            self.sink.play();
+        }
+
+        {
+            let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            s.set_total_duration(total);
+            s.set_current_position(position);
+            let snapshot = StateSnapshot::from(&*s);
+            self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+        }
+
+        Ok(())
+    }
+
+    pub fn shutdown(&mut self) {
+        self.progress.stop();
+        self.clear_queued();
+        self.cancel_playing();
+        self.sink.stop();
+        // 清理状态
+        let mut s = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        s.set_playback_state(PlaybackState::Stopped);
+        s.set_current_position(Duration::ZERO);
+        s.set_current_file(None);
+        let snapshot = StateSnapshot::from(&*s);
+        self.events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+    }
+}
+
+/* ====================== 进度上报 ======================== */
+
+/// 正在播放曲目的控制句柄，曲目边界上由音频线程切换
+type PlayingTrack = Arc<Mutex<Option<Arc<TrackCtl>>>>;
+
+/// 定期把正在播放曲目的帧计数换算成位置写入状态并通知前端；
+/// 位置完全来自音频线程实际取出的帧数，不依赖墙钟。
+struct ProgressClock {
+    inner: Option<ProgressInner>,
+}
+
+struct ProgressInner {
+    handle: std::thread::JoinHandle<()>,
+    quit: Arc<AtomicBool>,
+}
+
+impl ProgressClock {
+    fn new() -> Self {
+        Self { inner: None }
+    }
+
+    fn start(&mut self, playing: PlayingTrack, state: SharedState, events: EventBus) {
+        self.stop();
+
+        let quit = Arc::new(AtomicBool::new(false));
+        let quit2 = quit.clone();
+        let handle = std::thread::spawn(move || {
+            let tick = Duration::from_millis(200);
+            while !quit2.load(Ordering::Relaxed) {
+                std::thread::sleep(tick);
+
+                let ctl = playing.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                let Some(ctl) = ctl else { continue; };
+
+                let mut s = state.lock().unwrap_or_else(|e| e.into_inner());
+                if !s.is_playing() {
+                    continue;
+                }
+                let pos = ctl.position();
+                let pos = s.total_duration().map_or(pos, |total| pos.min(total));
+                s.set_current_position(pos);
+                let snapshot = StateSnapshot::from(&*s);
+                events.publish(PlayerEvent::StateUpdated(Box::new(snapshot)));
+            }
+        });
+
+        self.inner = Some(ProgressInner { handle, quit });
+    }
+
+    fn stop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            inner.quit.store(true, Ordering::Relaxed);
+            let _ = inner.handle.join();
+        }
+    }
+}
+
+/* ====================== Track Source ======================== */
+
+/// 单首曲目在音频线程与控制线程之间共享的状态
+struct TrackCtl {
+    cancelled: AtomicBool,
+    seeking: AtomicBool,       // 跳转等待淡出期间不计帧，进度停在目标位置
+    seek_tx: Mutex<mpsc::Sender<Duration>>, // 发往音频线程的跳转命令
+    frames: AtomicU64,         // 已从解码器取出的帧数（含起播偏移）
+    total_frames: Option<u64>, // 整首曲目的帧数
+    loop_start: AtomicU64,     // A–B 循环起点帧
+    loop_end: AtomicU64,       // A–B 循环终点帧，0 表示未设置
+    sample_rate: u32,
+    on_end: Mutex<Option<Box<dyn FnOnce() + Send>>>,
+}
+
+impl TrackCtl {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 请求音频线程在下一帧原地跳转；先更新帧计数，让进度立即反映目标位置
+    fn request_seek(&self, position: Duration) {
+        self.freeze_at(position);
+        _ = self.seek_tx.lock().unwrap_or_else(|e| e.into_inner()).send(position);
+    }
+
+    /// 跳转要等淡出走完时先把进度定在目标位置，音频线程执行跳转前不再计帧
+    fn freeze_at(&self, position: Duration) {
+        self.seeking.store(true, Ordering::Relaxed);
+        self.frames.store(self.duration_to_frames(position), Ordering::Relaxed);
+    }
+
+    fn set_ab_loop(&self, ab_loop: Option<(Duration, Duration)>) {
+        match ab_loop {
+            Some((start, end)) => {
+                self.loop_end.store(0, Ordering::Relaxed);
+                self.loop_start.store(self.duration_to_frames(start), Ordering::Relaxed);
+                self.loop_end.store(self.duration_to_frames(end).max(1), Ordering::Relaxed);
+            }
+            None => self.loop_end.store(0, Ordering::Relaxed),
+        }
+    }
+
+    /// 当前的 A–B 循环区间（帧）
+    fn ab_loop(&self) -> Option<(u64, u64)> {
+        let end = self.loop_end.load(Ordering::Relaxed);
+        (end != 0).then(|| (self.loop_start.load(Ordering::Relaxed), end))
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_frames.map(|frames| self.frames_to_duration(frames))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.on_end.lock().unwrap_or_else(|e| e.into_inner()).is_none()
+    }
+
+    /// 丢弃结束回调：淡出中被换下的曲目播完时不再切换曲目
+    fn detach(&self) {
+        self.on_end.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+
+    /// 触发曲目结束回调，只会执行一次
+    fn finish(&self) {
+        let on_end = self.on_end.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(on_end) = on_end {
+            on_end();
+        }
+    }
+
+    fn duration_to_frames(&self, duration: Duration) -> u64 {
+        (duration.as_secs_f64() * self.sample_rate as f64) as u64
+    }
+
+    fn frames_to_duration(&self, frames: u64) -> Duration {
+        Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+    }
+
+    /// 按实际取出的帧数计算的播放位置
+    fn position(&self) -> Duration {
+        self.frames_to_duration(self.frames.load(Ordering::Relaxed))
+    }
+
+    /// 距离曲目结束的时间；A–B 循环中曲目不会结束，返回 None
+    fn remaining(&self) -> Option<Duration> {
+        if self.ab_loop().is_some() {
+            return None;
+        }
+        let total = self.total_frames?;
+        Some(self.frames_to_duration(total.saturating_sub(self.frames.load(Ordering::Relaxed))))
+    }
+}
+
+/// 包装 SymphoniaSource：应用 ReplayGain，自然播放完毕时回调一次，作废后直接结束且不回调。
+struct TrackSource {
+    inner: SymphoniaSource,
+    ctl: Arc<TrackCtl>,
+    seek_rx: mpsc::Receiver<Duration>,
+    replay_gain: ReplayGain,
+    gain_mode: Arc<AtomicU8>,
+    gain_cache: (u8, f32), // (模式, 线性增益)，模式变化时重算
+    channel: u16,          // 当前帧内的声道序号，凑满一帧才计数
+    channels: u16,
+}
+
+impl TrackSource {
+    fn new(inner: SymphoniaSource, replay_gain: ReplayGain, gain_mode: Arc<AtomicU8>, on_end: Box<dyn FnOnce() + Send>) -> Self {
+        let sample_rate = inner.sample_rate;
+        let to_frames = |d: Duration| (d.as_secs_f64() * sample_rate as f64) as u64;
+        let (seek_tx, seek_rx) = mpsc::channel();
+        let ctl = Arc::new(TrackCtl {
+            cancelled: AtomicBool::new(false),
+            seeking: AtomicBool::new(false),
+            seek_tx: Mutex::new(seek_tx),
+            frames: AtomicU64::new(to_frames(inner.start_position)),
+            total_frames: inner.total_frames.or_else(|| inner.total_duration.map(to_frames)),
+            loop_start: AtomicU64::new(0),
+            loop_end: AtomicU64::new(0),
+            sample_rate,
+            on_end: Mutex::new(Some(on_end)),
+        });
+        let mode = gain_mode.load(Ordering::Relaxed);
+        let gain_cache = (mode, replay_gain.factor(ReplayGainMode::from_u8(mode)));
+        let channels = inner.channels_count.max(1);
+        Self { inner, ctl, seek_rx, replay_gain, gain_mode, gain_cache, channel: 0, channels }
+    }
+
+    /// 处理挂起的跳转命令，连续拖动时只执行最后一次
+    fn apply_pending_seek(&mut self) {
+        if let Some(position) = self.seek_rx.try_iter().last() {
+            self.seek_inner(position);
+            self.ctl.seeking.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// 到达 A–B 循环终点（或曲目在终点前结束）时回到起点；返回是否发生了跳转
+    fn apply_ab_loop(&mut self, at_end: bool) -> bool {
+        let Some((start, end)) = self.ctl.ab_loop() else { return false; };
+        if !at_end && self.ctl.frames.load(Ordering::Relaxed) < end {
+            return false;
+        }
+        self.seek_inner(self.ctl.frames_to_duration(start))
+    }
+
+    fn seek_inner(&mut self, position: Duration) -> bool {
+        match self.inner.seek_to(position) {
+            Ok(actual) => {
+                self.ctl.frames.store(self.ctl.duration_to_frames(actual), Ordering::Relaxed);
+                true
+            }
+            Err(e) => {
+                tracing::warn!("in-place seek to {:?} failed: {}", position, e);
+                false
+            }
+        }
+    }
+
+    fn gain(&mut self) -> f32 {
+        let mode = self.gain_mode.load(Ordering::Relaxed);
+        if mode != self.gain_cache.0 {
+            self.gain_cache = (mode, self.replay_gain.factor(ReplayGainMode::from_u8(mode)));
+        }
+        self.gain_cache.1
+    }
+
+    fn ctl(&self) -> Arc<TrackCtl> {
+        self.ctl.clone()
+    }
+}
+
+impl Iterator for TrackSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ctl.is_cancelled() {
+            return None;
+        }
+        if self.channel == 0 {
+            self.apply_pending_seek();
+            self.apply_ab_loop(false);
+        }
+        let sample = match self.inner.next() {
+            // 循环终点在曲目末尾之后时，播完即回到起点
+            None if self.channel == 0 && self.apply_ab_loop(true) => self.inner.next(),
+            sample => sample,
+        };
+        match sample {
+            Some(sample) => {
+                self.channel += 1;
+                if self.channel == self.channels {
+                    self.channel = 0;
+                    if !self.ctl.seeking.load(Ordering::Relaxed) {
+                        self.ctl.frames.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                Some(sample * self.gain())
+            }
+            None => {
+                self.ctl.finish();
+                None
+            }
+        }
+    }
+}
+
+impl Source for TrackSource {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/* ====================== Primed Queue ======================== */
+
+/// rodio 的 sink 队列在取到第一个 Source 之前以单声道的空 Source 报告格式，mixer 据此建立的第一段
+/// 会把立体声曲目的前 512 个样本当作单声道复制到两个声道。先把曲目追加到队列，
+/// 取出一个样本让队列切换到该曲目，再交给 mixer，第一段即按曲目的实际格式与分段长度建立。
+struct PrimedQueue {
+    first: Option<f32>,
+    queue: SourcesQueueOutput,
+}
+
+impl PrimedQueue {
+    fn new(mut queue: SourcesQueueOutput) -> Self {
+        let first = queue.next();
+        Self { first, queue }
+    }
+}
+
+impl Iterator for PrimedQueue {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.first.take().or_else(|| self.queue.next())
+    }
+}
+
+impl Source for PrimedQueue {
+    fn current_span_len(&self) -> Option<usize> {
+        self.queue.current_span_len().map(|n| n + self.first.is_some() as usize)
+    }
+    fn channels(&self) -> u16 {
+        self.queue.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.queue.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/* ====================== Crossfade Source ======================== */
+
+/// CrossfadeSource 的下一首槽位；Source 播完后关闭，之后的曲目需另行追加到 sink
+struct SlotInner {
+    track: Option<TrackSource>,
+    closed: bool,
+}
+
+type NextSlot = Arc<Mutex<SlotInner>>;
+
+fn new_slot() -> NextSlot {
+    Arc::new(Mutex::new(SlotInner { track: None, closed: false }))
+}
+
+/// 在当前曲目最后 N 秒内与槽位中的下一首做等功率交叉淡化；N 为 0 时退化为无缝衔接。
+/// 输出格式固定为首个曲目的声道数与采样率，格式不同的后续曲目会先转换。
+struct CrossfadeSource {
+    current: Box<dyn Source + Send>,
+    current_ctl: Arc<TrackCtl>,
+    outgoing: Option<Box<dyn Source + Send>>,
+    fade_pos: u64,
+    fade_len: u64,
+    slot: NextSlot,
+    crossfade_ms: Arc<AtomicU64>,
+    playback_rate: Arc<PlaybackRate>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl CrossfadeSource {
+    fn new(track: TrackSource, slot: NextSlot, crossfade_ms: Arc<AtomicU64>, playback_rate: Arc<PlaybackRate>) -> Self {
+        let channels = track.channels();
+        let sample_rate = track.sample_rate();
+        let current_ctl = track.ctl();
+        Self {
+            current: Box::new(track),
+            current_ctl,
+            outgoing: None,
+            fade_pos: 0,
+            fade_len: 0,
+            slot,
+            crossfade_ms,
+            playback_rate,
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// 从槽位取出下一首，必要时转换成本 Source 的输出格式；close 为真时槽位为空即关闭
+    fn take_next(&mut self, close: bool) -> Option<(Box<dyn Source + Send>, Arc<TrackCtl>)> {
+        let track = {
+            let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+            if slot.track.is_none() && close {
+                slot.closed = true;
+            }
+            slot.track.take()?
+        };
+        let ctl = track.ctl();
+        let source: Box<dyn Source + Send> =
+            if track.channels() == self.channels && track.sample_rate() == self.sample_rate {
+                Box::new(track)
+            } else {
+                Box::new(UniformSourceIterator::new(track, self.channels, self.sample_rate))
+            };
+        Some((source, ctl))
+    }
+
+    fn should_start_crossfade(&self) -> Option<Duration> {
+        // 交叉淡化时长按实际听感计算，换算成曲目内容时间需乘以播放速率
+        let crossfade = Duration::from_millis(self.crossfade_ms.load(Ordering::Relaxed))
+            .mul_f32(self.playback_rate.rate());
+        if crossfade.is_zero() || self.outgoing.is_some() {
+            return None;
+        }
+        let remaining = self.current_ctl.remaining()?;
+        if remaining > crossfade || remaining.is_zero() {
+            return None;
+        }
+        Some(remaining)
+    }
+}
+
+impl Iterator for CrossfadeSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(remaining) = self.should_start_crossfade() {
+            if let Some((next, next_ctl)) = self.take_next(false) {
+                // 淡化开始即视为切歌，进度从下一首的起点开始计
+                self.current_ctl.finish();
+                self.outgoing = Some(std::mem::replace(&mut self.current, next));
+                self.current_ctl = next_ctl;
+                self.fade_pos = 0;
+                self.fade_len = (remaining.as_secs_f64()
+                    * self.sample_rate as f64
+                    * self.channels as f64) as u64;
+            }
+        }
+
+        if let Some(outgoing) = self.outgoing.as_mut() {
+            let t = (self.fade_pos as f32 / self.fade_len.max(1) as f32).min(1.0);
+            let angle = t * std::f32::consts::FRAC_PI_2;
+            let out = outgoing.next();
+            let incoming = self.current.next().unwrap_or(0.0);
+            self.fade_pos += 1;
+            match out {
+                Some(out) => return Some(out * angle.cos() + incoming * angle.sin()),
+                None => {
+                    self.outgoing = None;
+                    return Some(incoming);
+                }
+            }
+        }
+
+        loop {
+            if let Some(sample) = self.current.next() {
+                return Some(sample);
+            }
+            // 当前曲目已结束（或被作废），无缝切到槽位中的下一首
+            let (next, next_ctl) = self.take_next(true)?;
+            self.current = next;
+            self.current_ctl = next_ctl;
+        }
+    }
+}
+
+impl Source for CrossfadeSource {
+    /// 输出格式固定，按当前曲目的解码缓冲分段；交叉淡化时以先耗尽的一方为界
+    fn current_span_len(&self) -> Option<usize> {
+        let current = self.current.current_span_len();
+        match self.outgoing.as_ref().and_then(|outgoing| outgoing.current_span_len()) {
+            Some(outgoing) => Some(current.map_or(outgoing, |current| current.min(outgoing))),
+            None => current,
+        }
+    }
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/* ====================== Symphonia Source ======================== */
+
+/// 将 symphonia 的解码结果包装成 rodio::Source；支持从任意时间点开始。
+/// 指定 TrackRange 时只输出文件中的这一段，对外的时长、位置与跳转都相对于区间起点。
+struct SymphoniaSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+
+    sample_rate: u32,
+    channels_count: u16,
+    signal_spec: SignalSpec,
+    time_base: Option<TimeBase>,
+    skip_until: Option<u64>, // 跳转后丢弃时间戳早于此值的帧
+    range_start: Duration,   // 区间在文件中的起点
+    end_ts: Option<u64>,     // 区间终点的时间戳，到达后视为曲目结束
+
+    buf: Vec<f32>,
+    buf_pos: usize,
+    failed: bool, // 解码出错，曲目到此结束；跳转后重新尝试
+
+    total_duration: Option<Duration>,
+    total_frames: Option<u64>,
+    start_position: Duration,
+}
+
+impl SymphoniaSource {
+    pub fn from_path_range(path: &Path, range: TrackRange, start: Duration) -> Result<Self> {
+        // 远程地址以 Range 请求读取；本地直接使用 File 以支持随机访问：APE、WavPack 等格式按帧定位
+        let url = path.to_str().filter(|p| remote::is_remote(p));
+        let source: Box<dyn MediaSource> = match url {
+            Some(url) => Box::new(HttpMediaSource::open(url)?),
+            None => Box::new(File::open(path)?),
+        };
+        let mss = MediaSourceStream::new(source, MediaSourceStreamOptions::default());
+
+        // 扩展名可能与内容不符，本地文件以嗅探结果作为格式提示
+        let mut hint = Hint::new();
+        let ext = match url {
+            Some(url) => remote::extension(url),
+            None => sniff(path)
+                .map(|f| f.extension())
+                .or_else(|| path.extension().and_then(|e| e.to_str())),
+        };
+        if let Some(ext) = ext {
+            hint.with_extension(ext);
+        }
+
+        let probed = codecs::probe()
+            .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())?;
+
+        let format = probed.format;
+
+        let track = format
+            .default_track()
+            .ok_or_else(|| anyhow::anyhow!("no default track"))?;
+        if track.codec_params.codec == CODEC_TYPE_NULL {
+            return Err(anyhow::anyhow!("unsupported codec"));
+        }
+
+        let decoder = codecs::codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or_else(|| anyhow::anyhow!("missing sample_rate"))?;
+        let channels = track
+            .codec_params
+            .channels
+            .ok_or_else(|| anyhow::anyhow!("missing channels"))?;
+        let channels_count = channels.count() as u16;
+
+        let signal_spec = SignalSpec { rate: sample_rate, channels };
+
+        let file_duration = calc_total_duration(&track.codec_params);
+        let track_id = track.id;
+        let time_base = track.codec_params.time_base;
+
+        // 区间内的时长与帧数；整个文件时直接取编码参数
+        let range_end = match (range.end, file_duration) {
+            (Some(end), Some(total)) => Some(end.min(total)),
+            (end, total) => end.or(total),
+        };
+        let (total_duration, total_frames) = if range == TrackRange::default() {
+            (file_duration, track.codec_params.n_frames)
+        } else {
+            let duration = range_end.map(|end| end.saturating_sub(range.start));
+            (duration, duration.map(|d| (d.as_secs_f64() * sample_rate as f64) as u64))
+        };
+        let end_ts = range.end
+            .and_then(|end| time_base.map(|tb| tb.calc_timestamp(time_from_duration(end))));
+
+        let mut source = Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            channels_count,
+            signal_spec,
+            time_base,
+            skip_until: None,
+            range_start: range.start,
+            end_ts,
+            buf: Vec::new(),
+            buf_pos: 0,
+            failed: false,
+            total_duration,
+            total_frames,
+            start_position: start,
+        };
+
+        // seek 到目标起点
+        if range.start + start > Duration::ZERO {
+            source.start_position = source.seek_to(start)?;
+        }
+
+        Ok(source)
+    }
+
+    /// 原地跳转：定位到目标之前的数据包，重置解码器并清空缓冲，解码时丢弃目标之前的帧。
+    /// position 与返回的实际起播位置都相对于区间起点
+    fn seek_to(&mut self, position: Duration) -> Result<Duration, SymphoniaError> {
+        let position = self.range_start + position;
+        let time = time_from_duration(position);
+        let seeked = match self.time_base {
+            Some(tb) => self.format.seek(
+                SeekMode::Accurate,
+                SeekTo::TimeStamp { ts: tb.calc_timestamp(time), track_id: self.track_id },
+            )?,
+            None => self.format.seek(
+                SeekMode::Accurate,
+                SeekTo::Time { time, track_id: Some(self.track_id) },
+            )?,
+        };
+
+        self.decoder.reset();
+        self.buf.clear();
+        self.buf_pos = 0;
+        self.failed = false;
+        self.skip_until = Some(seeked.required_ts);
+
+        let actual = match self.time_base {
+            Some(tb) => duration_from_time(tb.calc_time(seeked.required_ts)),
+            None => position,
+        };
+        Ok(actual.saturating_sub(self.range_start))
+    }
+
+    /// 时间戳差值换算成帧数
+    fn ts_to_frames(&self, ts: u64) -> u64 {
+        match self.time_base {
+            Some(tb) => (duration_from_time(tb.calc_time(ts)).as_secs_f64() * self.sample_rate as f64) as u64,
+            None => ts,
+        }
+    }
+
+    /// 提前解码首个数据包，避免切换到该曲目时在音频线程上才开始解码
+    fn prime(&mut self) -> Result<()> {
+        if self.buf_pos >= self.buf.len() {
+            self.refill()?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn refill(&mut self) -> Result<(), SymphoniaError> {
+        self.buf.clear();
+        self.buf_pos = 0;
+
+        loop {
+            match self.format.next_packet() {
+                Ok(packet) => {
+                    if packet.track_id() != self.track_id {
+                        continue;
+                    }
+                    let decoded = self.decoder.decode(&packet)?;
+
+                    let spec = *decoded.spec();
+                    let frames = decoded.frames();
+                    if frames == 0 {
+                        continue; // <- 这里跳过空帧
+                    }
+
+                    let (ts, dur) = (packet.ts(), packet.dur());
+                    // 已到区间终点：缓冲留空，上层视为曲目结束
+                    if self.end_ts.is_some_and(|end| ts >= end) {
+                        return Ok(());
+                    }
+
+                    let mut sbuf = SampleBuffer::<f32>::new(frames as u64, spec);
+                    sbuf.copy_interleaved_ref(decoded);
+
+                    // 跳转后目标位置之前的帧只解码不输出
+                    let mut skip = 0;
+                    if let Some(required) = self.skip_until {
+                        if ts + dur <= required {
+                            continue;
+                        }
+                        self.skip_until = None;
+                        if ts < required {
+                            skip = self.ts_to_frames(required - ts).min(frames as u64) as usize;
+                        }
+                    }
+                    // 区间终点落在本包内时截断
+                    let keep = match self.end_ts {
+                        Some(end) if ts + dur > end => self.ts_to_frames(end - ts).min(frames as u64) as usize,
+                        _ => frames,
+                    };
+                    if keep <= skip {
+                        return Ok(());
+                    }
+                    let channels = spec.channels.count();
+                    self.buf.extend_from_slice(&sbuf.samples()[skip * channels..keep * channels]);
+
+                    if !self.buf.is_empty() {
+                        return Ok(());
+                    }
+                }
+                Err(SymphoniaError::ResetRequired) => {
+                    self.decoder.reset();
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl Iterator for SymphoniaSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if self.buf_pos >= self.buf.len() {
+            if let Err(_) = self.refill() {
+                return None;
+            }
+            if self.buf_pos >= self.buf.len() {
+                return None;
+            }
+        }
+        let s = self.buf[self.buf_pos];
+        self.buf_pos += 1;
+        // 取完缓冲后立即解码下一包，让 current_span_len 始终报告准确的剩余长度
+        if self.buf_pos >= self.buf.len() && self.refill().is_err() {
+            self.failed = true;
+        }
+        Some(s)
+    }
+}
+
+impl Source for SymphoniaSource {
+    /// 缓冲中剩余的样本数；尚未解码、刚跳转或已结束时为 None
+    fn current_span_len(&self) -> Option<usize> {
+        let remaining = self.buf.len() - self.buf_pos;
+        (remaining > 0).then_some(remaining)
+    }
+    fn channels(&self) -> u16 {
+        self.channels_count
+    }
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+}
+
+/* ---------------- 辅助函数 ---------------- */
+
+/// 读取数值型配置，缺失或无法解析时返回 None
+fn read_config_number(key: &str) -> Option<f32> {
+    get_config_value(&connection(), key).ok()?.value.trim().parse().ok()
+}
+
+/// Time.frac 是 [0, 1) 内的秒数小数部分，calc_timestamp 对越界的值会 panic
+fn time_from_duration(dur: Duration) -> Time {
+    Time {
+        seconds: dur.as_secs(),
+        frac: dur.subsec_nanos() as f64 / 1_000_000_000.0,
+    }
+}
+
+fn duration_from_time(time: Time) -> Duration {
+    Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+}
+
+fn calc_total_duration(params: &symphonia::core::codecs::CodecParameters) -> Option<Duration> {
+    let n_frames = params.n_frames?;
+    let tb = params.time_base?;
+    let t = tb.calc_time(n_frames);
+
+    let secs = t.seconds.max(0) as u64;
+    let frac_secs = n_frames as f64 / params.sample_rate? as f64 - secs as f64;
+
+    Some(Duration::from_secs(secs) + Duration::from_secs_f64(frac_secs))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+    use super::*;
+    use crate::database::tests::isolate_app_data;
+    use crate::player::output::{tests::write_constant_wav, NullOutput};
+    use crate::player::state::new_shared_state;
+
+    #[test]
+    fn fades_do_not_block_commands() {
+        isolate_app_data();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("track.wav");
+        write_constant_wav(&path, 0.25, Duration::from_secs(6));
+
+        let state = new_shared_state();
+        let position = || state.lock().unwrap().current_position();
+        let mut backend = AudioBackend::with_output(state.clone(), Box::new(NullOutput::new(2, 44100)), EventBus::new());
+        backend.fade_duration = Duration::from_millis(500);
+        backend.load_and_play(&path, Duration::ZERO, ReplayGain::default(), TrackRange::default()).unwrap();
+        // 等起播的淡入走完
+        std::thread::sleep(Duration::from_millis(800));
+
+        // 包络由音频线程走完，命令立即返回
+        let quick = Duration::from_millis(100);
+        let started = Instant::now();
+        backend.seek(Duration::from_secs(3)).unwrap();
+        assert!(started.elapsed() < quick, "{:?}", started.elapsed());
+
+        // 淡出期间进度停在目标位置，淡出后从目标位置继续
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(position(), Duration::from_secs(3));
+        std::thread::sleep(Duration::from_millis(700));
+        let resumed = position();
+        assert!(resumed > Duration::from_secs(3) && resumed < Duration::from_millis(3800), "{resumed:?}");
+
+        // 暂停时淡出照常播放，静音后位置停在包络结束处
+        let paused_at = position();
+        let started = Instant::now();
+        backend.pause();
+        assert!(started.elapsed() < quick, "{:?}", started.elapsed());
+        std::thread::sleep(Duration::from_millis(900));
+        let stopped_at = position();
+        assert!(stopped_at > paused_at + Duration::from_millis(300), "{paused_at:?} {stopped_at:?}");
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(position(), stopped_at);
+
+        let started = Instant::now();
+        backend.resume();
+        assert!(started.elapsed() < quick, "{:?}", started.elapsed());
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(position() > stopped_at, "{:?}", position());
+
+        let started = Instant::now();
+        backend.stop();
+        assert!(started.elapsed() < quick, "{:?}", started.elapsed());
+        assert!(state.lock().unwrap().is_stopped());
+        assert!(backend.playing.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,278 @@
+use std::io::{Read, Seek, SeekFrom};
+use ape_decoder::{format::ApeFileInfo, FrameDecoder};
+use symphonia::core::{
+    audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec},
+    codecs::{
+        decl_codec_type, CodecDescriptor, CodecParameters, CodecType, Decoder, DecoderOptions,
+        FinalizeResult,
+    },
+    errors::{decode_error, seek_error, unsupported_error, Error, Result, SeekErrorKind},
+    formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track},
+    io::MediaSourceStream,
+    meta::{Metadata, MetadataLog},
+    probe::{Descriptor, Instantiate, QueryDescriptor},
+    units::TimeBase,
+};
+
+use super::channels_from_count;
+
+pub const CODEC_TYPE_APE: CodecType = decl_codec_type(b"ape");
+
+const MIN_VERSION: u16 = 3950;
+const MAX_FRAME_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Monkey's Audio 容器：一个数据包为一帧，包首字节为该帧相对 4 字节对齐的偏移。
+pub struct ApeReader {
+    reader: MediaSourceStream,
+    info: ApeFileInfo,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    frame: u32,
+}
+
+impl QueryDescriptor for ApeReader {
+    fn query() -> &'static [Descriptor] {
+        &[Descriptor {
+            short_name: "ape",
+            long_name: "Monkey's Audio",
+            extensions: &["ape"],
+            mime_types: &["audio/ape", "audio/x-ape"],
+            markers: &[b"MAC "],
+            score: Self::score,
+            inst: Instantiate::Format(|source, opt| Ok(Box::new(ApeReader::try_new(source, opt)?))),
+        }]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for ApeReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        // 探测器已读过 "MAC " 标记，回到文件头交给 ape_decoder 解析
+        source.seek(SeekFrom::Start(0))?;
+        let info = match ape_decoder::format::parse(&mut source) {
+            Ok(info) => info,
+            Err(_) => return decode_error("ape: invalid header"),
+        };
+        if info.descriptor.version < MIN_VERSION {
+            return unsupported_error("ape: files older than version 3.95");
+        }
+
+        let header = &info.header;
+        if header.sample_rate == 0 || header.total_frames == 0 {
+            return decode_error("ape: empty stream");
+        }
+        let channels = channels_from_count(header.channels as usize)
+            .ok_or(Error::Unsupported("ape: channel layout"))?;
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_APE)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_n_frames(info.total_blocks.max(0) as u64)
+            .with_bits_per_sample(header.bits_per_sample as u32)
+            .with_channels(channels)
+            .with_max_frames_per_packet(header.blocks_per_frame as u64)
+            .with_extra_data(
+                [info.descriptor.version.to_le_bytes(), header.compression_level.to_le_bytes()]
+                    .concat()
+                    .into_boxed_slice(),
+            );
+
+        Ok(Self {
+            reader: source,
+            info,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+            frame: 0,
+        })
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => TimeBase::new(1, self.info.header.sample_rate).calc_timestamp(time),
+        };
+        let blocks_per_frame = self.info.header.blocks_per_frame.max(1) as u64;
+        let frame = ts / blocks_per_frame;
+        if frame >= self.info.header.total_frames as u64 {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        // 每帧可独立解码，直接定位到目标所在帧
+        self.frame = frame as u32;
+        Ok(SeekedTo { track_id: 0, required_ts: ts, actual_ts: frame * blocks_per_frame })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frame = self.frame;
+        if frame >= self.info.header.total_frames {
+            return Err(Error::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        let seek_byte = self.info.seek_byte(frame);
+        let remainder = (seek_byte - self.info.seek_byte(0)) % 4;
+        let frame_bytes = self.info.frame_byte_count(frame);
+        if frame_bytes > MAX_FRAME_BYTES {
+            return decode_error("ape: frame too large");
+        }
+
+        // 位读取器按 4 字节对齐读取，且会多读一个字
+        let mut data = vec![remainder as u8];
+        self.reader.seek(SeekFrom::Start(seek_byte - remainder))?;
+        let want = frame_bytes + remainder + 4;
+        (&mut self.reader).take(want).read_to_end(&mut data)?;
+        if (data.len() as u64 - 1) < frame_bytes + remainder {
+            return decode_error("ape: truncated frame");
+        }
+
+        self.frame += 1;
+        let ts = frame as u64 * self.info.header.blocks_per_frame as u64;
+        let dur = self.info.frame_block_count(frame) as u64;
+        Ok(Packet::new_from_boxed_slice(0, ts, dur, data.into_boxed_slice()))
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// Monkey's Audio 解码器，逐帧解出交错的小端 PCM 后转为浮点
+pub struct ApeDecoder {
+    params: CodecParameters,
+    decoder: FrameDecoder,
+    channels: usize,
+    bits: u32,
+    buf: AudioBuffer<f32>,
+}
+
+impl Decoder for ApeDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let (Some(channels), Some(rate), Some(bits), Some(extra)) =
+            (params.channels, params.sample_rate, params.bits_per_sample, params.extra_data.as_deref())
+        else {
+            return unsupported_error("ape: incomplete codec parameters");
+        };
+        if extra.len() < 4 {
+            return unsupported_error("ape: missing stream version");
+        }
+        let version = u16::from_le_bytes([extra[0], extra[1]]);
+        let compression = u16::from_le_bytes([extra[2], extra[3]]);
+        let decoder = FrameDecoder::new(version, channels.count() as u16, bits as u16, compression)
+            .or_else(|_| unsupported_error("ape: stream parameters"))?;
+
+        let frames = params.max_frames_per_packet.unwrap_or(rate as u64);
+        Ok(Self {
+            params: params.clone(),
+            decoder,
+            channels: channels.count(),
+            bits,
+            buf: AudioBuffer::new(frames, SignalSpec::new(rate, channels)),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[CodecDescriptor {
+            codec: CODEC_TYPE_APE,
+            short_name: "ape",
+            long_name: "Monkey's Audio",
+            inst_func: |params, opt| Ok(Box::new(ApeDecoder::try_new(params, opt)?)),
+        }]
+    }
+
+    fn reset(&mut self) {
+        // 每帧解码前都会重新初始化预测器
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        let Some((&remainder, data)) = packet.buf().split_first() else {
+            return decode_error("ape: empty packet");
+        };
+        let frames = packet.dur as usize;
+        let pcm = match self.decoder.decode_frame(data, remainder as u32, frames) {
+            Ok(pcm) => pcm,
+            Err(_) => return decode_error("ape: corrupt frame"),
+        };
+
+        let bytes = (self.bits / 8) as usize;
+        if pcm.len() < frames * self.channels * bytes {
+            return decode_error("ape: short frame");
+        }
+        if self.buf.capacity() < frames {
+            self.buf = AudioBuffer::new(frames as u64, *self.buf.spec());
+        }
+        self.buf.clear();
+        self.buf.render_reserved(Some(frames));
+
+        let scale = 1.0 / (1u64 << (self.bits - 1)) as f32;
+        for c in 0..self.channels {
+            for (i, s) in self.buf.chan_mut(c).iter_mut().enumerate() {
+                let at = (i * self.channels + c) * bytes;
+                let sample = match bytes {
+                    1 => pcm[at] as i32 - 128, // 8 位为无符号
+                    2 => i16::from_le_bytes([pcm[at], pcm[at + 1]]) as i32,
+                    3 => i32::from_le_bytes([0, pcm[at], pcm[at + 1], pcm[at + 2]]) >> 8,
+                    _ => i32::from_le_bytes([pcm[at], pcm[at + 1], pcm[at + 2], pcm[at + 3]]),
+                };
+                *s = sample as f32 * scale;
+            }
+        }
+
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{decode, signal};
+
+    #[test]
+    fn lossless_decode_is_bit_exact() {
+        let files: [(&'static [u8], usize, u32); 2] = [
+            (include_bytes!("testdata/stereo16.ape"), 2, 16),
+            (include_bytes!("testdata/mono24.ape"), 1, 24),
+        ];
+        for (data, channels, bits) in files {
+            let decoded = decode(data, "ape", None);
+            assert_eq!((decoded.sample_rate, decoded.channels), (44100, channels));
+            assert_eq!(decoded.to_int(bits), signal(1024, channels, bits));
+        }
+    }
+
+    #[test]
+    fn seek_resumes_at_frame_start() {
+        // 夹具每帧 256 块，帧起点不按 4 字节对齐
+        let decoded = decode(include_bytes!("testdata/stereo16.ape"), "ape", Some(600));
+        assert_eq!(decoded.start, 512);
+        assert_eq!(decoded.to_int(16), signal(1024, 2, 16)[512 * 2..]);
+    }
+}
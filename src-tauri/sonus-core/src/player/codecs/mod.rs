@@ -0,0 +1,125 @@
+use std::sync::OnceLock;
+use symphonia::core::{audio::Channels, codecs::CodecRegistry, probe::Probe};
+
+pub(crate) mod ape;
+pub(crate) mod opus;
+pub(crate) mod wavpack;
+
+/// 在 symphonia 默认解码器之外注册 Opus、Monkey's Audio 与 WavPack
+pub fn codecs() -> &'static CodecRegistry {
+    static CODECS: OnceLock<CodecRegistry> = OnceLock::new();
+    CODECS.get_or_init(|| {
+        let mut registry = CodecRegistry::new();
+        symphonia::default::register_enabled_codecs(&mut registry);
+        registry.register_all::<opus::OpusDecoder>();
+        registry.register_all::<ape::ApeDecoder>();
+        registry.register_all::<wavpack::WavPackDecoder>();
+        registry
+    })
+}
+
+/// 在 symphonia 默认容器之外注册 Monkey's Audio 与 WavPack
+pub fn probe() -> &'static Probe {
+    static PROBE: OnceLock<Probe> = OnceLock::new();
+    PROBE.get_or_init(|| {
+        let mut probe = Probe::default();
+        symphonia::default::register_enabled_formats(&mut probe);
+        probe.register_all::<ape::ApeReader>();
+        probe.register_all::<wavpack::WavPackReader>();
+        probe
+    })
+}
+
+/// 按 WAVE 声道顺序取前 count 个声道
+pub(crate) fn channels_from_count(count: usize) -> Option<Channels> {
+    match count {
+        1..=26 => Channels::from_bits(((1u64 << count) - 1) as u32),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Cursor;
+    use symphonia::core::{
+        audio::SampleBuffer,
+        codecs::DecoderOptions,
+        errors::Error,
+        formats::{FormatOptions, SeekMode, SeekTo},
+        io::MediaSourceStream,
+        meta::MetadataOptions,
+        probe::Hint,
+    };
+    use super::*;
+
+    /// testdata 中无损夹具编码前的 PCM：锯齿波叠加伪随机噪声，按声道交错
+    pub(crate) fn signal(frames: usize, channels: usize, bits: u32) -> Vec<i32> {
+        let mut state = 0x1234_5678u32;
+        (0..frames * channels)
+            .map(|n| {
+                let (i, c) = (n / channels, n % channels);
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let noise = (state >> 24) as i32 - 128;
+                let saw = ((i * (c + 1) * 8) % 4096) as i32 - 2048;
+                ((saw * 4) << (bits - 16)) + noise
+            })
+            .collect()
+    }
+
+    /// 解码结果，样本按声道交错
+    pub(crate) struct Decoded {
+        pub sample_rate: u32,
+        pub channels: usize,
+        pub start: u64, // 跳转后实际开始的帧
+        pub samples: Vec<f32>,
+    }
+
+    impl Decoded {
+        /// 还原为 bits 位整数，用于逐位比对
+        pub fn to_int(&self, bits: u32) -> Vec<i32> {
+            let scale = (1u32 << (bits - 1)) as f32;
+            self.samples.iter().map(|&s| (s * scale).round() as i32).collect()
+        }
+    }
+
+    /// 经注册的容器与解码器完整解码，seek 不为 None 时先跳转到该帧
+    pub(crate) fn decode(data: &'static [u8], extension: &str, seek: Option<u64>) -> Decoded {
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension(extension);
+        let mut format = probe()
+            .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+            .unwrap()
+            .format;
+        let params = format.default_track().unwrap().codec_params.clone();
+        let mut decoder = codecs().make(&params, &DecoderOptions::default()).unwrap();
+
+        let start = match seek {
+            Some(ts) => {
+                let seeked = format.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id: 0 }).unwrap();
+                decoder.reset();
+                seeked.actual_ts
+            }
+            None => 0,
+        };
+
+        let mut samples = Vec::new();
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => panic!("{}", e),
+            };
+            let decoded = decoder.decode(&packet).unwrap();
+            let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+            buf.copy_interleaved_ref(decoded);
+            samples.extend_from_slice(buf.samples());
+        }
+        Decoded {
+            sample_rate: params.sample_rate.unwrap(),
+            channels: params.channels.unwrap().count(),
+            start,
+            samples,
+        }
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    f64::consts::PI,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use anyhow::{anyhow, Result};
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use crate::database::{connection, get_config_value, set_config_value};
+
+/// 标准 10 段均衡器的中心频率（Hz）
+pub const DEFAULT_FREQUENCIES: [f32; 10] = [31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0];
+const DEFAULT_Q: f32 = 1.41;
+const MAX_GAIN_DB: f32 = 24.0;
+
+/// 内置预设：名称与 10 段增益（dB）
+const BUILTIN_PRESETS: [(&str, [f32; 10]); 9] = [
+    ("Flat", [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("Bass Boost", [6.0, 5.0, 4.0, 2.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("Treble Boost", [0.0, 0.0, 0.0, 0.0, 0.0, 0.5, 2.0, 4.0, 5.0, 6.0]),
+    ("Vocal", [-2.0, -1.0, 0.0, 1.5, 3.0, 3.0, 2.0, 1.0, 0.0, -1.0]),
+    ("Rock", [4.5, 3.5, 2.0, -0.5, -1.5, -0.5, 1.5, 3.0, 4.0, 4.5]),
+    ("Pop", [-1.0, 0.5, 2.0, 3.0, 3.0, 1.5, 0.0, -0.5, -1.0, -1.0]),
+    ("Jazz", [3.0, 2.0, 1.0, 1.5, -1.0, -1.0, 0.0, 1.0, 2.0, 3.0]),
+    ("Classical", [4.0, 3.0, 2.0, 1.0, -1.0, -1.0, 0.0, 2.0, 3.0, 4.0]),
+    ("Electronic", [5.0, 4.0, 1.5, 0.0, -1.5, 1.5, 0.5, 1.5, 4.0, 5.0]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum FilterKind {
+    #[default]
+    Peaking,
+    LowShelf,
+    HighShelf,
+}
+
+/// 单个均衡频段
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EqBand {
+    pub frequency: f32,
+    pub gain: f32, // dB
+    #[serde(default = "default_q")]
+    pub q: f32,
+    #[serde(default)]
+    pub kind: FilterKind,
+}
+
+fn default_q() -> f32 {
+    DEFAULT_Q
+}
+
+impl EqBand {
+    fn peaking(frequency: f32, gain: f32) -> Self {
+        Self { frequency, gain, q: DEFAULT_Q, kind: FilterKind::Peaking }
+    }
+}
+
+/// 均衡器预设（内置或用户保存）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EqPreset {
+    pub name: String,
+    pub preamp: f32,
+    pub bands: Vec<EqBand>,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+/// 均衡器当前设置，对应 audio_enhancement / eq_* 配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EqSettings {
+    pub enabled: bool,
+    pub preamp: f32, // dB
+    pub bands: Vec<EqBand>,
+    pub preset: Option<String>,
+}
+
+impl Default for EqSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preamp: 0.0,
+            bands: DEFAULT_FREQUENCIES.iter().map(|&f| EqBand::peaking(f, 0.0)).collect(),
+            preset: Some("Flat".to_string()),
+        }
+    }
+}
+
+impl EqSettings {
+    fn load() -> Self {
+        let conn = connection();
+        let read = |key: &str| get_config_value(&conn, key).ok().map(|c| c.value);
+        let mut settings = Self::default();
+
+        if let Some(value) = read("audio_enhancement") {
+            settings.enabled = value.trim() == "1";
+        }
+        if let Some(preamp) = read("eq_preamp").and_then(|v| v.trim().parse::<f32>().ok()) {
+            settings.preamp = preamp.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+        }
+        if let Some(bands) = read("eq_bands").and_then(|v| serde_json::from_str::<Vec<EqBand>>(&v).ok()) {
+            if !bands.is_empty() {
+                settings.bands = bands;
+            }
+        }
+        settings.preset = read("eq_preset").filter(|v| !v.is_empty());
+        settings
+    }
+
+    fn save(&self) {
+        let conn = connection();
+        _ = set_config_value(&conn, "audio_enhancement", if self.enabled { "1" } else { "0" });
+        _ = set_config_value(&conn, "eq_preamp", &self.preamp.to_string());
+        if let Ok(bands) = serde_json::to_string(&self.bands) {
+            _ = set_config_value(&conn, "eq_bands", &bands);
+        }
+        _ = set_config_value(&conn, "eq_preset", self.preset.as_deref().unwrap_or(""));
+    }
+}
+
+/// 播放链共享的均衡器参数；修改后递增版本号，音频线程检测到后重算系数，无需重建 Source。
+pub struct Equalizer {
+    settings: Mutex<EqSettings>,
+    version: AtomicU64,
+}
+
+impl Equalizer {
+    pub fn load() -> Self {
+        Self {
+            settings: Mutex::new(EqSettings::load()),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    pub fn settings(&self) -> EqSettings {
+        self.settings.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update<F: FnOnce(&mut EqSettings)>(&self, f: F) {
+        // 写数据库在释放锁之后进行，避免音频线程长时间拿不到参数
+        let settings = {
+            let mut settings = self.settings.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut settings);
+            settings.clone()
+        };
+        self.version.fetch_add(1, Ordering::Release);
+        settings.save();
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.update(|s| s.enabled = enabled);
+    }
+
+    pub fn set_preamp(&self, preamp: f32) {
+        self.update(|s| s.preamp = preamp.clamp(-MAX_GAIN_DB, MAX_GAIN_DB));
+    }
+
+    pub fn set_band_gain(&self, index: usize, gain: f32) -> Result<()> {
+        let mut result = Ok(());
+        self.update(|s| match s.bands.get_mut(index) {
+            Some(band) => {
+                band.gain = gain.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+                s.preset = None;
+            }
+            None => result = Err(anyhow!("Band index {} out of range", index)),
+        });
+        result
+    }
+
+    /// 替换整组频段（自定义频段数量与频率）
+    pub fn set_bands(&self, bands: Vec<EqBand>) -> Result<()> {
+        if bands.is_empty() {
+            return Err(anyhow!("Equalizer needs at least one band"));
+        }
+        if let Some(band) = bands.iter().find(|b| !is_positive(b.frequency) || !is_positive(b.q)) {
+            return Err(anyhow!("Invalid band: {:?}", band));
+        }
+        self.update(|s| {
+            s.bands = bands
+                .into_iter()
+                .map(|b| EqBand { gain: b.gain.clamp(-MAX_GAIN_DB, MAX_GAIN_DB), ..b })
+                .collect();
+            s.preset = None;
+        });
+        Ok(())
+    }
+
+    pub fn presets(&self) -> Vec<EqPreset> {
+        let mut presets = builtin_presets();
+        presets.extend(load_user_presets());
+        presets
+    }
+
+    pub fn apply_preset(&self, name: &str) -> Result<()> {
+        let preset = self
+            .presets()
+            .into_iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| anyhow!("Equalizer preset not found: {}", name))?;
+        self.update(|s| {
+            s.preamp = preset.preamp;
+            s.bands = preset.bands;
+            s.preset = Some(preset.name);
+        });
+        Ok(())
+    }
+
+    /// 把当前设置保存为用户预设，同名覆盖；不能覆盖内置预设
+    pub fn save_preset(&self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("Preset name is empty"));
+        }
+        if BUILTIN_PRESETS.iter().any(|(n, _)| *n == name) {
+            return Err(anyhow!("Cannot overwrite built-in preset: {}", name));
+        }
+
+        let settings = self.settings();
+        let mut presets = load_user_presets();
+        presets.retain(|p| p.name != name);
+        presets.push(EqPreset {
+            name: name.to_string(),
+            preamp: settings.preamp,
+            bands: settings.bands,
+            builtin: false,
+        });
+        save_user_presets(&presets)?;
+        self.update(|s| s.preset = Some(name.to_string()));
+        Ok(())
+    }
+
+    pub fn delete_preset(&self, name: &str) -> Result<()> {
+        let mut presets = load_user_presets();
+        let before = presets.len();
+        presets.retain(|p| p.name != name);
+        if presets.len() == before {
+            return Err(anyhow!("User preset not found: {}", name));
+        }
+        save_user_presets(&presets)?;
+        if self.settings().preset.as_deref() == Some(name) {
+            self.update(|s| s.preset = None);
+        }
+        Ok(())
+    }
+}
+
+fn is_positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+fn builtin_presets() -> Vec<EqPreset> {
+    BUILTIN_PRESETS
+        .iter()
+        .map(|(name, gains)| EqPreset {
+            name: name.to_string(),
+            preamp: -gains.iter().cloned().fold(0.0f32, f32::max),
+            bands: DEFAULT_FREQUENCIES.iter().zip(gains).map(|(&f, &g)| EqBand::peaking(f, g)).collect(),
+            builtin: true,
+        })
+        .collect()
+}
+
+fn load_user_presets() -> Vec<EqPreset> {
+    get_config_value(&connection(), "eq_user_presets")
+        .ok()
+        .and_then(|c| serde_json::from_str::<Vec<EqPreset>>(&c.value).ok())
+        .unwrap_or_default()
+}
+
+fn save_user_presets(presets: &[EqPreset]) -> Result<()> {
+    let value = serde_json::to_string(presets)?;
+    set_config_value(&connection(), "eq_user_presets", &value)?;
+    Ok(())
+}
+
+/// RBJ biquad 系数（已按 a0 归一化）
+#[derive(Clone, Copy)]
+struct Coefficients {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Coefficients {
+    fn new(band: &EqBand, sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+        let f0 = (band.frequency as f64).min(fs * 0.45);
+        let a = 10f64.powf(band.gain as f64 / 40.0);
+        let w0 = 2.0 * PI * f0 / fs;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * band.q as f64);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match band.kind {
+            FilterKind::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            FilterKind::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha,
+            ),
+            FilterKind::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha,
+            ),
+        };
+
+        Self { b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: a1 / a0, a2: a2 / a0 }
+    }
+}
+
+/// 单声道单频段的滤波状态（转置直接 II 型）
+#[derive(Clone, Copy, Default)]
+struct BiquadState {
+    z1: f64,
+    z2: f64,
+}
+
+impl BiquadState {
+    #[inline]
+    fn process(&mut self, c: &Coefficients, x: f64) -> f64 {
+        let y = c.b0 * x + self.z1;
+        self.z1 = c.b1 * x - c.a1 * y + self.z2;
+        self.z2 = c.b2 * x - c.a2 * y;
+        y
+    }
+}
+
+/// 在 Source 上应用均衡器，参数变化时就地重算系数
+pub struct EqSource<S> {
+    inner: S,
+    eq: Arc<Equalizer>,
+    version: u64,
+    sample_rate: u32,
+    enabled: bool,
+    preamp: f32,
+    coefficients: Vec<Coefficients>,
+    states: Vec<BiquadState>, // channels * bands
+    channels: usize,
+    channel: usize,
+}
+
+impl<S: Source> EqSource<S> {
+    pub fn new(inner: S, eq: Arc<Equalizer>) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        let mut source = Self {
+            inner,
+            eq,
+            version: u64::MAX,
+            sample_rate: 0,
+            enabled: false,
+            preamp: 1.0,
+            coefficients: Vec::new(),
+            states: Vec::new(),
+            channels,
+            channel: 0,
+        };
+        source.refresh();
+        source
+    }
+
+    fn refresh(&mut self) {
+        // 声道数变化与参数无关，不依赖能否拿到锁
+        let channels = self.inner.channels().max(1) as usize;
+        if channels != self.channels {
+            self.channels = channels;
+            self.states = vec![BiquadState::default(); channels * self.coefficients.len()];
+            self.channel = 0;
+        }
+
+        let version = self.eq.version.load(Ordering::Acquire);
+        let sample_rate = self.inner.sample_rate();
+        if version == self.version && sample_rate == self.sample_rate {
+            return;
+        }
+        // 控制线程正在修改参数时沿用旧系数，下一个样本再试
+        let Ok(settings) = self.eq.settings.try_lock() else { return; };
+
+        self.enabled = settings.enabled;
+        self.preamp = 10f32.powf(settings.preamp / 20.0);
+        self.coefficients = settings
+            .bands
+            .iter()
+            .map(|b| Coefficients::new(b, sample_rate))
+            .collect();
+        // 频段数量不变时保留滤波状态，避免调节增益时爆音
+        let len = channels * self.coefficients.len();
+        if self.states.len() != len {
+            self.states = vec![BiquadState::default(); len];
+            self.channel = 0;
+        }
+        self.version = version;
+        self.sample_rate = sample_rate;
+    }
+}
+
+impl<S: Source> Iterator for EqSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.inner.next()?;
+        if self.channel == 0 {
+            self.refresh();
+        }
+
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % self.channels;
+        if !self.enabled {
+            return Some(sample);
+        }
+
+        let bands = self.coefficients.len();
+        let states = &mut self.states[channel * bands..(channel + 1) * bands];
+        let mut y = (sample * self.preamp) as f64;
+        for (state, c) in states.iter_mut().zip(&self.coefficients) {
+            y = state.process(c, y);
+        }
+        Some(y as f32)
+    }
+}
+
+impl<S: Source> Source for EqSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn source_created_while_settings_locked() {
+        let eq = Arc::new(Equalizer {
+            settings: Mutex::new(EqSettings { enabled: true, ..EqSettings::default() }),
+            version: AtomicU64::new(0),
+        });
+        let guard = eq.settings.lock().unwrap();
+        let mut source = EqSource::new(SamplesBuffer::new(2, 44100, vec![0.5; 8]), eq.clone());
+        // 拿不到参数时原样输出
+        assert_eq!(source.next(), Some(0.5));
+        drop(guard);
+        assert_eq!(source.by_ref().count(), 7);
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use rodio::Source;
+
+type FadeAction = Box<dyn FnOnce(&Fader) + Send>;
+
+/// 播放/暂停/停止/跳转时的音量包络，由音频线程逐样本推进到目标增益。
+/// 需要等淡出走完的操作排队到 actions，由音频线程在静音时执行，控制线程不必等待。
+pub struct Fader {
+    gain: AtomicU32,    // 当前增益（f32 位模式）
+    target: AtomicU32,  // 目标增益（f32 位模式）
+    ramp_ms: AtomicU64, // 从 0 到 1 所需的时长
+    paused: AtomicBool, // 淡出到静音后不再从上游取样
+    pending: AtomicBool, // actions 非空，音频线程据此避免逐帧加锁
+    actions: Mutex<Vec<FadeAction>>,
+}
+
+impl Default for Fader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fader {
+    pub fn new() -> Self {
+        Self {
+            gain: AtomicU32::new(1.0f32.to_bits()),
+            target: AtomicU32::new(1.0f32.to_bits()),
+            ramp_ms: AtomicU64::new(0),
+            paused: AtomicBool::new(false),
+            pending: AtomicBool::new(false),
+            actions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 立即跳到指定增益
+    pub fn set(&self, gain: f32) {
+        self.gain.store(gain.to_bits(), Ordering::Relaxed);
+        self.target.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 在 duration 内线性过渡到目标增益
+    pub fn fade_to(&self, target: f32, duration: Duration) {
+        self.ramp_ms.store(duration.as_millis() as u64, Ordering::Relaxed);
+        self.target.store(target.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 淡出后暂停：静音后 FadeSource 输出静音帧，上游停在包络走完的位置
+    pub fn pause(&self, duration: Duration) {
+        self.paused.store(true, Ordering::Relaxed);
+        self.fade_to(0.0, duration);
+    }
+
+    /// 取消暂停并从当前增益淡入；尚未执行的排队操作先在调用线程上执行
+    pub fn resume(&self, duration: Duration) {
+        self.paused.store(false, Ordering::Relaxed);
+        self.run_pending();
+        self.fade_to(1.0, duration);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// 淡出到静音时在音频线程上执行 action，已静音时在下一帧执行
+    pub fn then(&self, action: impl FnOnce(&Fader) + Send + 'static) {
+        let mut actions = self.actions.lock().unwrap_or_else(|e| e.into_inner());
+        actions.push(Box::new(action));
+        self.pending.store(true, Ordering::Relaxed);
+    }
+
+    fn is_silent(&self) -> bool {
+        self.gain.load(Ordering::Relaxed) == 0.0f32.to_bits() && self.target.load(Ordering::Relaxed) == 0.0f32.to_bits()
+    }
+
+    fn run_pending(&self) {
+        if !self.pending.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let actions = std::mem::take(&mut *self.actions.lock().unwrap_or_else(|e| e.into_inner()));
+        for action in actions {
+            action(self);
+        }
+    }
+
+    fn step(&self, samples_per_sec: u32) -> f32 {
+        let gain = f32::from_bits(self.gain.load(Ordering::Relaxed));
+        let target = f32::from_bits(self.target.load(Ordering::Relaxed));
+        if gain == target {
+            return gain;
+        }
+
+        let ramp_ms = self.ramp_ms.load(Ordering::Relaxed);
+        let next = if ramp_ms == 0 || samples_per_sec == 0 {
+            target
+        } else {
+            let inc = 1000.0 / (ramp_ms as f32 * samples_per_sec as f32);
+            if gain < target { (gain + inc).min(target) } else { (gain - inc).max(target) }
+        };
+        self.gain.store(next.to_bits(), Ordering::Relaxed);
+        next
+    }
+}
+
+/// 把 Fader 的增益应用到任意 Source 上，并在帧边界上执行排队操作与暂停
+pub struct FadeSource<S> {
+    inner: S,
+    fader: Arc<Fader>,
+    channel: u16, // 当前帧内的声道序号
+    held: bool,   // 当前帧是暂停时补的静音
+}
+
+impl<S: Source> FadeSource<S> {
+    pub fn new(inner: S, fader: Arc<Fader>) -> Self {
+        Self { inner, fader, channel: 0, held: false }
+    }
+}
+
+impl<S: Source> Iterator for FadeSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let channels = self.inner.channels().max(1);
+        if self.channel == 0 {
+            self.held = self.fader.is_silent() && {
+                self.fader.run_pending();
+                self.fader.is_silent() && self.fader.is_paused()
+            };
+        }
+        let sample = if self.held {
+            0.0
+        } else {
+            let sample = self.inner.next()?;
+            sample * self.fader.step(self.inner.sample_rate() * channels as u32)
+        };
+        self.channel = (self.channel + 1) % channels;
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for FadeSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
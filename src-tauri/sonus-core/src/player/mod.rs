@@ -0,0 +1,15 @@
+pub mod ab_loop;
+pub mod audio_backend;
+pub mod codecs;
+pub mod equalizer;
+pub mod fade;
+pub mod output;
+pub mod output_device;
+pub mod remote;
+pub mod replay_gain;
+pub mod resume;
+pub mod sleep_timer;
+pub mod spectrum;
+pub mod state;
+pub mod stereo;
+pub mod time_stretch;
\ No newline at end of file
@@ -0,0 +1,249 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use anyhow::Result;
+use rodio::{
+    mixer::{self, Mixer, MixerSource},
+    OutputStream,
+};
+
+/// 每次从混音器取出的帧数
+const BLOCK_FRAMES: usize = 1024;
+
+/// 音频输出：AudioBackend 把 Sink 连接到它的混音器上，由输出决定样本的去向与消耗速度。
+/// 声卡之外的实现用于无声卡环境下的自动化测试。
+pub trait AudioOutput: Send {
+    fn mixer(&self) -> &Mixer;
+}
+
+/// rodio 打开的声卡输出流
+impl AudioOutput for OutputStream {
+    fn mixer(&self) -> &Mixer {
+        OutputStream::mixer(self)
+    }
+}
+
+/// 空输出：按实时速度消耗并丢弃样本，播放进度与真实声卡一致
+pub struct NullOutput {
+    mixer: Mixer,
+    renderer: Renderer,
+}
+
+impl NullOutput {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let (mixer, source) = mixer::mixer(channels, sample_rate);
+        let renderer = Renderer::spawn(source, true, |_| {});
+        Self { mixer, renderer }
+    }
+}
+
+impl AudioOutput for NullOutput {
+    fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+}
+
+impl Drop for NullOutput {
+    fn drop(&mut self) {
+        self.renderer.stop();
+    }
+}
+
+/// WAV 文件输出：以 32 位浮点写入混音结果，有声音时不限速渲染，
+/// 全静音的块（空闲、暂停）按实时速度写入，避免空转时写满磁盘。
+/// drop 时补全文件头。
+pub struct WavFileOutput {
+    mixer: Mixer,
+    renderer: Renderer,
+}
+
+impl WavFileOutput {
+    pub fn create<P: AsRef<Path>>(path: P, channels: u16, sample_rate: u32) -> Result<Self> {
+        let mut writer = WavWriter::create(path.as_ref(), channels, sample_rate)?;
+        let (mixer, source) = mixer::mixer(channels, sample_rate);
+        let renderer = Renderer::spawn(source, false, move |block| {
+            let result = match block {
+                Some(samples) => writer.write(samples),
+                None => writer.finish(),
+            };
+            if let Err(e) = result {
+                tracing::warn!("write wav output failed: {}", e);
+            }
+        });
+        Ok(Self { mixer, renderer })
+    }
+}
+
+impl AudioOutput for WavFileOutput {
+    fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+}
+
+impl Drop for WavFileOutput {
+    fn drop(&mut self) {
+        self.renderer.stop();
+    }
+}
+
+/* ====================== 渲染线程 ======================== */
+
+/// 在独立线程上从混音器取样本交给 write；线程退出前以 None 调用一次 write
+struct Renderer {
+    quit: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Renderer {
+    fn spawn<F>(mut source: MixerSource, real_time: bool, mut write: F) -> Self
+    where
+        F: FnMut(Option<&[f32]>) + Send + 'static,
+    {
+        let quit = Arc::new(AtomicBool::new(false));
+        let quit2 = quit.clone();
+        let handle = std::thread::spawn(move || {
+            let rate = rodio::Source::sample_rate(&source);
+            let block_len = BLOCK_FRAMES * rodio::Source::channels(&source) as usize;
+            let block_time = Duration::from_secs_f64(BLOCK_FRAMES as f64 / rate as f64);
+            let mut block = Vec::with_capacity(block_len);
+            let mut deadline = Instant::now();
+
+            while !quit2.load(Ordering::Relaxed) {
+                block.clear();
+                block.extend(source.by_ref().take(block_len));
+                let silent = block.iter().all(|&s| s == 0.0);
+                if !block.is_empty() {
+                    write(Some(&block));
+                }
+
+                if real_time || silent {
+                    deadline += block_time;
+                    std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                } else {
+                    deadline = Instant::now();
+                }
+            }
+            write(None);
+        });
+        Self { quit, handle: Some(handle) }
+    }
+
+    fn stop(&mut self) {
+        self.quit.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/* ====================== WAV 写入 ======================== */
+
+/// 32 位浮点 WAV；数据长度在 finish 时回填
+pub(crate) struct WavWriter {
+    file: BufWriter<File>,
+    data_len: u32,
+}
+
+impl WavWriter {
+    pub(crate) fn create(path: &Path, channels: u16, sample_rate: u32) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let block_align = channels * 4;
+        file.write_all(b"RIFF")?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(b"WAVEfmt ")?;
+        file.write_all(&16u32.to_le_bytes())?;
+        file.write_all(&3u16.to_le_bytes())?; // WAVE_FORMAT_IEEE_FLOAT
+        file.write_all(&channels.to_le_bytes())?;
+        file.write_all(&sample_rate.to_le_bytes())?;
+        file.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        file.write_all(&block_align.to_le_bytes())?;
+        file.write_all(&32u16.to_le_bytes())?;
+        file.write_all(b"data")?;
+        file.write_all(&0u32.to_le_bytes())?;
+        Ok(Self { file, data_len: 0 })
+    }
+
+    pub(crate) fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len = self.data_len.saturating_add(samples.len() as u32 * 4);
+        Ok(())
+    }
+
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&(36 + self.data_len).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(40))?;
+        self.file.write_all(&self.data_len.to_le_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rodio::{buffer::SamplesBuffer, Sink};
+
+    /// 读回 WavWriter 写出的文件：(声道数, 采样率, 交错样本)
+    pub(crate) fn read_wav(path: &Path) -> (u16, u32, Vec<f32>) {
+        let bytes = std::fs::read(path).unwrap();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        let channels = u16::from_le_bytes([bytes[22], bytes[23]]);
+        let sample_rate = u32::from_le_bytes(bytes[24..28].try_into().unwrap());
+        let data_len = u32::from_le_bytes(bytes[40..44].try_into().unwrap()) as usize;
+        let samples = bytes[44..44 + data_len]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        (channels, sample_rate, samples)
+    }
+
+    /// 写一个恒定电平的立体声 WAV，便于按样本值分辨曲目
+    pub(crate) fn write_constant_wav(path: &Path, level: f32, duration: Duration) {
+        let frames = (duration.as_secs_f64() * 44100.0) as usize;
+        let mut writer = WavWriter::create(path, 2, 44100).unwrap();
+        writer.write(&vec![level; frames * 2]).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn wav_output_renders_faster_than_real_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        let samples = vec![0.5f32; 44100 * 2 * 5];
+
+        let output = WavFileOutput::create(&path, 2, 44100).unwrap();
+        let started = Instant::now();
+        output.mixer().add(SamplesBuffer::new(2, 44100, samples.clone()));
+        std::thread::sleep(Duration::from_millis(500));
+        drop(output);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let (channels, sample_rate, written) = read_wav(&path);
+        assert_eq!((channels, sample_rate), (2, 44100));
+        assert_eq!(written, samples);
+    }
+
+    #[test]
+    fn null_output_consumes_in_real_time() {
+        let output = NullOutput::new(2, 44100);
+        let sink = Sink::connect_new(output.mixer());
+        sink.append(SamplesBuffer::new(2, 44100, vec![0.5f32; 44100 * 2 * 5]));
+
+        std::thread::sleep(Duration::from_millis(500));
+        let position = sink.get_pos();
+        assert!(position >= Duration::from_millis(300), "{position:?}");
+        assert!(position <= Duration::from_millis(900), "{position:?}");
+        assert!(!sink.empty());
+    }
+}
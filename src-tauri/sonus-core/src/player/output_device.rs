@@ -0,0 +1,75 @@
+use anyhow::Result;
+use rodio::cpal::{self, traits::HostTrait, StreamError};
+use rodio::{DeviceTrait, OutputStream, OutputStreamBuilder};
+use serde::Serialize;
+use crate::events::{EventBus, PlayerEvent};
+
+/// 输出设备信息（以设备名作为标识持久化到 output_device 配置）
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputDeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// OutputDeviceChanged 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputDeviceChanged {
+    pub name: Option<String>, // None 表示系统默认设备
+    pub fallback: bool,       // 是否因设备丢失而回退到默认设备
+}
+
+pub fn list_output_devices() -> Vec<OutputDeviceInfo> {
+    let host = cpal::default_host();
+    let default_name = host.default_output_device().and_then(|d| d.name().ok());
+    let Ok(devices) = host.output_devices() else {
+        return Vec::new();
+    };
+
+    devices
+        .filter_map(|d| d.name().ok())
+        .map(|name| OutputDeviceInfo {
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            name,
+        })
+        .collect()
+}
+
+/// 打开指定名称的输出设备；name 为 None 或设备不存在时使用默认设备。
+/// 返回的流在设备被拔出时会发布 OutputDeviceLost。
+pub fn open_output_stream(name: Option<&str>, events: &EventBus) -> Result<(OutputStream, Option<String>)> {
+    let events = events.clone();
+    let on_error = move |err: StreamError| {
+        tracing::warn!("audio output stream error: {}", err);
+        if matches!(err, StreamError::DeviceNotAvailable) {
+            events.publish(PlayerEvent::OutputDeviceLost);
+        }
+    };
+
+    if let Some(name) = name {
+        match find_output_device(name) {
+            Some(device) => {
+                let stream = OutputStreamBuilder::from_device(device)
+                    .map_err(|e| anyhow::anyhow!("output device {name} unavailable: {e}"))?
+                    .with_error_callback(on_error)
+                    .open_stream_or_fallback()
+                    .map_err(|e| anyhow::anyhow!("open output device {name} failed: {e}"))?;
+                return Ok((stream, Some(name.to_string())));
+            }
+            None => tracing::warn!("output device {:?} not found, using default", name),
+        }
+    }
+
+    let stream = OutputStreamBuilder::from_default_device()
+        .map_err(|e| anyhow::anyhow!("no default output device: {e}"))?
+        .with_error_callback(on_error)
+        .open_stream_or_fallback()
+        .map_err(|e| anyhow::anyhow!("open default output device failed: {e}"))?;
+    Ok((stream, None))
+}
+
+fn find_output_device(name: &str) -> Option<cpal::Device> {
+    cpal::default_host()
+        .output_devices()
+        .ok()?
+        .find(|d| d.name().is_ok_and(|n| n == name))
+}
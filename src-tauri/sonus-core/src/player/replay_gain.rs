@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use crate::library::index::Track;
+
+/// 响度均衡模式，对应 loudness_balance 配置：0 关闭，1 曲目增益，2 专辑增益
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplayGainMode {
+    Off,
+    Track,
+    Album,
+}
+
+impl ReplayGainMode {
+    pub fn from_config(value: &str) -> Self {
+        match value.trim() {
+            "1" => ReplayGainMode::Track,
+            "2" => ReplayGainMode::Album,
+            _ => ReplayGainMode::Off,
+        }
+    }
+
+    pub fn config_value(self) -> &'static str {
+        match self {
+            ReplayGainMode::Off => "0",
+            ReplayGainMode::Track => "1",
+            ReplayGainMode::Album => "2",
+        }
+    }
+
+    pub(crate) fn as_u8(self) -> u8 {
+        self.config_value().as_bytes()[0] - b'0'
+    }
+
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ReplayGainMode::Track,
+            2 => ReplayGainMode::Album,
+            _ => ReplayGainMode::Off,
+        }
+    }
+}
+
+/// 单首曲目的 ReplayGain 标签（增益单位 dB，峰值为线性满刻度比例）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl From<&Track> for ReplayGain {
+    fn from(track: &Track) -> Self {
+        Self {
+            track_gain: track.replaygain_track_gain,
+            track_peak: track.replaygain_track_peak,
+            album_gain: track.replaygain_album_gain,
+            album_peak: track.replaygain_album_peak,
+        }
+    }
+}
+
+impl ReplayGain {
+    /// 按模式计算线性增益；缺少对应标签时退回另一组，峰值存在时限制增益避免削波
+    pub fn factor(&self, mode: ReplayGainMode) -> f32 {
+        let (gain, peak) = match mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (
+                self.track_gain.or(self.album_gain),
+                self.track_peak.or(self.album_peak),
+            ),
+            ReplayGainMode::Album => (
+                self.album_gain.or(self.track_gain),
+                self.album_peak.or(self.track_peak),
+            ),
+        };
+
+        let Some(gain) = gain else { return 1.0; };
+        let factor = 10f32.powf(gain / 20.0);
+        match peak {
+            Some(peak) if peak > 0.0 && factor * peak > 1.0 => 1.0 / peak,
+            _ => factor,
+        }
+    }
+}
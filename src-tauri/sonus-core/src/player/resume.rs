@@ -0,0 +1,143 @@
+use std::path::Path;
+use std::time::Duration;
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use crate::database::{connection, execute_with_params, get_config_value, query_with_params, set_config_value};
+use crate::library::index::Track;
+
+/// 距离结尾不足这段时间时视为已听完，清除记忆的位置
+const FINISHED_MARGIN: Duration = Duration::from_secs(30);
+
+/// 哪些曲目记忆播放位置，对应 resume_min_duration / resume_genres / resume_folders 配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResumeRules {
+    pub min_duration: u32,    // 分钟，时长不短于此的曲目记忆位置，0 表示不按时长判断
+    pub genres: Vec<String>,  // 流派（不区分大小写），如 Audiobook、Podcast
+    pub folders: Vec<String>, // 位于这些文件夹下的曲目
+}
+
+impl Default for ResumeRules {
+    fn default() -> Self {
+        Self {
+            min_duration: 20,
+            genres: vec!["Audiobook".to_string(), "Podcast".to_string()],
+            folders: Vec::new(),
+        }
+    }
+}
+
+impl ResumeRules {
+    pub fn load() -> Self {
+        let conn = connection();
+        let read = |key: &str| get_config_value(&conn, key).ok().map(|c| c.value);
+        let defaults = Self::default();
+        Self {
+            min_duration: read("resume_min_duration")
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(defaults.min_duration),
+            genres: read("resume_genres")
+                .and_then(|v| serde_json::from_str(&v).ok())
+                .unwrap_or(defaults.genres),
+            folders: read("resume_folders")
+                .and_then(|v| serde_json::from_str(&v).ok())
+                .unwrap_or(defaults.folders),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let conn = connection();
+        set_config_value(&conn, "resume_min_duration", &self.min_duration.to_string())?;
+        set_config_value(&conn, "resume_genres", &serde_json::to_string(&self.genres)?)?;
+        set_config_value(&conn, "resume_folders", &serde_json::to_string(&self.folders)?)?;
+        Ok(())
+    }
+
+    /// 曲目是否按规则记忆播放位置；不在曲库中的曲目没有 id，不记忆
+    pub fn applies_to(&self, track: &Track) -> bool {
+        if track.id.is_none() {
+            return false;
+        }
+        if self.min_duration > 0 && track.duration >= self.min_duration * 60 {
+            return true;
+        }
+        // M4B 是有声书专用的容器
+        if Path::new(&track.file_path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("m4b")) {
+            return true;
+        }
+        let genre_matches = track.genre.iter().flatten()
+            .any(|genre| self.genres.iter().any(|g| g.eq_ignore_ascii_case(genre)));
+        genre_matches || self.folders.iter().any(|folder| Path::new(&track.file_path).starts_with(folder))
+    }
+}
+
+/// 记忆的播放位置，列表中附带曲目信息以便展示
+#[derive(Debug, Clone, Serialize)]
+pub struct ResumePosition {
+    pub track_id: usize,
+    pub title: Option<String>,
+    pub file_path: String,
+    pub position_ms: u64,
+    pub duration: u32, // 曲目时长（秒）
+    pub update_time: String,
+}
+
+impl ResumePosition {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            track_id: row.get(0)?,
+            title: row.get(1)?,
+            file_path: row.get(2)?,
+            position_ms: row.get(3)?,
+            duration: row.get(4)?,
+            update_time: row.get(5)?,
+        })
+    }
+}
+
+/// 保存曲目的播放位置；位置为 0 或已接近结尾时清除记录
+pub fn save_position(track_id: usize, position: Duration, total: Option<Duration>) -> rusqlite::Result<()> {
+    let finished = total.is_some_and(|total| position + FINISHED_MARGIN.min(total / 20) >= total);
+    if position.is_zero() || finished {
+        return clear_position(track_id);
+    }
+    let position_ms = position.as_millis() as u64;
+    execute_with_params(
+        &connection(),
+        "INSERT INTO resume_position (music_id, position_ms, update_time) VALUES (?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT(music_id) DO UPDATE SET position_ms = excluded.position_ms, update_time = excluded.update_time",
+        &[&track_id, &position_ms],
+    )?;
+    Ok(())
+}
+
+pub fn get_position(track_id: usize) -> rusqlite::Result<Option<Duration>> {
+    let found = query_with_params(
+        &connection(),
+        "SELECT position_ms FROM resume_position WHERE music_id = ?",
+        &[&track_id],
+        |row| row.get::<_, u64>(0)
+    )?;
+    Ok(found.into_iter().next().map(Duration::from_millis))
+}
+
+/// 所有记忆的位置，最近收听的在前；曲目已从曲库删除的记录不返回
+pub fn get_positions() -> rusqlite::Result<Vec<ResumePosition>> {
+    query_with_params(
+        &connection(),
+        "SELECT r.music_id, m.title, m.file_path, r.position_ms, m.duration, r.update_time
+         FROM resume_position r JOIN music m ON m.id = r.music_id
+         ORDER BY r.update_time DESC, r.music_id",
+        &[],
+        ResumePosition::from_row
+    )
+}
+
+pub fn clear_position(track_id: usize) -> rusqlite::Result<()> {
+    execute_with_params(&connection(), "DELETE FROM resume_position WHERE music_id = ?", &[&track_id])?;
+    Ok(())
+}
+
+pub fn clear_positions() -> rusqlite::Result<()> {
+    execute_with_params(&connection(), "DELETE FROM resume_position", &[])?;
+    Ok(())
+}
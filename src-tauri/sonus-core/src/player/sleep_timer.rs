@@ -0,0 +1,133 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use crate::events::{EventBus, PlayerEvent};
+use crate::player::fade::Fader;
+
+/// 睡眠定时结束前开始淡出的时长
+pub const SLEEP_FADE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SleepTimerMode {
+    Minutes { minutes: u32 }, // N 分钟后停止
+    EndOfTrack,               // 当前曲目播完后停止
+    EndOfQueue,               // 播放列表最后一首播完后停止
+}
+
+/// 睡眠定时状态，随 SleepTimerUpdated 事件发布
+#[derive(Debug, Clone, Serialize)]
+pub struct SleepTimerStatus {
+    pub mode: Option<SleepTimerMode>, // None 表示未设置
+    pub remaining_ms: Option<u64>,    // 无法估计时为 None
+}
+
+/// 睡眠定时器：由控制器每秒驱动一次，最后 30 秒通过独立的增益级淡出，不改动用户音量。
+pub struct SleepTimer {
+    mode: Option<SleepTimerMode>,
+    deadline: Option<Instant>, // 仅 Minutes 模式
+    fading: bool,
+    fader: Arc<Fader>,
+    events: EventBus,
+}
+
+impl SleepTimer {
+    pub fn new(fader: Arc<Fader>, events: EventBus) -> Self {
+        Self {
+            mode: None,
+            deadline: None,
+            fading: false,
+            fader,
+            events,
+        }
+    }
+
+    pub fn mode(&self) -> Option<SleepTimerMode> {
+        self.mode
+    }
+
+    pub fn set(&mut self, mode: SleepTimerMode) -> Result<()> {
+        self.deadline = match mode {
+            SleepTimerMode::Minutes { minutes: 0 } => return Err(anyhow!("sleep timer minutes must be greater than 0")),
+            SleepTimerMode::Minutes { minutes } => Some(Instant::now() + minutes_to_duration(minutes)),
+            SleepTimerMode::EndOfTrack | SleepTimerMode::EndOfQueue => None,
+        };
+        self.mode = Some(mode);
+        self.restore_volume();
+        Ok(())
+    }
+
+    /// 延长定时；仅 Minutes 模式可延长
+    pub fn extend(&mut self, minutes: u32) -> Result<()> {
+        let (Some(SleepTimerMode::Minutes { minutes: total }), Some(deadline)) = (self.mode, self.deadline) else {
+            return Err(anyhow!("only a timed sleep timer can be extended"));
+        };
+        self.mode = Some(SleepTimerMode::Minutes { minutes: total + minutes });
+        self.deadline = Some(deadline.max(Instant::now()) + minutes_to_duration(minutes));
+        self.restore_volume();
+        Ok(())
+    }
+
+    pub fn cancel(&mut self) {
+        self.mode = None;
+        self.deadline = None;
+        self.restore_volume();
+    }
+
+    /// 定时已到并停止播放后调用：清除定时，增益复位
+    pub fn finish(&mut self) {
+        self.mode = None;
+        self.deadline = None;
+        self.fading = false;
+        self.fader.set(1.0);
+        self.emit(None);
+    }
+
+    /// Minutes 模式距离截止的时间
+    pub fn deadline_remaining(&self) -> Option<Duration> {
+        self.deadline.map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// 每秒调用一次：进入最后 30 秒时开始淡出，并通知前端剩余时间。
+    /// 返回 true 表示 Minutes 模式已到期，需要停止播放
+    pub fn update(&mut self, remaining: Option<Duration>) -> bool {
+        if self.mode.is_none() {
+            return false;
+        }
+
+        if let Some(remaining) = remaining {
+            if remaining <= SLEEP_FADE && !self.fading {
+                self.fading = true;
+                self.fader.fade_to(0.0, remaining);
+            }
+        }
+        self.emit(remaining);
+
+        self.deadline.is_some() && remaining.is_some_and(|r| r.is_zero())
+    }
+
+    pub fn status(&self, remaining: Option<Duration>) -> SleepTimerStatus {
+        SleepTimerStatus {
+            mode: self.mode,
+            remaining_ms: remaining.map(|r| r.as_millis() as u64),
+        }
+    }
+
+    pub fn emit(&self, remaining: Option<Duration>) {
+        self.events.publish(PlayerEvent::SleepTimerUpdated(self.status(remaining)));
+    }
+
+    fn restore_volume(&mut self) {
+        if self.fading {
+            self.fading = false;
+            self.fader.fade_to(1.0, Duration::from_secs(1));
+        }
+    }
+}
+
+fn minutes_to_duration(minutes: u32) -> Duration {
+    Duration::from_secs(minutes as u64 * 60)
+}
@@ -0,0 +1,356 @@
+use std::{
+    collections::VecDeque,
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use crate::events::{EventBus, PlayerEvent};
+
+pub const DEFAULT_SPECTRUM_RATE: u32 = 30;
+pub const MAX_SPECTRUM_RATE: u32 = 60;
+pub const DEFAULT_SPECTRUM_BANDS: u32 = 32;
+pub const MAX_SPECTRUM_BANDS: u32 = 128;
+
+const FFT_SIZE: usize = 2048;
+const CHUNK_FRAMES: usize = 256;   // 音频线程攒够这么多帧再提交，减少加锁次数
+const MIN_FREQUENCY: f32 = 20.0;
+const MAX_FREQUENCY: f32 = 20000.0;
+const FLOOR_DB: f32 = -90.0;       // 频段幅度归一化的下限
+
+/// 一帧频谱，随 Spectrum 事件发布
+#[derive(Debug, Clone, Serialize)]
+pub struct SpectrumFrame {
+    pub bands: Vec<f32>, // 对数分布的频段幅度，-90..0 dBFS 映射到 0..1
+    pub rms: f32,        // 本周期内的线性 RMS 电平
+    pub peak: f32,       // 本周期内的线性峰值
+}
+
+/// 频谱设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectrumSettings {
+    pub enabled: bool,
+    pub rate: u32,  // 每秒发送次数
+    pub bands: u32, // 频段数
+}
+
+/// 音频线程提交、分析线程取走的样本
+struct Captured {
+    samples: VecDeque<f32>, // 最近 FFT_SIZE 帧的单声道混合
+    sample_rate: u32,
+    fresh: bool,            // 上次分析后是否有新样本
+    sum_squares: f64,
+    count: u64,
+    peak: f32,
+}
+
+/// 频谱分析：音频线程只做单声道混合与电平累计，FFT 在独立线程上按设定频率进行。
+/// 未开启时不启动线程，音频线程只检查一次开关。
+pub struct SpectrumAnalyzer {
+    enabled: AtomicBool,
+    rate: AtomicU32,
+    bands: AtomicU32,
+    captured: Mutex<Captured>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+    events: EventBus,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(events: EventBus) -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            rate: AtomicU32::new(DEFAULT_SPECTRUM_RATE),
+            bands: AtomicU32::new(DEFAULT_SPECTRUM_BANDS),
+            captured: Mutex::new(Captured {
+                samples: VecDeque::with_capacity(FFT_SIZE),
+                sample_rate: 44100,
+                fresh: false,
+                sum_squares: 0.0,
+                count: 0,
+                peak: 0.0,
+            }),
+            worker: Mutex::new(None),
+            events,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn settings(&self) -> SpectrumSettings {
+        SpectrumSettings {
+            enabled: self.is_enabled(),
+            rate: self.rate.load(Ordering::Relaxed),
+            bands: self.bands.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 开启并按给定频率与频段数发布频谱；已开启时只更新参数
+    pub fn enable(self: &Arc<Self>, rate: Option<u32>, bands: Option<u32>) {
+        if let Some(rate) = rate {
+            self.rate.store(rate.clamp(1, MAX_SPECTRUM_RATE), Ordering::Relaxed);
+        }
+        if let Some(bands) = bands {
+            self.bands.store(bands.clamp(1, MAX_SPECTRUM_BANDS), Ordering::Relaxed);
+        }
+
+        let mut worker = self.worker.lock().unwrap_or_else(|e| e.into_inner());
+        if worker.is_some() {
+            return;
+        }
+        self.enabled.store(true, Ordering::Relaxed);
+        let analyzer = self.clone();
+        *worker = Some(std::thread::spawn(move || analyzer.run()));
+    }
+
+    /// 关闭并等待分析线程退出
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+        let handle = self.worker.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+        let mut captured = self.captured.lock().unwrap_or_else(|e| e.into_inner());
+        captured.samples.clear();
+        captured.fresh = false;
+    }
+
+    /// 音频线程提交一批单声道样本及其电平；分析线程正占用时直接丢弃，不阻塞播放
+    fn submit(&self, mono: &[f32], sample_rate: u32, sum_squares: f64, count: u64, peak: f32) {
+        let Ok(mut captured) = self.captured.try_lock() else {
+            return;
+        };
+        if captured.sample_rate != sample_rate {
+            captured.samples.clear();
+            captured.sample_rate = sample_rate;
+        }
+        captured.samples.extend(mono);
+        let excess = captured.samples.len().saturating_sub(FFT_SIZE);
+        captured.samples.drain(..excess);
+        captured.fresh = true;
+        captured.sum_squares += sum_squares;
+        captured.count += count;
+        captured.peak = captured.peak.max(peak);
+    }
+
+    fn run(&self) {
+        let mut fft = Fft::new(FFT_SIZE);
+        let mut silent_sent = false;
+        while self.is_enabled() {
+            let interval = Duration::from_secs_f32(1.0 / self.rate.load(Ordering::Relaxed).max(1) as f32);
+            std::thread::sleep(interval);
+
+            let (samples, sample_rate, rms, peak) = {
+                let mut captured = self.captured.lock().unwrap_or_else(|e| e.into_inner());
+                if !captured.fresh {
+                    (None, captured.sample_rate, 0.0, 0.0)
+                } else {
+                    let rms = (captured.sum_squares / captured.count.max(1) as f64).sqrt() as f32;
+                    let peak = captured.peak;
+                    captured.fresh = false;
+                    captured.sum_squares = 0.0;
+                    captured.count = 0;
+                    captured.peak = 0.0;
+                    (Some(captured.samples.iter().copied().collect::<Vec<f32>>()), captured.sample_rate, rms, peak)
+                }
+            };
+
+            let band_count = self.bands.load(Ordering::Relaxed) as usize;
+            let frame = match samples {
+                Some(samples) => {
+                    silent_sent = false;
+                    SpectrumFrame { bands: fft.bands(&samples, sample_rate, band_count), rms, peak }
+                }
+                // 暂停或停止后发送一帧静音让可视化回落，之后不再发送
+                None if !silent_sent => {
+                    silent_sent = true;
+                    SpectrumFrame { bands: vec![0.0; band_count], rms: 0.0, peak: 0.0 }
+                }
+                None => continue,
+            };
+            self.events.publish(PlayerEvent::Spectrum(Box::new(frame)));
+        }
+    }
+}
+
+/// 分析所用的 FFT：基 2 原位变换，输入加 Hann 窗
+struct Fft {
+    size: usize,
+    window: Vec<f32>,
+    window_sum: f32,
+    twiddles: Vec<(f32, f32)>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+impl Fft {
+    fn new(size: usize) -> Self {
+        let window: Vec<f32> = (0..size)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / size as f32).cos())
+            .collect();
+        let window_sum = window.iter().sum();
+        let twiddles = (0..size / 2)
+            .map(|k| {
+                let angle = -2.0 * PI * k as f32 / size as f32;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+        Self { size, window, window_sum, twiddles, re: vec![0.0; size], im: vec![0.0; size] }
+    }
+
+    fn transform(&mut self) {
+        let n = self.size;
+        // 位反转重排
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+            if i < j {
+                self.re.swap(i, j);
+                self.im.swap(i, j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= n {
+            let step = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..len / 2 {
+                    let (wr, wi) = self.twiddles[k * step];
+                    let (a, b) = (start + k, start + k + len / 2);
+                    let tr = self.re[b] * wr - self.im[b] * wi;
+                    let ti = self.re[b] * wi + self.im[b] * wr;
+                    self.re[b] = self.re[a] - tr;
+                    self.im[b] = self.im[a] - ti;
+                    self.re[a] += tr;
+                    self.im[a] += ti;
+                }
+            }
+            len <<= 1;
+        }
+    }
+
+    /// 计算对数分布的频段幅度；样本不足一个窗长时前面补零
+    fn bands(&mut self, samples: &[f32], sample_rate: u32, band_count: usize) -> Vec<f32> {
+        let offset = self.size - samples.len().min(self.size);
+        let samples = &samples[samples.len().saturating_sub(self.size)..];
+        self.re.iter_mut().for_each(|s| *s = 0.0);
+        self.im.iter_mut().for_each(|s| *s = 0.0);
+        for (i, &s) in samples.iter().enumerate() {
+            self.re[offset + i] = s * self.window[offset + i];
+        }
+        self.transform();
+
+        let bin_width = sample_rate as f32 / self.size as f32;
+        let max_frequency = MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+        let ratio = (max_frequency / MIN_FREQUENCY).ln();
+        let magnitude = |bin: usize| {
+            // 单边谱幅度，按窗函数增益归一化到满幅正弦为 1
+            let bin = bin.clamp(1, self.size / 2 - 1);
+            (self.re[bin].hypot(self.im[bin])) * 2.0 / self.window_sum
+        };
+
+        (0..band_count)
+            .map(|b| {
+                let low = MIN_FREQUENCY * (ratio * b as f32 / band_count as f32).exp();
+                let high = MIN_FREQUENCY * (ratio * (b + 1) as f32 / band_count as f32).exp();
+                let first = (low / bin_width).floor() as usize;
+                let last = ((high / bin_width).ceil() as usize).max(first + 1);
+                let level = (first..last).map(magnitude).fold(0.0f32, f32::max);
+                let db = 20.0 * level.max(1e-9).log10();
+                ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+/// 旁路取样 Source：把流经的样本交给频谱分析，样本本身原样输出
+pub struct SpectrumSource<S> {
+    inner: S,
+    analyzer: Arc<SpectrumAnalyzer>,
+    channels: usize,
+    channel: usize,
+    frame_sum: f32,
+    mono: Vec<f32>,
+    sum_squares: f64,
+    count: u64,
+    peak: f32,
+}
+
+impl<S: Source> SpectrumSource<S> {
+    pub fn new(inner: S, analyzer: Arc<SpectrumAnalyzer>) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        Self {
+            inner,
+            analyzer,
+            channels,
+            channel: 0,
+            frame_sum: 0.0,
+            mono: Vec::with_capacity(CHUNK_FRAMES),
+            sum_squares: 0.0,
+            count: 0,
+            peak: 0.0,
+        }
+    }
+
+    fn flush(&mut self) {
+        self.analyzer.submit(&self.mono, self.inner.sample_rate(), self.sum_squares, self.count, self.peak);
+        self.mono.clear();
+        self.sum_squares = 0.0;
+        self.count = 0;
+        self.peak = 0.0;
+    }
+}
+
+impl<S: Source> Iterator for SpectrumSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.inner.next()?;
+        if !self.analyzer.is_enabled() {
+            self.channel = 0;
+            self.frame_sum = 0.0;
+            return Some(sample);
+        }
+
+        self.frame_sum += sample;
+        self.sum_squares += (sample * sample) as f64;
+        self.count += 1;
+        self.peak = self.peak.max(sample.abs());
+        self.channel += 1;
+        if self.channel == self.channels {
+            self.mono.push(self.frame_sum / self.channels as f32);
+            self.channel = 0;
+            self.frame_sum = 0.0;
+            if self.mono.len() >= CHUNK_FRAMES {
+                self.flush();
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for SpectrumSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
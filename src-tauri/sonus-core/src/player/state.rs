@@ -1,9 +1,11 @@
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
-use crate::core::library::index::Track;
-use crate::core::playlist::manager::Playlist;
-use crate::core::playlist::play_mode::PlayMode;
+use crate::library::chapters::{chapter_at, Chapter};
+use crate::library::index::Track;
+use crate::player::stereo::Crossfeed;
+use crate::playlist::manager::Playlist;
+use crate::playlist::play_mode::PlayMode;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PlaybackState {
@@ -23,6 +25,13 @@ pub struct PlayerState {
     current_playlist: Playlist,
     current_play_mode: PlayMode,
     current_index: Option<usize>,
+    playback_rate: f32,
+    preserve_pitch: bool,
+    ab_loop: Option<(Duration, Duration)>,
+    balance: f32,
+    mono: bool,
+    crossfeed: Crossfeed,
+    chapters: Vec<Chapter>, // 当前曲目的章节
 }
 
 impl Default for PlayerState {
@@ -37,6 +46,13 @@ impl Default for PlayerState {
             current_playlist: Playlist::new(),
             current_play_mode: PlayMode::Single,
             current_index: None,
+            playback_rate: 1.0,
+            preserve_pitch: true,
+            ab_loop: None,
+            balance: 0.0,
+            mono: false,
+            crossfeed: Crossfeed::Off,
+            chapters: Vec::new(),
         }
     }
 }
@@ -71,6 +87,30 @@ impl PlayerState {
     pub fn current_index(&self) -> Option<usize> { self.current_index }
     pub fn set_current_index(&mut self, index: Option<usize>) { self.current_index = index; }
 
+    pub fn playback_rate(&self) -> f32 { self.playback_rate }
+    pub fn set_playback_rate(&mut self, rate: f32) { self.playback_rate = rate; }
+
+    pub fn preserve_pitch(&self) -> bool { self.preserve_pitch }
+    pub fn set_preserve_pitch(&mut self, enabled: bool) { self.preserve_pitch = enabled; }
+
+    pub fn ab_loop(&self) -> Option<(Duration, Duration)> { self.ab_loop }
+    pub fn set_ab_loop(&mut self, ab_loop: Option<(Duration, Duration)>) { self.ab_loop = ab_loop; }
+
+    pub fn balance(&self) -> f32 { self.balance }
+    pub fn set_balance(&mut self, balance: f32) { self.balance = balance.clamp(-1.0, 1.0); }
+
+    pub fn mono(&self) -> bool { self.mono }
+    pub fn set_mono(&mut self, enabled: bool) { self.mono = enabled; }
+
+    pub fn crossfeed(&self) -> Crossfeed { self.crossfeed }
+    pub fn set_crossfeed(&mut self, crossfeed: Crossfeed) { self.crossfeed = crossfeed; }
+
+    pub fn chapters(&self) -> &[Chapter] { &self.chapters }
+    pub fn set_chapters(&mut self, chapters: Vec<Chapter>) { self.chapters = chapters; }
+
+    /// 当前位置所在的章节
+    pub fn current_chapter(&self) -> Option<&Chapter> { chapter_at(&self.chapters, self.current_position) }
+
     // 便捷方法
     pub fn is_playing(&self) -> bool { self.playback_state == PlaybackState::Playing }
     pub fn is_paused(&self) -> bool { self.playback_state == PlaybackState::Paused }
@@ -103,6 +143,15 @@ pub struct StateSnapshot {
     pub current_playlist: Playlist,
     pub current_play_mode: PlayMode,
     pub current_index: Option<usize>,
+    pub playback_rate: f32,
+    pub preserve_pitch: bool,
+    pub loop_start: Option<u64>, // 以毫秒为单位
+    pub loop_end: Option<u64>,   // 以毫秒为单位
+    pub balance: f32,            // -1 左 … 1 右
+    pub mono: bool,
+    pub crossfeed: Crossfeed,
+    pub chapter_index: Option<usize>,
+    pub chapter_title: Option<String>,
 }
 
 impl From<&PlayerState> for StateSnapshot {
@@ -117,6 +166,15 @@ impl From<&PlayerState> for StateSnapshot {
             current_playlist: state.current_playlist().clone(),
             current_play_mode: state.current_play_mode().into(),
             current_index: state.current_index(),
+            playback_rate: state.playback_rate(),
+            preserve_pitch: state.preserve_pitch(),
+            loop_start: state.ab_loop().map(|(start, _)| start.as_millis() as u64),
+            loop_end: state.ab_loop().map(|(_, end)| end.as_millis() as u64),
+            balance: state.balance(),
+            mono: state.mono(),
+            crossfeed: state.crossfeed(),
+            chapter_index: state.current_chapter().map(|c| c.index),
+            chapter_title: state.current_chapter().and_then(|c| c.title.clone()),
         }
     }
 }
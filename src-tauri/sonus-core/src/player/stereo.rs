@@ -0,0 +1,330 @@
+use std::{
+    f64::consts::PI,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use crate::database::{connection, get_config_value, set_config_value};
+
+/// Bauer 交叉馈送强度，对应 crossfeed 配置：0 关闭，1–3 为 bs2b 的三组预设
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Crossfeed {
+    #[default]
+    Off,
+    Default,  // 700 Hz, 4.5 dB
+    ChuMoy,   // 700 Hz, 6 dB
+    JanMeier, // 650 Hz, 9.5 dB
+}
+
+impl Crossfeed {
+    pub fn from_config(value: &str) -> Self {
+        match value.trim() {
+            "1" => Crossfeed::Default,
+            "2" => Crossfeed::ChuMoy,
+            "3" => Crossfeed::JanMeier,
+            _ => Crossfeed::Off,
+        }
+    }
+
+    pub fn config_value(self) -> &'static str {
+        match self {
+            Crossfeed::Off => "0",
+            Crossfeed::Default => "1",
+            Crossfeed::ChuMoy => "2",
+            Crossfeed::JanMeier => "3",
+        }
+    }
+
+    /// (低通截止频率 Hz, 馈送量 dB)
+    fn params(self) -> Option<(f64, f64)> {
+        match self {
+            Crossfeed::Off => None,
+            Crossfeed::Default => Some((700.0, 4.5)),
+            Crossfeed::ChuMoy => Some((700.0, 6.0)),
+            Crossfeed::JanMeier => Some((650.0, 9.5)),
+        }
+    }
+}
+
+/// 立体声像设置，对应 balance / mono_downmix / crossfeed 配置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct StereoSettings {
+    pub balance: f32, // -1 只有左声道，0 居中，1 只有右声道
+    pub mono: bool,   // 混合为单声道，单只耳机收听时不丢失声部
+    pub crossfeed: Crossfeed,
+}
+
+impl StereoSettings {
+    fn load() -> Self {
+        let conn = connection();
+        let read = |key: &str| get_config_value(&conn, key).ok().map(|c| c.value);
+        Self {
+            balance: read("balance")
+                .and_then(|v| v.trim().parse::<f32>().ok())
+                .filter(|v| v.is_finite())
+                .map_or(0.0, |v| v.clamp(-1.0, 1.0)),
+            mono: read("mono_downmix").is_some_and(|v| v.trim() == "1"),
+            crossfeed: read("crossfeed").map_or(Crossfeed::Off, |v| Crossfeed::from_config(&v)),
+        }
+    }
+
+    fn save(&self) {
+        let conn = connection();
+        _ = set_config_value(&conn, "balance", &self.balance.to_string());
+        _ = set_config_value(&conn, "mono_downmix", if self.mono { "1" } else { "0" });
+        _ = set_config_value(&conn, "crossfeed", self.crossfeed.config_value());
+    }
+
+    fn is_neutral(&self) -> bool {
+        self.balance == 0.0 && !self.mono && self.crossfeed == Crossfeed::Off
+    }
+}
+
+/// 播放链共享的立体声像参数；修改后递增版本号，音频线程检测到后更新，无需重建 Source。
+pub struct StereoImage {
+    settings: Mutex<StereoSettings>,
+    version: AtomicU64,
+}
+
+impl StereoImage {
+    pub fn load() -> Self {
+        Self {
+            settings: Mutex::new(StereoSettings::load()),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    pub fn settings(&self) -> StereoSettings {
+        *self.settings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update<F: FnOnce(&mut StereoSettings)>(&self, f: F) {
+        let mut settings = self.settings.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut settings);
+        settings.save();
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn set_balance(&self, balance: f32) {
+        self.update(|s| s.balance = balance.clamp(-1.0, 1.0));
+    }
+
+    pub fn set_mono(&self, enabled: bool) {
+        self.update(|s| s.mono = enabled);
+    }
+
+    pub fn set_crossfeed(&self, crossfeed: Crossfeed) {
+        self.update(|s| s.crossfeed = crossfeed);
+    }
+}
+
+/// bs2b 交叉馈送：每个声道经低通后馈入另一侧，本侧用高频提升补偿，模拟音箱聆听时的串音
+#[derive(Clone, Copy, Default)]
+struct CrossfeedFilter {
+    a0_lo: f64,
+    b1_lo: f64,
+    a0_hi: f64,
+    a1_hi: f64,
+    b1_hi: f64,
+    gain: f64,
+    lo: [f64; 2],
+    hi: [f64; 2],
+    last: [f64; 2], // 上一帧输入
+}
+
+impl CrossfeedFilter {
+    fn new(cutoff: f64, feed_db: f64, sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+        let gain_lo_db = feed_db * -5.0 / 6.0 - 3.0;
+        let gain_hi_db = feed_db / 6.0 - 3.0;
+        let g_lo = 10f64.powf(gain_lo_db / 20.0);
+        let g_hi = 1.0 - 10f64.powf(gain_hi_db / 20.0);
+        let cutoff_hi = cutoff * 2f64.powf((gain_lo_db - 20.0 * g_hi.log10()) / 12.0);
+
+        let x_lo = (-2.0 * PI * cutoff / fs).exp();
+        let x_hi = (-2.0 * PI * cutoff_hi.min(fs * 0.45) / fs).exp();
+        Self {
+            a0_lo: g_lo * (1.0 - x_lo),
+            b1_lo: x_lo,
+            a0_hi: 1.0 - g_hi * (1.0 - x_hi),
+            a1_hi: -x_hi,
+            b1_hi: x_hi,
+            // 补偿低频叠加后的增益，避免削波
+            gain: 1.0 / (1.0 - g_hi + g_lo),
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, left: f64, right: f64) -> (f64, f64) {
+        for (ch, x) in [left, right].into_iter().enumerate() {
+            self.lo[ch] = self.a0_lo * x + self.b1_lo * self.lo[ch];
+            self.hi[ch] = self.a0_hi * x + self.a1_hi * self.last[ch] + self.b1_hi * self.hi[ch];
+            self.last[ch] = x;
+        }
+        (
+            (self.hi[0] + self.lo[1]) * self.gain,
+            (self.hi[1] + self.lo[0]) * self.gain,
+        )
+    }
+}
+
+/// 在 Source 上应用平衡、单声道混合与交叉馈送；按整帧处理，非立体声只做单声道混合
+pub struct StereoSource<S> {
+    inner: S,
+    image: Arc<StereoImage>,
+    version: u64,
+    sample_rate: u32,
+    settings: StereoSettings,
+    crossfeed: Option<CrossfeedFilter>,
+    frame: Vec<f32>,
+    pos: usize,
+}
+
+impl<S: Source> StereoSource<S> {
+    pub fn new(inner: S, image: Arc<StereoImage>) -> Self {
+        let mut source = Self {
+            inner,
+            image,
+            version: u64::MAX,
+            sample_rate: 0,
+            settings: StereoSettings::default(),
+            crossfeed: None,
+            frame: Vec::new(),
+            pos: 0,
+        };
+        source.refresh();
+        source
+    }
+
+    fn refresh(&mut self) {
+        let version = self.image.version.load(Ordering::Acquire);
+        let sample_rate = self.inner.sample_rate();
+        if version == self.version && sample_rate == self.sample_rate {
+            return;
+        }
+        // 控制线程正在修改参数时沿用旧设置，下一帧再试
+        let Ok(settings) = self.image.settings.try_lock() else { return; };
+
+        // 只在开关或强度变化时重建滤波器，保留滤波状态避免爆音
+        let rebuild = settings.crossfeed != self.settings.crossfeed || sample_rate != self.sample_rate;
+        self.settings = *settings;
+        if rebuild {
+            self.crossfeed = settings.crossfeed.params()
+                .map(|(cutoff, feed)| CrossfeedFilter::new(cutoff, feed, sample_rate));
+        }
+        self.version = version;
+        self.sample_rate = sample_rate;
+    }
+
+    /// 读入下一帧并处理；上游在帧中途结束时原样输出剩余样本
+    fn fill_frame(&mut self) -> bool {
+        let channels = self.inner.channels().max(1) as usize;
+        self.frame.clear();
+        self.pos = 0;
+        for _ in 0..channels {
+            match self.inner.next() {
+                Some(sample) => self.frame.push(sample),
+                None => break,
+            }
+        }
+        if self.frame.is_empty() {
+            return false;
+        }
+        if self.frame.len() < channels {
+            return true;
+        }
+
+        self.refresh();
+        if self.settings.is_neutral() {
+            return true;
+        }
+
+        if self.settings.mono {
+            let mix = self.frame.iter().sum::<f32>() / channels as f32;
+            self.frame.fill(mix);
+        }
+        if channels != 2 {
+            return true;
+        }
+
+        let (mut left, mut right) = (self.frame[0] as f64, self.frame[1] as f64);
+        if let Some(filter) = self.crossfeed.as_mut().filter(|_| !self.settings.mono) {
+            (left, right) = filter.process(left, right);
+        }
+        let balance = self.settings.balance as f64;
+        left *= (1.0 - balance).min(1.0);
+        right *= (1.0 + balance).min(1.0);
+        self.frame[0] = left as f32;
+        self.frame[1] = right as f32;
+        true
+    }
+}
+
+impl<S: Source> Iterator for StereoSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.frame.len() && !self.fill_frame() {
+            return None;
+        }
+        let sample = self.frame[self.pos];
+        self.pos += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for StereoSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn image(settings: StereoSettings) -> Arc<StereoImage> {
+        Arc::new(StereoImage { settings: Mutex::new(settings), version: AtomicU64::new(0) })
+    }
+
+    fn render(samples: Vec<f32>, settings: StereoSettings) -> Vec<f32> {
+        StereoSource::new(SamplesBuffer::new(2, 44100, samples), image(settings)).collect()
+    }
+
+    #[test]
+    fn balance_and_mono_per_frame() {
+        let input = vec![1.0, 0.0, 0.5, -0.5];
+        assert_eq!(render(input.clone(), StereoSettings::default()), input);
+
+        let left = StereoSettings { balance: -0.5, ..Default::default() };
+        assert_eq!(render(input.clone(), left), vec![1.0, 0.0, 0.5, -0.25]);
+
+        let mono = StereoSettings { mono: true, ..Default::default() };
+        assert_eq!(render(input, mono), vec![0.5, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn crossfeed_bleeds_lows_into_other_channel() {
+        let settings = StereoSettings { crossfeed: Crossfeed::Default, ..Default::default() };
+        // 只有左声道的直流信号，稳定后右声道应收到衰减后的馈送，且不超过左声道
+        let out = render([1.0, 0.0].repeat(44100), settings);
+        let (left, right) = (out[out.len() - 2], out[out.len() - 1]);
+        assert!(right > 0.1 && right < left, "left {left} right {right}");
+        assert!(left <= 1.0);
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use rodio::Source;
+
+pub const MIN_PLAYBACK_RATE: f32 = 0.5;
+pub const MAX_PLAYBACK_RATE: f32 = 3.0;
+
+const WSOLA_FRAME_MS: f64 = 30.0;  // 合成帧长
+const WSOLA_SEARCH_MS: f64 = 8.0;  // 相似度搜索半径
+
+/// 播放速率，音频线程实时读取
+pub struct PlaybackRate {
+    rate: AtomicU32, // f32 位模式
+    preserve_pitch: AtomicBool,
+}
+
+impl Default for PlaybackRate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlaybackRate {
+    pub fn new() -> Self {
+        Self {
+            rate: AtomicU32::new(1.0f32.to_bits()),
+            preserve_pitch: AtomicBool::new(true),
+        }
+    }
+
+    pub fn rate(&self) -> f32 {
+        f32::from_bits(self.rate.load(Ordering::Relaxed))
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        let rate = rate.clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE);
+        self.rate.store(rate.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn preserve_pitch(&self) -> bool {
+        self.preserve_pitch.load(Ordering::Relaxed)
+    }
+
+    pub fn set_preserve_pitch(&self, enabled: bool) {
+        self.preserve_pitch.store(enabled, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Passthrough,
+    Varispeed, // 重采样变速，音调随之改变
+    Wsola,     // 波形相似叠加，保持音调
+}
+
+/// 变速 Source 适配器：速率为 1 时直通，否则按设置做变调重采样或 WSOLA 时间伸缩。
+/// 上游位置按实际消耗的输入帧计算，因此进度与跳转不受速率影响。
+pub struct TimeStretchSource<S> {
+    inner: S,
+    rate: Arc<PlaybackRate>,
+    channels: usize,
+    sample_rate: u32,
+    mode: Mode,
+    input_done: bool,
+
+    // 已从上游读取、尚未消耗的交错样本；input_base 为 input[0] 的绝对帧序号
+    input: VecDeque<f32>,
+    input_base: u64,
+    out: VecDeque<f32>,
+
+    // 变调重采样
+    frac: f64,
+    frame_a: Vec<f32>,
+    frame_b: Vec<f32>,
+
+    // WSOLA
+    frame_len: usize,
+    window: Vec<f32>,
+    search: usize,
+    analysis_pos: f64,
+    prev_start: u64,
+    overlap: Vec<f32>,
+}
+
+impl<S: Source> TimeStretchSource<S> {
+    pub fn new(inner: S, rate: Arc<PlaybackRate>) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        let sample_rate = inner.sample_rate();
+        let frame_len = ((sample_rate as f64 * WSOLA_FRAME_MS / 1000.0) as usize / 2 * 2).max(64);
+        // 周期 Hann 窗，50% 重叠时恒等相加
+        let window = (0..frame_len)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame_len as f32).cos())
+            .collect();
+        Self {
+            inner,
+            rate,
+            channels,
+            sample_rate,
+            mode: Mode::Passthrough,
+            input_done: false,
+            input: VecDeque::new(),
+            input_base: 0,
+            out: VecDeque::new(),
+            frac: 0.0,
+            frame_a: Vec::new(),
+            frame_b: Vec::new(),
+            frame_len,
+            window,
+            search: (sample_rate as f64 * WSOLA_SEARCH_MS / 1000.0) as usize,
+            analysis_pos: 0.0,
+            prev_start: 0,
+            overlap: Vec::new(),
+        }
+    }
+
+    fn wanted_mode(&self) -> Mode {
+        let rate = self.rate.rate();
+        if (rate - 1.0).abs() < 1e-3 {
+            Mode::Passthrough
+        } else if self.rate.preserve_pitch() {
+            Mode::Wsola
+        } else {
+            Mode::Varispeed
+        }
+    }
+
+    /// 切换模式：丢弃内部的伸缩状态，从当前输入位置重新开始
+    fn switch_mode(&mut self, mode: Mode) {
+        if mode == self.mode {
+            return;
+        }
+        match self.mode {
+            // 重采样已取出的下一帧还没输出，放回输入队列
+            Mode::Varispeed => {
+                for &s in self.frame_b.iter().rev() {
+                    self.input.push_front(s);
+                }
+                self.input_base -= (!self.frame_b.is_empty()) as u64;
+            }
+            // WSOLA 的重叠尾部直接输出，避免丢样
+            Mode::Wsola => self.out.extend(self.overlap.drain(..)),
+            Mode::Passthrough => {}
+        }
+        self.frame_a.clear();
+        self.frame_b.clear();
+        self.frac = 0.0;
+        self.analysis_pos = self.input_base as f64;
+        self.prev_start = self.input_base.saturating_sub((self.frame_len / 2) as u64);
+        self.overlap.clear();
+        self.mode = mode;
+    }
+
+    /// 读取上游直到输入队列至少有 frames 帧；上游耗尽时返回 false
+    fn fill_input(&mut self, frames: u64) -> bool {
+        while (self.input.len() / self.channels) < frames as usize {
+            if self.input_done {
+                return false;
+            }
+            match self.inner.next() {
+                Some(s) => self.input.push_back(s),
+                None => self.input_done = true,
+            }
+        }
+        true
+    }
+
+    fn pop_frame(&mut self, frame: &mut Vec<f32>) -> bool {
+        frame.clear();
+        if !self.fill_input(1) {
+            return false;
+        }
+        frame.extend(self.input.drain(..self.channels));
+        self.input_base += 1;
+        true
+    }
+
+    fn input_at(&self, frame: u64, channel: usize) -> f32 {
+        frame
+            .checked_sub(self.input_base)
+            .and_then(|f| self.input.get(f as usize * self.channels + channel))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn mono_at(&self, frame: u64) -> f32 {
+        (0..self.channels).map(|c| self.input_at(frame, c)).sum()
+    }
+
+    fn step_passthrough(&mut self) -> bool {
+        if !self.fill_input(1) {
+            return false;
+        }
+        self.out.extend(self.input.drain(..self.channels));
+        self.input_base += 1;
+        true
+    }
+
+    fn step_varispeed(&mut self) -> bool {
+        if self.frame_a.is_empty() {
+            let mut a = Vec::new();
+            if !self.pop_frame(&mut a) {
+                return false;
+            }
+            let mut b = Vec::new();
+            if !self.pop_frame(&mut b) {
+                // 只剩最后一帧
+                self.out.extend(a);
+                return true;
+            }
+            self.frame_a = a;
+            self.frame_b = b;
+        }
+
+        let t = self.frac as f32;
+        for c in 0..self.channels {
+            let a = self.frame_a[c];
+            self.out.push_back(a + (self.frame_b[c] - a) * t);
+        }
+
+        self.frac += self.rate.rate() as f64;
+        while self.frac >= 1.0 {
+            self.frac -= 1.0;
+            let mut next = std::mem::take(&mut self.frame_a);
+            if !self.pop_frame(&mut next) {
+                // 上游结束
+                self.frame_a.clear();
+                self.frame_b.clear();
+                break;
+            }
+            self.frame_a = std::mem::replace(&mut self.frame_b, next);
+        }
+        true
+    }
+
+    /// 产出一个合成跳距（frame_len / 2 帧）的 WSOLA 输出
+    fn step_wsola(&mut self) -> bool {
+        let hop = self.frame_len / 2;
+        let search = self.search as u64;
+        let nominal = self.analysis_pos as u64;
+        let lo = nominal.saturating_sub(search).max(self.input_base);
+        let hi = nominal + search;
+
+        let available = self.fill_input(hi + self.frame_len as u64 - self.input_base);
+        let input_end = self.input_base + (self.input.len() / self.channels) as u64;
+        if !available && lo >= input_end {
+            // 输入已耗尽，输出重叠尾部后结束
+            if self.overlap.is_empty() {
+                return false;
+            }
+            self.out.extend(self.overlap.drain(..));
+            return true;
+        }
+
+        // 在搜索范围内寻找与上一段自然延续最相似的起点（单声道混合、隔点相关）
+        let natural = self.prev_start + hop as u64;
+        let target: Vec<f32> = (0..hop as u64).step_by(2).map(|i| self.mono_at(natural + i)).collect();
+        let candidates: Vec<f32> = (lo..hi + hop as u64).map(|f| self.mono_at(f)).collect();
+        let mut best = nominal.max(lo);
+        let mut best_score = f32::MIN;
+        for offset in 0..=(hi - lo) as usize {
+            let score: f32 = target
+                .iter()
+                .enumerate()
+                .map(|(j, t)| t * candidates[offset + j * 2])
+                .sum();
+            if score > best_score {
+                best_score = score;
+                best = lo + offset as u64;
+            }
+        }
+
+        if self.overlap.is_empty() {
+            self.overlap = vec![0.0; hop * self.channels];
+        }
+        let mut next_overlap = Vec::with_capacity(self.overlap.len());
+        for i in 0..hop {
+            let w_in = self.window[i];
+            let w_out = self.window[i + hop];
+            for c in 0..self.channels {
+                let head = self.input_at(best + i as u64, c);
+                let tail = self.input_at(best + (hop + i) as u64, c);
+                self.out.push_back(self.overlap[i * self.channels + c] + head * w_in);
+                next_overlap.push(tail * w_out);
+            }
+        }
+        self.overlap = next_overlap;
+        self.prev_start = best;
+        self.analysis_pos += hop as f64 * self.rate.rate() as f64;
+
+        // 丢弃之后不会再访问的输入
+        let keep_from = (self.prev_start + hop as u64).min((self.analysis_pos as u64).saturating_sub(search));
+        while self.input_base < keep_from && self.input.len() >= self.channels {
+            self.input.drain(..self.channels);
+            self.input_base += 1;
+        }
+        true
+    }
+}
+
+impl<S: Source> Iterator for TimeStretchSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(s) = self.out.pop_front() {
+                return Some(s);
+            }
+
+            let mode = self.wanted_mode();
+            self.switch_mode(mode);
+            let produced = match mode {
+                Mode::Passthrough => self.step_passthrough(),
+                Mode::Varispeed => self.step_varispeed(),
+                Mode::Wsola => self.step_wsola(),
+            };
+            if !produced && self.out.is_empty() {
+                return None;
+            }
+        }
+    }
+}
+
+impl<S: Source> Source for TimeStretchSource<S> {
+    /// 输出格式固定；原速时逐帧透传，已取出未输出的样本加上输入的剩余长度即为本段长度
+    fn current_span_len(&self) -> Option<usize> {
+        match self.mode {
+            Mode::Passthrough => self.inner.current_span_len().map(|n| n + self.input.len() + self.out.len()),
+            _ => (!self.out.is_empty()).then_some(self.out.len()),
+        }
+    }
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
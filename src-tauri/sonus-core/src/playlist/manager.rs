@@ -2,11 +2,11 @@ use std::time::Duration;
 use chrono::{DateTime, Utc};
 use rand::prelude::SliceRandom;
 use serde::{Deserialize, Serialize};
-use tracing::info;
-use crate::app::database;
-use crate::core::library::index::Track;
-use crate::core::player::audio_backend::AudioBackend;
-pub(crate) use super::play_mode::PlayMode;
+use tracing::{info, warn};
+use crate::library::index::Track;
+use crate::player::audio_backend::{AudioBackend, TrackRange};
+use crate::player::replay_gain::ReplayGain;
+pub use super::play_mode::PlayMode;
 
 /**
  * Playlist Manager
@@ -38,6 +38,7 @@ pub struct PlaylistManager {
     pub playlist: Playlist,
     pub current_index: Option<usize>,
     pub play_mode: PlayMode,
+    pub queued_index: Option<usize>, // 已预排到后端的下一首
     original_tracks: Vec<Track>,
 }
 
@@ -48,6 +49,7 @@ impl PlaylistManager {
             playlist,
             current_index: None,
             play_mode: PlayMode::Repeat,
+            queued_index: None,
             original_tracks,
         }
     }
@@ -60,6 +62,21 @@ impl PlaylistManager {
         &mut self.playlist.tracks
     }
 
+    /// 随机模式打乱前的原始顺序
+    pub fn original_tracks(&self) -> &[Track] {
+        &self.original_tracks
+    }
+
+    /// 恢复上次会话的播放列表，不触发随机打乱
+    pub fn restore(&mut self, tracks: Vec<Track>, original_tracks: Vec<Track>, current_index: Option<usize>, play_mode: PlayMode) {
+        self.playlist.tracks = tracks;
+        self.original_tracks = original_tracks;
+        self.current_index = current_index.filter(|&i| i < self.playlist.tracks.len());
+        self.queued_index = None;
+        self.play_mode = play_mode;
+        self.playlist.updated_at = Utc::now();
+    }
+
     pub fn empty_playlist() -> Self {
         let original_tracks = vec![];
         Self {
@@ -72,6 +89,7 @@ impl PlaylistManager {
             },
             current_index: None,
             play_mode: PlayMode::Repeat,
+            queued_index: None,
             original_tracks,
         }
     }
@@ -90,19 +108,86 @@ impl PlaylistManager {
         }
     }
 
-    pub fn play(&mut self, backend: &mut AudioBackend) {
-        if let Some(index) = self.current_index {
+    pub fn play(&mut self, backend: &mut AudioBackend) -> anyhow::Result<()> {
+        self.play_at(backend, Duration::new(0, 0))
+    }
+
+    /// 从指定位置播放当前曲目（无当前曲目时播放第一首）；
+    /// 无法打开或解码时返回错误，当前索引仍指向这首曲目，原来的播放不受影响
+    pub fn play_at(&mut self, backend: &mut AudioBackend, position: Duration) -> anyhow::Result<()> {
+        if self.current_index.is_none() && !self.playlist.tracks.is_empty() {
+            self.current_index = Some(0);
+        }
+        if let Some(track) = self.get_current_track() {
             backend.load_and_play(
-                self.playlist.tracks[index].file_path.clone(),
-                Duration::new(0, 0)
-            ).expect("Failed to load and play track");
-        } else {
-            if !self.playlist.tracks.is_empty() {
-                backend.load_and_play(
-                    self.playlist.tracks[0].file_path.clone(),
-                    Duration::new(0, 0)
-                ).expect("Failed to load and play first track");
-                self.current_index = Some(0);
+                track.file_path.clone(),
+                position,
+                ReplayGain::from(track),
+                TrackRange::from(track)
+            )?;
+        }
+        self.queue_next(backend);
+        Ok(())
+    }
+
+    /// 标记当前曲目是否可播放，返回被修改的曲目
+    pub fn set_current_unavailable(&mut self, unavailable: bool) -> Option<&Track> {
+        let index = self.current_index?;
+        let track = self.playlist.tracks.get_mut(index)?;
+        track.unavailable = unavailable;
+        Some(track)
+    }
+
+    /// 把下一首预排到后端，实现无缝播放
+    pub fn queue_next(&mut self, backend: &mut AudioBackend) {
+        backend.clear_queued();
+        self.queued_index = self.peek_next_index();
+        if let Some(index) = self.queued_index {
+            let track = &self.playlist.tracks[index];
+            let path = track.file_path.clone();
+            if let Err(e) = backend.queue_next(&path, ReplayGain::from(track), TrackRange::from(track)) {
+                warn!("queue_next: Failed to queue {}: {}", path, e);
+                self.queued_index = None;
+            }
+        }
+    }
+
+    /// 后端已切换到预排曲目，当前索引随之前进
+    pub fn advance_to_queued(&mut self) -> Option<&Track> {
+        let index = self.queued_index.take()?;
+        if index >= self.playlist.tracks.len() {
+            info!("advance_to_queued: Queued index {} out of bounds", index);
+            return None;
+        }
+        self.current_index = Some(index);
+        self.get_current_track()
+    }
+
+    /// 计算下一首的索引但不移动当前位置；列表循环与随机模式下不预排当前曲目自身，
+    /// 只有一首时由 TrackEnded 重新播放
+    fn peek_next_index(&self) -> Option<usize> {
+        let tracks_len = self.playlist.tracks.len();
+        if tracks_len == 0 {
+            return None;
+        }
+
+        match self.play_mode {
+            PlayMode::Repeat => match self.current_index {
+                Some(current) if current + 1 < tracks_len => Some(current + 1),
+                _ => Some(0).filter(|&index| self.current_index != Some(index)),
+            },
+            PlayMode::Single => self.current_index,
+            PlayMode::Random => {
+                if tracks_len == 1 {
+                    return Some(0).filter(|&index| self.current_index != Some(index));
+                }
+                let mut rng = rand::rng();
+                loop {
+                    let next_index = rand::Rng::random_range(&mut rng, 0..tracks_len);
+                    if self.current_index != Some(next_index) {
+                        return Some(next_index);
+                    }
+                }
             }
         }
     }
@@ -574,3 +659,39 @@ impl PlaylistManager {
         self.current_index = if original.is_empty() { None } else { Some(0) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 当前曲目为列表最后一首
+    fn at_last_track(len: usize, play_mode: PlayMode) -> PlaylistManager {
+        let mut playlist = Playlist::new();
+        playlist.tracks = (0..len)
+            .map(|i| {
+                let mut track = Track::new();
+                track.file_path = format!("{i}.flac");
+                track
+            })
+            .collect();
+        let mut manager = PlaylistManager::new(playlist);
+        manager.play_mode = play_mode;
+        manager.current_index = Some(len - 1);
+        manager
+    }
+
+    #[test]
+    fn repeat_does_not_pre_queue_the_current_track() {
+        // 列表循环在末尾预排第一首
+        assert_eq!(at_last_track(3, PlayMode::Repeat).peek_next_index(), Some(0));
+
+        // 只有一首时不把自身预排为下一首，否则同一曲目会被打开两次；播完后由 TrackEnded 重新播放
+        let mut single = at_last_track(1, PlayMode::Repeat);
+        assert_eq!(single.peek_next_index(), None);
+        assert_eq!(single.next_track().map(|t| t.file_path.as_str()), Some("0.flac"));
+        assert_eq!(at_last_track(1, PlayMode::Random).peek_next_index(), None);
+
+        // 单曲循环仍预排自身，无缝重复
+        assert_eq!(at_last_track(1, PlayMode::Single).peek_next_index(), Some(0));
+    }
+}
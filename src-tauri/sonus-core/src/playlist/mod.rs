@@ -0,0 +1,3 @@
+pub mod manager;
+mod persistence;
+pub mod play_mode;
\ No newline at end of file
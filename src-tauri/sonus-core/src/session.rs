@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use crate::database::{connection, get_config_value, set_config_value};
+use crate::playlist::play_mode::PlayMode;
+
+/// 上次退出时的播放会话，序列化后存入 config 表的 session 键
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub track_ids: Vec<usize>,          // 当前播放列表顺序
+    pub original_track_ids: Vec<usize>, // 随机模式前的原始顺序
+    pub current_index: Option<usize>,
+    pub play_mode: PlayMode,
+    pub position_ms: u64,
+    pub volume: f32,
+}
+
+impl Session {
+    pub fn load() -> Option<Self> {
+        let value = get_config_value(&connection(), "session").ok()?.value;
+        if value.is_empty() {
+            return None;
+        }
+        serde_json::from_str(&value)
+            .map_err(|e| tracing::warn!("Failed to parse saved session: {}", e))
+            .ok()
+    }
+
+    pub fn save(&self) {
+        match serde_json::to_string(self) {
+            Ok(value) => {
+                if let Err(e) = set_config_value(&connection(), "session", &value) {
+                    tracing::warn!("Failed to save session: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to serialize session: {}", e),
+        }
+    }
+}
+
+/// memory_play：恢复时是否从上次的位置继续
+pub fn memory_play_enabled() -> bool {
+    config_flag("memory_play")
+}
+
+/// start_play：启动后是否自动播放
+pub fn start_play_enabled() -> bool {
+    config_flag("start_play")
+}
+
+fn config_flag(key: &str) -> bool {
+    get_config_value(&connection(), key).is_ok_and(|c| c.value.trim() == "1")
+}
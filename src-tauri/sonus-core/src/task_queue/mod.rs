@@ -1,8 +1,7 @@
 pub mod task;
 pub mod tracker;
 pub mod queue;
-pub mod tauri_integration;
 
-pub use task::{Task, TaskType, TaskResult, TaskData};
+pub use task::{Task, TaskType, TaskResult, TaskData, FileMetadata};
 pub use queue::{TaskQueue, TaskQueueHandle};
 pub use tracker::{TaskStatus, TaskEvent, TaskStats, TaskTracker};
\ No newline at end of file
@@ -5,6 +5,7 @@ use std::sync::Arc;
 use tokio::sync::{mpsc, Semaphore};
 use super::task::{Task, TaskContext, TaskResult};
 use super::tracker::TaskTracker;
+use crate::library::writer::LibraryWriter;
 use tracing::info;
 
 /// 任务队列句柄，用于向队列提交新任务
@@ -19,12 +20,18 @@ impl TaskQueueHandle {
         // 忽略发送错误（通常是队列已关闭）
         let _ = self.sender.send(task).await;
     }
+
+    /// 在异步运行时之外（如文件监视线程）提交任务，队列已满时阻塞等待
+    pub fn blocking_submit_task(&self, task: Box<dyn Task>) {
+        let _ = self.sender.blocking_send(task);
+    }
 }
 
 /// 任务队列管理器
 pub struct TaskQueue {
     receiver: mpsc::Receiver<Box<dyn Task>>,
     tracker: TaskTracker,
+    writer: LibraryWriter,
     max_concurrent_tasks: usize,
     sender: mpsc::Sender<Box<dyn Task>>,
 }
@@ -35,9 +42,13 @@ impl TaskQueue {
         // 创建通道，缓冲区大小为1000
         let (sender, receiver) = mpsc::channel(1000);
 
+        // 所有任务共用一个曲库写入线程，曲目得以跨任务合并到同一事务
+        let writer = LibraryWriter::new(tracker.events().clone());
+
         let queue = Self {
             receiver,
             tracker,
+            writer,
             max_concurrent_tasks,
             sender: sender.clone(),
         };
@@ -64,7 +75,7 @@ impl TaskQueue {
 
             // 创建任务上下文
             let queue_handle = self.create_handle();
-            let context = TaskContext::new(queue_handle, tracker.clone());
+            let context = TaskContext::new(queue_handle, tracker.clone(), self.writer.clone());
             info!("任务上下文已创建");
 
             // 获取信号量许可，控制并发
@@ -2,15 +2,18 @@ use std::fmt;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
-use crate::core::task_queue::TaskStatus;
+use crate::library::chapters::Chapter;
+use crate::library::writer::LibraryWriter;
+use crate::task_queue::TaskStatus;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TaskType {
     DirectoryScan,
     MetadataExtraction,
     ExtensionCheck,
-    SqlGeneration,
-    SqlExecution
+    CueSheet,
+    IntegrityCheck,
+    FileRemoval
 }
 
 impl fmt::Display for TaskType {
@@ -19,8 +22,9 @@ impl fmt::Display for TaskType {
             TaskType::DirectoryScan => {write!(f, "DirectoryScan")}
             TaskType::MetadataExtraction => {write!(f, "MetadataExtraction")}
             TaskType::ExtensionCheck => {write!(f, "ExtensionCheck")}
-            TaskType::SqlGeneration => {write!(f, "SqlGeneration")}
-            TaskType::SqlExecution => {write!(f, "SqlExecution")}
+            TaskType::CueSheet => {write!(f, "CueSheet")}
+            TaskType::IntegrityCheck => {write!(f, "IntegrityCheck")}
+            TaskType::FileRemoval => {write!(f, "FileRemoval")}
         }
     }
 }
@@ -31,36 +35,49 @@ pub enum TaskData {
     String(String),
     Path(String),
     PathList(Vec<String>),
-    FileMetadata {
-        title: Option<String>,
-        album: Option<String>,
-        artist: Option<Vec<String>>,
-        album_artist: Option<String>,
-        composer: Option<Vec<String>>,
-        lyricist: Option<Vec<String>>,
-        genre: Option<Vec<String>>,
-        release_date: Option<DateTime<Utc>>,
-        track_number: Option<u16>,
-        disc_number: Option<u16>,
-        disc_total: Option<u16>,
-        bpm: Option<u16>,
-        duration: u32,
-        cover_art: Option<Vec<String>>,
-        audio_format: Option<String>,
-        audio_size: u64,
-        bitrate: Option<u32>,
-        sample_rate: Option<u32>,
-        file_path: String,
-        create_time: Option<DateTime<Utc>>,
-        update_time: Option<DateTime<Utc>>,
-        copyright: Option<String>,
-        remark: Option<String>,
-        path_type: u8,
-        is_love: u8,
-        lyrics: Option<String>,
-        hash: String
-    },
-    SqlQuery(String),
+    FileMetadata(Box<FileMetadata>),
+}
+
+/// 单个音频文件（或 CUE 拆分出的一轨）的元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub artist: Option<Vec<String>>,
+    pub album_artist: Option<String>,
+    pub composer: Option<Vec<String>>,
+    pub lyricist: Option<Vec<String>>,
+    pub genre: Option<Vec<String>>,
+    pub release_date: Option<DateTime<Utc>>,
+    pub track_number: Option<u16>,
+    pub disc_number: Option<u16>,
+    pub disc_total: Option<u16>,
+    pub bpm: Option<u16>,
+    pub duration: u32,
+    pub cover_art: Option<Vec<String>>,
+    pub audio_format: Option<String>,
+    pub audio_size: u64,
+    pub bitrate: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub file_path: String,
+    pub create_time: Option<DateTime<Utc>>,
+    pub update_time: Option<DateTime<Utc>>,
+    pub copyright: Option<String>,
+    pub remark: Option<String>,
+    pub path_type: u8,
+    pub is_love: u8,
+    pub lyrics: Option<String>,
+    pub hash: String,
+    pub replaygain_track_gain: Option<f32>,
+    pub replaygain_track_peak: Option<f32>,
+    pub replaygain_album_gain: Option<f32>,
+    pub replaygain_album_peak: Option<f32>,
+    pub start_offset: Option<u64>,
+    pub end_offset: Option<u64>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+    #[serde(default)]
+    pub mtime: Option<i64>,
 }
 
 #[derive(Debug, Clone)]
@@ -74,13 +91,15 @@ pub enum TaskResult {
 pub struct TaskContext {
     queue_handle: super::queue::TaskQueueHandle,
     tracker: super::tracker::TaskTracker,
+    writer: LibraryWriter,
 }
 
 impl TaskContext {
-    pub fn new(queue_handle: super::queue::TaskQueueHandle, tracker: super::tracker::TaskTracker) -> Self {
+    pub fn new(queue_handle: super::queue::TaskQueueHandle, tracker: super::tracker::TaskTracker, writer: LibraryWriter) -> Self {
         Self {
             queue_handle,
             tracker,
+            writer,
         }
     }
 
@@ -91,6 +110,15 @@ impl TaskContext {
     pub fn tracker(&self) -> &super::tracker::TaskTracker {
         &self.tracker
     }
+
+    pub fn events(&self) -> &crate::events::EventBus {
+        self.tracker.events()
+    }
+
+    /// 扫描得到的曲目经此批量写入曲库
+    pub fn writer(&self) -> &LibraryWriter {
+        &self.writer
+    }
 }
 
 pub trait Task: Send + Sync + 'static {
@@ -2,9 +2,10 @@
 //! 提供任务统计信息和事件通知
 
 use std::sync::Arc;
-use tokio::sync::{Mutex, mpsc};
+use tokio::sync::Mutex;
 use serde::{Serialize, Deserialize};
 use super::task::{TaskType, Task};
+use crate::events::EventBus;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::info;
 
@@ -28,7 +29,7 @@ impl fmt::Display for TaskStatus {
     }
 }
 
-/// 任务事件，用于通知前端任务状态变化
+/// 任务事件，通过事件总线通知订阅者任务状态变化
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskEvent {
     pub task_id: String,
@@ -58,17 +59,17 @@ pub struct TaskTracker {
 struct TaskTrackerInner {
     tasks: Mutex<Vec<Box<dyn Task>>>,
     stats: Mutex<TaskStats>,
-    event_sender: mpsc::Sender<TaskEvent>,
+    events: EventBus,
 }
 
 impl TaskTracker {
     /// 创建新的任务跟踪器
-    pub fn new(event_sender: mpsc::Sender<TaskEvent>) -> Self {
+    pub fn new(events: EventBus) -> Self {
         Self {
             inner: Arc::new(TaskTrackerInner {
                 tasks: Mutex::new(Vec::new()),
                 stats: Mutex::new(TaskStats::default()),
-                event_sender,
+                events,
             }),
         }
     }
@@ -139,8 +140,12 @@ impl TaskTracker {
             timestamp: Self::current_timestamp(),
         };
 
-        // 发送事件，忽略发送错误
-        let _ = self.inner.event_sender.send(event).await;
+        self.inner.events.publish(event);
+    }
+
+    /// 任务事件所在的事件总线，任务执行中也可借此发布曲库事件
+    pub fn events(&self) -> &EventBus {
+        &self.inner.events
     }
 
     /// 获取当前任务统计信息
@@ -7,10 +7,11 @@
  */
 use rusqlite::{Connection, Result};
 
-use super::database::{
+use sonus_core::database::{
     Config,
     connection,
-    get_config_value
+    get_config_value,
+    init_schema
 };
 use super::window;
 
@@ -20,71 +21,7 @@ fn init_db() -> Result<Connection> {
     {
         let conn = connection();
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS config (
-            key TEXT PRIMARY KEY NOT NULL,
-            value TEXT
-        )",
-            (),
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS music (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title TEXT,
-            album TEXT,
-            artist TEXT,
-            album_artist TEXT,
-            composer TEXT,
-            lyricist TEXT,
-            genre TEXT,
-            release_date TIMESTAMP,
-            track_number INTEGER,
-            disc_number INTEGER,
-            disc_total INTEGER,
-            bpm INTEGER,
-            duration INTEGER,
-            cover_art TEXT,
-            audio_format TEXT,
-            audio_size INTEGER,
-            bitrate INTEGER,
-            sample_rate INTEGER,
-            path_type TEXT,
-            file_path INTEGER,
-            create_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            update_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            copyright TEXT,
-            remark TEXT,
-            is_love INTEGER,
-            lyrics TEXT,
-            hash TEXT
-        )",
-            (),
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS playlist (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT,
-            create_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            update_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            remark TEXT
-        )",
-            (),
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS playlist_music (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            playlist_id INTEGER,
-            music_id INTEGER,
-            create_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            update_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            remark TEXT
-        )",
-            (),
-        )?;
-
+        init_schema(&conn)?;
         init_config(&conn)?;
 
         Ok(conn)
@@ -112,10 +49,18 @@ fn init_config(conn: &Connection) -> Result<()> {
         "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
         ("memory_play", "0"),
     )?; // Memory lat play location
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("session", ""),
+    )?; // Last Play Session (JSON)
     conn.execute(
         "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
         ("fade_in_out", "0"),
     )?; // Fade In Out
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("crossfade", "0"),
+    )?; // Crossfade Seconds
     conn.execute(
         "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
         ("loudness_balance", "0"),
@@ -124,6 +69,54 @@ fn init_config(conn: &Connection) -> Result<()> {
         "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
         ("audio_enhancement", "0"),
     )?; // Audio Enhancement
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("output_device", ""),
+    )?; // Output Device (empty = system default)
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("eq_preamp", "0"),
+    )?; // Equalizer Preamp
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("eq_bands", ""),
+    )?; // Equalizer Bands (JSON)
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("eq_preset", "Flat"),
+    )?; // Equalizer Preset
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("eq_user_presets", "[]"),
+    )?; // Equalizer User Presets (JSON)
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("balance", "0"),
+    )?; // Channel Balance (-1 left .. 1 right)
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("mono_downmix", "0"),
+    )?; // Mono Downmix
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("crossfeed", "0"),
+    )?; // Headphone Crossfeed (0 off, 1 default, 2 Chu Moy, 3 Jan Meier)
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("resume_min_duration", "20"),
+    )?; // Remember Position For Tracks Longer Than (minutes, 0 = off)
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("resume_genres", r#"["Audiobook","Podcast"]"#),
+    )?; // Remember Position For Genres (JSON)
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("resume_folders", "[]"),
+    )?; // Remember Position For Folders (JSON)
+    conn.execute(
+        "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
+        ("library_folders", "[]"),
+    )?; // Library Folders, watched for changes (JSON)
     conn.execute(
         "INSERT OR IGNORE INTO config (key, value) VALUES (?, ?)",
         ("dynamic_backdrop", "0"),
@@ -1,6 +1,6 @@
 pub mod init;
 
-pub mod database;
+pub mod task_queue;
 
 pub mod window;
 pub use window::{
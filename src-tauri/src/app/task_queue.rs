@@ -0,0 +1,35 @@
+//! 创建任务队列并交给 Tauri 管理，任务事件经事件总线发往前端；
+//! 曲库文件夹的变化由文件监视器转为任务提交到队列
+
+use tauri::Manager;
+use sonus_core::events::EventBus;
+use sonus_core::library::watcher::{library_folders, LibraryWatcher};
+use sonus_core::task_queue::{TaskQueue, TaskTracker};
+use tracing::{info, warn};
+
+pub fn init_task_queue<M: Manager<tauri::Wry>>(app: &M, events: EventBus) {
+    // 创建任务跟踪器
+    let tracker = TaskTracker::new(events);
+
+    // 创建任务队列
+    let (mut task_queue, queue_handle) = TaskQueue::new(10, tracker.clone());
+
+    // 监视已加入曲库的文件夹
+    let watcher = LibraryWatcher::new(queue_handle.clone());
+    for folder in library_folders() {
+        if let Err(e) = watcher.watch(&folder) {
+            warn!("无法监视曲库文件夹: {}, 错误: {}", folder, e);
+        }
+    }
+
+    // 存储状态
+    app.manage(watcher);
+    app.manage(queue_handle);
+    app.manage(tracker);
+
+    // 队列后台执行
+    tauri::async_runtime::spawn(async move {
+        task_queue.run().await;
+        info!("任务队列已退出");
+    });
+}
@@ -14,7 +14,7 @@ use windows::Win32::Graphics::Dwm::{
     DwmGetWindowAttribute,
     DwmSetWindowAttribute
 };
-use super::database::{
+use sonus_core::database::{
     set_config_value,
     connection
 };
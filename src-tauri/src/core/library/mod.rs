@@ -1,3 +0,0 @@
-pub mod scanner;
-pub mod index;
-pub mod search;
\ No newline at end of file
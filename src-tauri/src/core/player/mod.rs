@@ -1,2 +0,0 @@
-pub(crate) mod audio_backend;
-pub(crate) mod state;
\ No newline at end of file
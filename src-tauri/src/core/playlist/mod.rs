@@ -1,3 +0,0 @@
-pub(crate) mod manager;
-mod persistence;
-pub(crate) mod play_mode;
\ No newline at end of file
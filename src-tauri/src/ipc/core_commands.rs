@@ -3,11 +3,21 @@ use std::sync::MutexGuard;
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use tauri::State;
-use crate::core::controller::{PlayMode, PlayerController, SharedPlayerController};
-use crate::core::library::index::Track;
-use crate::core::player::state::{PlaybackState};
-use crate::core::playlist::manager::Playlist;
-use crate::core::playlist::play_mode;
+use sonus_core::controller::{PlayMode, PlayerController, SharedPlayerController};
+use sonus_core::library::chapters::{self, Chapter};
+use sonus_core::library::index::Track;
+use sonus_core::player::ab_loop::{self, SavedLoop};
+use sonus_core::player::equalizer::{EqBand, EqPreset, EqSettings};
+use sonus_core::player::output_device::{list_output_devices, OutputDeviceInfo};
+use sonus_core::player::remote;
+use sonus_core::player::replay_gain::ReplayGainMode;
+use sonus_core::player::resume::{self, ResumePosition, ResumeRules};
+use sonus_core::player::sleep_timer::{SleepTimerMode, SleepTimerStatus};
+use sonus_core::player::spectrum::SpectrumSettings;
+use sonus_core::player::state::{PlaybackState};
+use sonus_core::player::stereo::{Crossfeed, StereoSettings};
+use sonus_core::playlist::manager::Playlist;
+use sonus_core::playlist::play_mode;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct DurationMs(pub u64);
@@ -55,6 +65,13 @@ pub struct PlayerStatus {
     pub play_mode: play_mode::PlayMode,
 }
 
+// 淡入淡出设置
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FadeSettings {
+    pub fade_in_out: DurationMs,
+    pub crossfade: DurationMs,
+}
+
 fn get_controller_lock(controller: &SharedPlayerController) -> MutexGuard<PlayerController> {
     controller
         .lock()
@@ -135,6 +152,311 @@ pub fn seek(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn set_fade_in_out(controller: State<SharedPlayerController>, duration: DurationMs) {
+    tracing::info!("set_fade_in_out called: {}ms", duration.0);
+    let mut controller = get_controller_lock(&controller);
+    controller.set_fade_duration(duration.into());
+}
+
+#[tauri::command]
+pub fn set_crossfade(controller: State<SharedPlayerController>, seconds: f32) -> Result<(), String> {
+    tracing::info!("set_crossfade called: {}s", seconds);
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!("Invalid crossfade duration: {}", seconds));
+    }
+    let mut controller = get_controller_lock(&controller);
+    controller.set_crossfade(Duration::from_secs_f32(seconds));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_fade_settings(controller: State<SharedPlayerController>) -> FadeSettings {
+    let controller = get_controller_lock(&controller);
+    let (fade_in_out, crossfade) = controller.fade_settings();
+    FadeSettings {
+        fade_in_out: fade_in_out.into(),
+        crossfade: crossfade.into(),
+    }
+}
+
+#[tauri::command]
+pub fn set_loudness_balance(controller: State<SharedPlayerController>, mode: ReplayGainMode) {
+    tracing::info!("set_loudness_balance called: {:?}", mode);
+    let mut controller = get_controller_lock(&controller);
+    controller.set_replay_gain_mode(mode);
+}
+
+#[tauri::command]
+pub fn get_loudness_balance(controller: State<SharedPlayerController>) -> ReplayGainMode {
+    let controller = get_controller_lock(&controller);
+    controller.replay_gain_mode()
+}
+
+#[tauri::command]
+pub fn set_playback_rate(controller: State<SharedPlayerController>, rate: f32) -> Result<(), String> {
+    tracing::info!("set_playback_rate called: {}", rate);
+    let mut controller = get_controller_lock(&controller);
+    controller.set_playback_rate(rate).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_preserve_pitch(controller: State<SharedPlayerController>, enabled: bool) {
+    let mut controller = get_controller_lock(&controller);
+    controller.set_preserve_pitch(enabled);
+}
+
+#[tauri::command]
+pub fn set_sleep_timer(controller: State<SharedPlayerController>, mode: SleepTimerMode) -> Result<(), String> {
+    tracing::info!("set_sleep_timer called: {:?}", mode);
+    let mut controller = get_controller_lock(&controller);
+    controller.set_sleep_timer(mode).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn extend_sleep_timer(controller: State<SharedPlayerController>, minutes: u32) -> Result<(), String> {
+    tracing::info!("extend_sleep_timer called: {}", minutes);
+    let mut controller = get_controller_lock(&controller);
+    controller.extend_sleep_timer(minutes).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cancel_sleep_timer(controller: State<SharedPlayerController>) {
+    tracing::info!("cancel_sleep_timer called");
+    let mut controller = get_controller_lock(&controller);
+    controller.cancel_sleep_timer();
+}
+
+#[tauri::command]
+pub fn get_sleep_timer(controller: State<SharedPlayerController>) -> SleepTimerStatus {
+    let controller = get_controller_lock(&controller);
+    controller.sleep_timer_status()
+}
+
+#[tauri::command]
+pub fn set_ab_loop(controller: State<SharedPlayerController>, start: DurationMs, end: DurationMs) -> Result<(), String> {
+    tracing::info!("set_ab_loop called: {}ms - {}ms", start.0, end.0);
+    let mut controller = get_controller_lock(&controller);
+    controller.set_ab_loop(start.into(), end.into()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_ab_loop(controller: State<SharedPlayerController>) {
+    tracing::info!("clear_ab_loop called");
+    let mut controller = get_controller_lock(&controller);
+    controller.clear_ab_loop();
+}
+
+#[tauri::command]
+pub fn save_ab_loop(controller: State<SharedPlayerController>, name: String) -> Result<SavedLoop, String> {
+    tracing::info!("save_ab_loop called: {}", name);
+    let mut controller = get_controller_lock(&controller);
+    controller.save_ab_loop(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_saved_loops(track_id: usize) -> Result<Vec<SavedLoop>, String> {
+    ab_loop::get_loops(track_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn apply_saved_loop(controller: State<SharedPlayerController>, id: i64) -> Result<(), String> {
+    tracing::info!("apply_saved_loop called: {}", id);
+    let mut controller = get_controller_lock(&controller);
+    controller.apply_saved_loop(id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_saved_loop(id: i64) -> Result<(), String> {
+    tracing::info!("delete_saved_loop called: {}", id);
+    ab_loop::delete_loop(id).map_err(|e| e.to_string())
+}
+
+/// track_id 为空时返回正在播放曲目的章节
+#[tauri::command]
+pub fn get_chapters(controller: State<SharedPlayerController>, track_id: Option<usize>) -> Result<Vec<Chapter>, String> {
+    match track_id {
+        Some(id) => chapters::get_chapters(id).map_err(|e| e.to_string()),
+        None => Ok(get_controller_lock(&controller).chapters()),
+    }
+}
+
+#[tauri::command]
+pub fn next_chapter(controller: State<SharedPlayerController>) -> Result<Chapter, String> {
+    tracing::info!("next_chapter called");
+    let mut controller = get_controller_lock(&controller);
+    controller.next_chapter().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn previous_chapter(controller: State<SharedPlayerController>) -> Result<Chapter, String> {
+    tracing::info!("previous_chapter called");
+    let mut controller = get_controller_lock(&controller);
+    controller.previous_chapter().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn seek_to_chapter(controller: State<SharedPlayerController>, index: usize) -> Result<Chapter, String> {
+    tracing::info!("seek_to_chapter called: {}", index);
+    let mut controller = get_controller_lock(&controller);
+    controller.seek_to_chapter(index).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_resume_positions() -> Result<Vec<ResumePosition>, String> {
+    resume::get_positions().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_resume_position(track_id: usize) -> Result<(), String> {
+    tracing::info!("clear_resume_position called: {}", track_id);
+    resume::clear_position(track_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_resume_positions() -> Result<(), String> {
+    tracing::info!("clear_resume_positions called");
+    resume::clear_positions().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn jump_to_resume_position(controller: State<SharedPlayerController>, track_id: usize) -> Result<Option<Track>, String> {
+    tracing::info!("jump_to_resume_position called: {}", track_id);
+    let mut controller = get_controller_lock(&controller);
+    controller.jump_to_resume_position(track_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_resume_rules(controller: State<SharedPlayerController>) -> ResumeRules {
+    let controller = get_controller_lock(&controller);
+    controller.resume_rules()
+}
+
+#[tauri::command]
+pub fn set_resume_rules(controller: State<SharedPlayerController>, rules: ResumeRules) -> Result<(), String> {
+    tracing::info!("set_resume_rules called: {:?}", rules);
+    let mut controller = get_controller_lock(&controller);
+    controller.set_resume_rules(rules).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_output_devices() -> Vec<OutputDeviceInfo> {
+    list_output_devices()
+}
+
+#[tauri::command]
+pub fn get_output_device(controller: State<SharedPlayerController>) -> Option<String> {
+    let controller = get_controller_lock(&controller);
+    controller.output_device()
+}
+
+#[tauri::command]
+pub fn set_output_device(controller: State<SharedPlayerController>, name: Option<String>) -> Result<(), String> {
+    tracing::info!("set_output_device called: {:?}", name);
+    let mut controller = get_controller_lock(&controller);
+    controller
+        .set_output_device(name.filter(|n| !n.is_empty()))
+        .map_err(|e| {
+            tracing::error!("set_output_device error: {}", e);
+            e.to_string()
+        })
+}
+
+#[tauri::command]
+pub fn get_equalizer(controller: State<SharedPlayerController>) -> EqSettings {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().settings()
+}
+
+#[tauri::command]
+pub fn set_equalizer_enabled(controller: State<SharedPlayerController>, enabled: bool) {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().set_enabled(enabled);
+}
+
+#[tauri::command]
+pub fn set_equalizer_preamp(controller: State<SharedPlayerController>, preamp: f32) {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().set_preamp(preamp);
+}
+
+#[tauri::command]
+pub fn set_equalizer_band(controller: State<SharedPlayerController>, index: usize, gain: f32) -> Result<(), String> {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().set_band_gain(index, gain).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_equalizer_bands(controller: State<SharedPlayerController>, bands: Vec<EqBand>) -> Result<(), String> {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().set_bands(bands).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_equalizer_presets(controller: State<SharedPlayerController>) -> Vec<EqPreset> {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().presets()
+}
+
+#[tauri::command]
+pub fn apply_equalizer_preset(controller: State<SharedPlayerController>, name: String) -> Result<(), String> {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().apply_preset(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_equalizer_preset(controller: State<SharedPlayerController>, name: String) -> Result<(), String> {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().save_preset(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_equalizer_preset(controller: State<SharedPlayerController>, name: String) -> Result<(), String> {
+    let controller = get_controller_lock(&controller);
+    controller.equalizer().delete_preset(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_stereo_image(controller: State<SharedPlayerController>) -> StereoSettings {
+    let controller = get_controller_lock(&controller);
+    controller.stereo_image()
+}
+
+#[tauri::command]
+pub fn set_balance(controller: State<SharedPlayerController>, balance: f32) -> Result<(), String> {
+    let mut controller = get_controller_lock(&controller);
+    controller.set_balance(balance).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_mono_downmix(controller: State<SharedPlayerController>, enabled: bool) {
+    let mut controller = get_controller_lock(&controller);
+    controller.set_mono(enabled);
+}
+
+#[tauri::command]
+pub fn set_crossfeed(controller: State<SharedPlayerController>, crossfeed: Crossfeed) {
+    let mut controller = get_controller_lock(&controller);
+    controller.set_crossfeed(crossfeed);
+}
+
+/// 开启时按 rate（次/秒）发送 audio-spectrum 事件，bands 为频段数；关闭后不再做任何分析
+#[tauri::command]
+pub fn set_audio_spectrum(controller: State<SharedPlayerController>, enabled: bool, rate: Option<u32>, bands: Option<u32>) {
+    let spectrum = get_controller_lock(&controller).spectrum();
+    if enabled {
+        spectrum.enable(rate, bands);
+    } else {
+        spectrum.disable();
+    }
+}
+
+#[tauri::command]
+pub fn get_audio_spectrum(controller: State<SharedPlayerController>) -> SpectrumSettings {
+    let controller = get_controller_lock(&controller);
+    controller.spectrum().settings()
+}
+
 #[tauri::command]
 pub fn get_player_status(controller: State<SharedPlayerController>) -> PlayerStatus {
     let controller = get_controller_lock(&controller); 
@@ -162,14 +484,7 @@ pub fn shutdown_player(controller: State<SharedPlayerController>) {
 pub fn next_track(controller: State<SharedPlayerController>) -> Result<Option<Track>, String> {
     tracing::info!("next_track called");
     let mut controller = get_controller_lock(&controller);
-
-    let next = controller.playlist_manager.next_track().cloned();
-
-    if next.is_some() {
-        controller.play();
-    }
-
-    Ok(next)
+    Ok(controller.play_next())
 }
 
 #[tauri::command]
@@ -196,6 +511,7 @@ pub fn insert_track_at(
     tracing::info!("insert_track_at called: position {}", position);
     let mut controller = get_controller_lock(&controller);
     controller.playlist_manager.insert_at(position, track)?;
+    controller.requeue_next();
     Ok(controller.playlist_manager.get_playlist().clone())
 }
 
@@ -207,6 +523,7 @@ pub fn insert_track_after_current(
     tracing::info!("insert_track_after_current called");
     let mut controller = get_controller_lock(&controller);
     controller.playlist_manager.insert_track_to_current_next(track)?;
+    controller.requeue_next();
     Ok(controller.playlist_manager.get_playlist().clone())
 }
 
@@ -218,6 +535,23 @@ pub fn add_track_to_end(
     tracing::info!("add_track_to_end called");
     let mut controller = get_controller_lock(&controller);
     controller.playlist_manager.insert_track_to_end(track)?;
+    controller.requeue_next();
+    Ok(controller.playlist_manager.get_playlist().clone())
+}
+
+/// 把 HTTP(S) / WebDAV 地址作为远程曲目添加到播放列表末尾
+#[tauri::command]
+pub fn add_url_to_playlist(
+    controller: State<SharedPlayerController>,
+    url: String
+) -> Result<Playlist, String> {
+    tracing::info!("add_url_to_playlist called: {}", url);
+    if !remote::is_remote(&url) {
+        return Err(format!("Not an http(s) or WebDAV url: {}", url));
+    }
+    let mut controller = get_controller_lock(&controller);
+    controller.playlist_manager.insert_track_to_end(Track::from_url(&url))?;
+    controller.requeue_next();
     Ok(controller.playlist_manager.get_playlist().clone())
 }
 
@@ -230,6 +564,7 @@ pub fn move_track(
     tracing::info!("move_track called: from {} to {}", from_index, to_position);
     let mut controller = get_controller_lock(&controller);
     controller.playlist_manager.insert_at_by_index(from_index, to_position)?;
+    controller.requeue_next();
     Ok(controller.playlist_manager.get_playlist().clone())
 }
 
@@ -241,6 +576,7 @@ pub fn remove_track(
     tracing::info!("remove_track called: position {}", position);
     let mut controller = get_controller_lock(&controller);
     controller.playlist_manager.remove_at(position)?;
+    controller.requeue_next();
     Ok(controller.playlist_manager.get_playlist().clone())
 }
 
@@ -251,6 +587,7 @@ pub fn clear_playlist(
     tracing::info!("clear_playlist called");
     let mut controller = get_controller_lock(&controller);
     controller.playlist_manager.clear();
+    controller.requeue_next();
     Ok(controller.playlist_manager.get_playlist().clone())
 }
 
@@ -262,6 +599,7 @@ pub fn overwrite_playlist(
     tracing::info!("overwrite_playlist called: {}", playlist.name);
     let mut controller = get_controller_lock(&controller);
     controller.playlist_manager.overwrite_playlist(&playlist);
+    controller.requeue_next();
     Ok(controller.playlist_manager.get_playlist().clone())
 }
 
@@ -273,6 +611,7 @@ pub fn set_play_mode(
     tracing::info!("set_play_mode called: {:?}", mode);
     let mut controller = get_controller_lock(&controller);
     controller.playlist_manager.set_play_mode(mode.clone());
+    controller.requeue_next();
     Ok(controller.playlist_manager.play_mode.clone())
 }
 
@@ -0,0 +1,51 @@
+//! 把核心库事件总线上的事件转发给前端，事件名与负载保持不变
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast::error::RecvError;
+use sonus_core::events::{Event, EventBus, LibraryEvent, PlayerEvent};
+
+/// 订阅事件总线，在后台把每个事件以对应的名称发给前端
+pub fn forward_events(app_handle: AppHandle, events: &EventBus) {
+    let mut receiver = events.subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => forward(&app_handle, event),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("event forwarding lagged, {} events skipped", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+fn forward(app_handle: &AppHandle, event: Event) {
+    match event {
+        Event::Player(event) => match event {
+            PlayerEvent::StateUpdated(snapshot) => emit(app_handle, "player-state-updated", *snapshot),
+            PlayerEvent::TrackChanged(changed) => emit(app_handle, "track-changed", changed),
+            PlayerEvent::TrackEnded => emit(app_handle, "track-ended", ()),
+            PlayerEvent::SleepTimerUpdated(status) => emit(app_handle, "sleep-timer-updated", status),
+            PlayerEvent::Spectrum(frame) => emit(app_handle, "audio-spectrum", *frame),
+            PlayerEvent::OutputDeviceChanged(changed) => emit(app_handle, "output-device-changed", changed),
+            PlayerEvent::OutputDeviceLost => emit(app_handle, "output-device-lost", ()),
+            PlayerEvent::PlaybackError(error) => emit(app_handle, "playback-error", error),
+        },
+        Event::Library(event) => match event {
+            LibraryEvent::TrackIndexed { path } => emit(app_handle, "library-track-indexed", path),
+            LibraryEvent::IntegrityChecked(report) => emit(app_handle, "library-integrity-checked", report),
+            LibraryEvent::ScanFinished(report) => emit(app_handle, "library-scan-finished", report),
+            LibraryEvent::TrackMissing { path } => emit(app_handle, "library-track-missing", path),
+        },
+        Event::Task(event) => app_handle
+            .emit_to("main", "task-event", event)
+            .unwrap_or_else(|e| tracing::error!("发送任务事件失败: {}", e)),
+    }
+}
+
+fn emit<S: Serialize + Clone>(app_handle: &AppHandle, name: &str, payload: S) {
+    app_handle.emit(name, payload)
+        .unwrap_or_else(|e| tracing::error!("emit {} failed: {}", name, e));
+}
@@ -1,8 +1,36 @@
-use crate::core::library;
-use crate::core::library::index::Track;
+use sonus_core::library;
+use sonus_core::library::index::Track;
+use sonus_core::library::integrity::{self, IntegrityResult};
+use sonus_core::library::watcher::LibraryWatcher;
+use tauri::State;
 
 #[tauri::command]
 pub async fn get_all_songs(limit: usize, offset: usize) -> Result<Vec<Track>, String> {
     let tracks = library::index::get_all_songs(limit, offset).expect("Failed to get all songs");
     Ok(tracks)
 }
+
+/// problems_only 时只返回校验未通过的曲目
+#[tauri::command]
+pub fn get_integrity_results(problems_only: bool) -> Result<Vec<IntegrityResult>, String> {
+    integrity::get_results(problems_only).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_integrity_result(track_id: usize) -> Result<Option<IntegrityResult>, String> {
+    integrity::get_result(track_id).map_err(|e| e.to_string())
+}
+
+/// 已加入曲库并被监视的文件夹
+#[tauri::command]
+pub fn get_library_folders() -> Vec<String> {
+    library::watcher::library_folders()
+}
+
+/// 移出曲库文件夹并停止监视，已索引的曲目保留
+#[tauri::command]
+pub fn remove_library_folder(path: String, watcher: State<'_, LibraryWatcher>) -> Result<bool, String> {
+    let removed = library::watcher::remove_library_folder(&path).map_err(|e| e.to_string())?;
+    watcher.unwatch(&path).map_err(|e| e.to_string())?;
+    Ok(removed)
+}
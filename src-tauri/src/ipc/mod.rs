@@ -1,3 +1,4 @@
+pub mod events;
 pub mod window_commands;
 pub use window_commands::*;
 
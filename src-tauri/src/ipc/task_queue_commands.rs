@@ -1,17 +1,25 @@
 use tauri::{State, Window};
-use crate::core::task_queue::{TaskQueueHandle, TaskStats, TaskTracker};
-use tracing::info;
+use sonus_core::library::watcher::{add_library_folder, LibraryWatcher};
+use sonus_core::task_queue::{TaskQueueHandle, TaskStats, TaskTracker};
+use tracing::{info, warn};
 
-/// Tauri命令：开始扫描目录
+/// Tauri命令：开始扫描目录，并把目录加入曲库文件夹持续监视
 #[tauri::command]
 pub async fn start_directory_scan(
     path: String,
-    queue_handle: State<'_, TaskQueueHandle>
+    queue_handle: State<'_, TaskQueueHandle>,
+    watcher: State<'_, LibraryWatcher>
 ) -> Result<(), String> {
+    if std::path::Path::new(&path).is_dir() && add_library_folder(&path).map_err(|e| e.to_string())? {
+        if let Err(e) = watcher.watch(&path) {
+            warn!("无法监视曲库文件夹: {}, 错误: {}", path, e);
+        }
+    }
+
     // 创建目录扫描任务
     info!("进入 start_directory_scan 方法体");
     info!("准备创建 DirectoryScanTask，path: {}", path);
-    let scan_task = Box::new(super::super::core::library::scanner::DirectoryScanTask::new(path));
+    let scan_task = Box::new(sonus_core::library::scanner::DirectoryScanTask::new(path));
     info!("DirectoryScanTask 创建成功");
     info!("准备提交任务到队列");
     // 提交任务到队列
@@ -21,6 +29,18 @@ pub async fn start_directory_scan(
     Ok(())
 }
 
+/// Tauri命令：校验曲库中所有本地文件的完整性，返回加入队列的文件数
+#[tauri::command]
+pub async fn start_library_verify(queue_handle: State<'_, TaskQueueHandle>) -> Result<usize, String> {
+    let files = sonus_core::library::integrity::library_files().map_err(|e| e.to_string())?;
+    info!("开始校验曲库，共 {} 个文件", files.len());
+    for path in &files {
+        let task = Box::new(sonus_core::library::integrity::IntegrityCheckTask::new(path.clone()));
+        queue_handle.submit_task(task).await;
+    }
+    Ok(files.len())
+}
+
 /// Tauri命令：获取当前任务统计信息
 #[tauri::command]
 pub async fn get_task_stats(tracker: State<'_, TaskTracker>) -> Result<TaskStats, String> {
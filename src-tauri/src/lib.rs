@@ -13,10 +13,15 @@ pub fn run() {
             ipc::get_window_hwnd,
             // task queue commands
             ipc::start_directory_scan,
+            ipc::start_library_verify,
             ipc::get_task_stats,
             ipc::register_task_listener,
             // library commands
             ipc::get_all_songs,
+            ipc::get_integrity_results,
+            ipc::get_integrity_result,
+            ipc::get_library_folders,
+            ipc::remove_library_folder,
             // player commands
             ipc::play_to_playlist,
             ipc::play_from,
@@ -31,62 +36,126 @@ pub fn run() {
             ipc::insert_track_at,
             ipc::insert_track_after_current,
             ipc::add_track_to_end,
+            ipc::add_url_to_playlist,
             ipc::move_track,
             ipc::remove_track,
             ipc::clear_playlist,
             ipc::overwrite_playlist,
             ipc::set_play_mode,
             ipc::get_current_index,
-            ipc::set_and_play_index
+            ipc::set_and_play_index,
+            ipc::set_fade_in_out,
+            ipc::set_crossfade,
+            ipc::get_fade_settings,
+            ipc::set_loudness_balance,
+            ipc::get_loudness_balance,
+            ipc::get_equalizer,
+            ipc::set_equalizer_enabled,
+            ipc::set_equalizer_preamp,
+            ipc::set_equalizer_band,
+            ipc::set_equalizer_bands,
+            ipc::get_equalizer_presets,
+            ipc::apply_equalizer_preset,
+            ipc::save_equalizer_preset,
+            ipc::delete_equalizer_preset,
+            ipc::get_stereo_image,
+            ipc::set_balance,
+            ipc::set_mono_downmix,
+            ipc::set_crossfeed,
+            ipc::get_output_devices,
+            ipc::get_output_device,
+            ipc::set_output_device,
+            ipc::set_playback_rate,
+            ipc::set_preserve_pitch,
+            ipc::set_sleep_timer,
+            ipc::extend_sleep_timer,
+            ipc::cancel_sleep_timer,
+            ipc::get_sleep_timer,
+            ipc::set_ab_loop,
+            ipc::clear_ab_loop,
+            ipc::save_ab_loop,
+            ipc::get_saved_loops,
+            ipc::apply_saved_loop,
+            ipc::delete_saved_loop,
+            ipc::get_chapters,
+            ipc::next_chapter,
+            ipc::previous_chapter,
+            ipc::seek_to_chapter,
+            ipc::get_resume_positions,
+            ipc::clear_resume_position,
+            ipc::clear_resume_positions,
+            ipc::jump_to_resume_position,
+            ipc::get_resume_rules,
+            ipc::set_resume_rules,
+            ipc::set_audio_spectrum,
+            ipc::get_audio_spectrum
         ])
         .setup(|app| {
             // init app
             let app_handle = app.handle();
-            let app_handle_clone = app_handle.clone();
             app::init::init(&app_handle);
-            init_task_queue(app.handle().clone())?;
+
+            // core events are forwarded to the frontend
+            let events = EventBus::new();
+            ipc::events::forward_events(app_handle.clone(), &events);
+            init_task_queue(app, events.clone());
             
             // init player
-            let shared_state = core::player::state::new_shared_state();
-            let player_controller = new_shared_player_controller(shared_state, app.handle().clone())
+            let shared_state = sonus_core::player::state::new_shared_state();
+            let player_controller = new_shared_player_controller(shared_state, events.clone())
                 .expect("Failed to initialize player controller");
+            handle_player_events(&player_controller, &events);
             app.manage(player_controller.clone());
-            
-            // Listening track-ended event
-            app_handle.listen("track-ended", move |_| {
-                info!("track-ended event triggered");
-                let app_handle_async = app_handle_clone.clone();
-                tauri::async_runtime::spawn(async move {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-                    let state = app_handle_async.state::<SharedPlayerController>();
 
-                    match ipc::next_track(state) {
-                        Ok(Some(track)) => {
-                            info!("Auto-play next track: {:?}", track);
-                        }
-                        Ok(None) => {
-                            info!("No next track available, playlist ended.");
-                        }
-                        Err(e) => {
-                            error!("Failed to auto-play next track: {}", e);
-                        }
+            // restore last session
+            if let Err(e) = player_controller.lock().unwrap_or_else(|e| e.into_inner()).restore_session() {
+                error!("Failed to restore session: {}", e);
+            }
+
+            // drive the sleep timer
+            let sleep_controller = player_controller.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                    sleep_controller.lock().unwrap_or_else(|e| e.into_inner()).tick_sleep_timer();
+                }
+            });
+
+            // save session and resume position periodically while playing
+            let session_controller = player_controller.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
+                    let controller = session_controller.lock().unwrap_or_else(|e| e.into_inner());
+                    if controller.is_playing() {
+                        controller.save_session();
+                        controller.save_resume_position();
                     }
-                });
+                }
             });
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                if let Some(controller) = app_handle.try_state::<SharedPlayerController>() {
+                    let controller = controller.lock().unwrap_or_else(|e| e.into_inner());
+                    controller.save_session();
+                    controller.save_resume_position();
+                }
+            }
+        });
 }
 
 
 pub mod ipc;
 pub mod app;
-pub mod core;
 pub mod utils;
 
-use tauri::{Listener, Manager};
-use tracing::{error, info};
-use core::task_queue::tauri_integration::init_task_queue;
-use crate::core::controller::{new_shared_player_controller, PlayerController, SharedPlayerController};
+use tauri::Manager;
+use tracing::error;
+use app::task_queue::init_task_queue;
+use sonus_core::controller::{handle_player_events, new_shared_player_controller, SharedPlayerController};
+use sonus_core::events::EventBus;